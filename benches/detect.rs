@@ -0,0 +1,41 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use bar_dec::detect_barcode_regions_slice;
+
+const WIDTH: u32 = 1600;
+const HEIGHT: u32 = 1200;
+
+/// Builds a white page with a single barcode of pseudo-random 1-3 module bars.
+fn fixture_image() -> Vec<u8> {
+    let mut img = vec![255u8; (WIDTH * HEIGHT) as usize];
+    let (x0, x1, y0, y1, module) = (400, 1200, 300, 500, 3);
+
+    // Fixed LCG seed so every run benchmarks the same pattern
+    let mut seed = 12345u32;
+    let mut pattern = Vec::new();
+    let mut black = true;
+    while (pattern.len() as u32) < x1 - x0 {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        let bar_width = ((seed >> 16) % 3 + 1) * module;
+        let value = if black { 0 } else { 255 };
+        pattern.extend(std::iter::repeat_n(value, bar_width as usize));
+        black = !black;
+    }
+
+    for y in y0..y1 {
+        let row = (y * WIDTH) as usize;
+        img[row + x0 as usize..row + x1 as usize].copy_from_slice(&pattern[..(x1 - x0) as usize]);
+    }
+    img
+}
+
+fn bench_detect(c: &mut Criterion) {
+    let img = fixture_image();
+
+    c.bench_function("detect_barcode_regions_slice 1600x1200", |b| {
+        b.iter(|| detect_barcode_regions_slice(black_box(&img), WIDTH, HEIGHT, None).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_detect);
+criterion_main!(benches);
@@ -0,0 +1,186 @@
+use rustfft::FftPlanner;
+
+use crate::{
+    detect_horizontal, detect_oriented, image_view, BarcodeRegion, DetectError, DetectionConfig,
+    Orientation, OrientationConflict,
+};
+
+/// A reusable barcode detector.
+///
+/// Holds the detection settings together with an FFT planner, so scanning
+/// many images with one detector plans each FFT length only once instead of
+/// once per image. The free functions like `detect_barcode_regions_slice`
+/// build a throwaway detector for one-shot use.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{BarcodeDetector, Orientation};
+/// let mut detector = BarcodeDetector::builder()
+///     .threshold(40.0)
+///     .section_height(8)
+///     .orientation(Orientation::Both)
+///     .build();
+///
+/// let pages = vec![(vec![255; 800 * 600], 800, 600), (vec![255; 600 * 800], 600, 800)];
+/// for (pixels, width, height) in pages {
+///     let regions = detector.detect(&pixels, width, height)?;
+///     assert!(regions.is_empty());
+/// }
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+pub struct BarcodeDetector {
+    config: DetectionConfig,
+    orientation: Orientation,
+    planner: FftPlanner<f32>,
+}
+
+impl BarcodeDetector {
+    /// Creates a detector scanning for horizontal barcodes with `config`.
+    pub fn new(config: DetectionConfig) -> Self {
+        BarcodeDetector {
+            config,
+            orientation: Orientation::Horizontal,
+            planner: FftPlanner::new(),
+        }
+    }
+
+    /// Starts building a detector from the default settings.
+    pub fn builder() -> BarcodeDetectorBuilder {
+        BarcodeDetectorBuilder::default()
+    }
+
+    /// The settings the detector runs with.
+    pub fn config(&self) -> &DetectionConfig {
+        &self.config
+    }
+
+    /// Detects barcode-like regions in a borrowed grayscale buffer.
+    ///
+    /// The horizontal pass reuses the detector's FFT planner. Vertical and
+    /// combined orientations also scan the transposed image, where each pass
+    /// plans its own FFTs on the rayon pool.
+    ///
+    /// # Arguments
+    ///
+    /// * `img_data` - A slice of `u8` representing the grayscale image data.
+    /// * `width` - The width of the image.
+    /// * `height` - The height of the image.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `BarcodeRegion` containing detected regions,
+    /// `DetectError::InvalidConfig` if the detector's settings don't pass
+    /// `DetectionConfig::validate`, or `DetectError::DimensionMismatch` if
+    /// `img_data` doesn't hold `width * height` bytes.
+    pub fn detect(
+        &mut self,
+        img_data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<BarcodeRegion>, DetectError> {
+        self.config.validate()?;
+        match self.orientation {
+            Orientation::Horizontal => {
+                let img = image_view(img_data, width, height)?;
+                detect_horizontal(&img, &self.config, &mut self.planner)
+            }
+            orientation => detect_oriented(
+                img_data,
+                width,
+                height,
+                &self.config,
+                orientation,
+                OrientationConflict::KeepBoth,
+            ),
+        }
+    }
+}
+
+impl Default for BarcodeDetector {
+    fn default() -> Self {
+        BarcodeDetector::new(DetectionConfig::default())
+    }
+}
+
+/// Builds a `BarcodeDetector`; see `BarcodeDetector::builder`.
+///
+/// Setters cover the commonly tuned settings. For anything else, fill in the
+/// public fields of a `DetectionConfig` and pass it to `config`. Since
+/// `config` replaces every setting made so far, call it first and chain the
+/// setters after it.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{BarcodeDetector, DetectionConfig, Orientation};
+/// let detector = BarcodeDetector::builder()
+///     .config(DetectionConfig {
+///         auto_invert: true,
+///         ..DetectionConfig::default()
+///     })
+///     .threshold(40.0)
+///     .orientation(Orientation::Both)
+///     .build();
+///
+/// assert!(detector.config().auto_invert);
+/// assert_eq!(detector.config().threshold, 40.0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BarcodeDetectorBuilder {
+    config: DetectionConfig,
+    orientation: Orientation,
+}
+
+impl BarcodeDetectorBuilder {
+    /// Replaces every detection setting with `config`, including any made by
+    /// earlier setter calls; the orientation is kept.
+    pub fn config(mut self, config: DetectionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Minimum summed section magnitude for a section to pass.
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.config.threshold = threshold;
+        self
+    }
+
+    /// Height in pixels of each band of sections.
+    pub fn section_height(mut self, section_height: u32) -> Self {
+        self.config.section_height = section_height;
+        self
+    }
+
+    /// Number of consecutive passing sections a run needs to become a region.
+    pub fn consecutive_threshold(mut self, consecutive_threshold: usize) -> Self {
+        self.config.consecutive_threshold = consecutive_threshold;
+        self
+    }
+
+    /// Factor the image is reduced by before detection.
+    pub fn downscale(mut self, downscale: u32) -> Self {
+        self.config.downscale = downscale;
+        self
+    }
+
+    /// Resolution of the scan in dots per inch.
+    pub fn dpi(mut self, dpi: f32) -> Self {
+        self.config.dpi = dpi;
+        self
+    }
+
+    /// Which bar orientations to look for.
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Creates the detector.
+    pub fn build(self) -> BarcodeDetector {
+        BarcodeDetector {
+            orientation: self.orientation,
+            ..BarcodeDetector::new(self.config)
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+
+#[cfg(feature = "python")]
+use pyo3::exceptions::{PyIOError, PyValueError};
+#[cfg(feature = "python")]
+use pyo3::PyErr;
+
+/// A `DetectionConfig` field holds a value detection can't work with.
+///
+/// Returned by `DetectionConfig::validate`; detection wraps it in
+/// `DetectError::InvalidConfig`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Name of the offending field.
+    pub field: &'static str,
+    /// The range or form the field's value must have.
+    pub reason: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid config: {} {}", self.field, self.reason)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(feature = "python")]
+impl From<ConfigError> for PyErr {
+    fn from(err: ConfigError) -> Self {
+        PyValueError::new_err(err.to_string())
+    }
+}
+
+/// Errors that can occur while preparing an image for detection or running it.
+#[derive(Debug)]
+pub enum DetectError {
+    /// The image file could not be read.
+    Io(std::io::Error),
+    /// The TIFF container or one of its pages could not be decoded.
+    #[cfg(feature = "tiff")]
+    Tiff(tiff::TiffError),
+    /// The page uses a pixel format detection can't convert to luma.
+    UnsupportedColorType(String),
+    /// The pixel buffer length doesn't match `width * height` (times the channel count).
+    DimensionMismatch { expected: usize, got: usize },
+    /// The image has no pixels along at least one axis.
+    EmptyImage { width: u32, height: u32 },
+    /// The working image, after any downscale, has no pixels left to split into sections.
+    ImageTooSmall { width: u32, height: u32 },
+    /// The configured origin pushes region coordinates past `u32::MAX`.
+    OriginOutOfRange {
+        origin: (u32, u32),
+        width: u32,
+        height: u32,
+    },
+    /// The image would need more sections than `max_total_sections` allows.
+    TooManySections { sections: u64, limit: u64 },
+    /// A `DetectionConfig` field holds a value detection can't work with.
+    InvalidConfig(ConfigError),
+    /// Detection found fewer regions than `min_regions` requires.
+    TooFewRegions { found: usize, min: usize },
+    /// A band image could not be written to `debug_dump_dir`.
+    DebugDump(std::io::Error),
+    /// A region's end lies at or before its start on some axis.
+    InvalidRegion {
+        x_start: u32,
+        x_end: u32,
+        y_start: u32,
+        y_end: u32,
+    },
+    /// A Python-side failure, e.g. a keyword override naming an unknown field.
+    #[cfg(feature = "python")]
+    Python(PyErr),
+}
+
+impl fmt::Display for DetectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectError::Io(err) => write!(f, "failed to read image: {err}"),
+            #[cfg(feature = "tiff")]
+            DetectError::Tiff(err) => write!(f, "failed to decode TIFF: {err}"),
+            DetectError::UnsupportedColorType(color) => {
+                write!(f, "unsupported color type: {color}")
+            }
+            DetectError::DimensionMismatch { expected, got } => {
+                write!(f, "image buffer holds {got} bytes, expected {expected}")
+            }
+            DetectError::EmptyImage { width, height } => {
+                write!(
+                    f,
+                    "image is {width}x{height}; detection needs at least one pixel"
+                )
+            }
+            DetectError::ImageTooSmall { width, height } => write!(
+                f,
+                "working image is {width}x{height} after downscaling, too small to split into sections"
+            ),
+            DetectError::OriginOutOfRange {
+                origin: (x, y),
+                width,
+                height,
+            } => write!(
+                f,
+                "origin ({x}, {y}) plus image size {width}x{height} overflows u32 coordinates"
+            ),
+            DetectError::TooManySections { sections, limit } => write!(
+                f,
+                "image needs {sections} sections, more than the limit of {limit}"
+            ),
+            DetectError::InvalidConfig(err) => write!(f, "{err}"),
+            DetectError::TooFewRegions { found, min } => write!(
+                f,
+                "found {found} barcode regions, fewer than the required {min}"
+            ),
+            DetectError::DebugDump(err) => write!(f, "failed to write debug band image: {err}"),
+            DetectError::InvalidRegion {
+                x_start,
+                x_end,
+                y_start,
+                y_end,
+            } => write!(
+                f,
+                "region x {x_start}..{x_end}, y {y_start}..{y_end} is empty or inverted"
+            ),
+            #[cfg(feature = "python")]
+            DetectError::Python(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for DetectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DetectError::Io(err) => Some(err),
+            #[cfg(feature = "tiff")]
+            DetectError::Tiff(err) => Some(err),
+            DetectError::DebugDump(err) => Some(err),
+            DetectError::InvalidConfig(err) => Some(err),
+            #[cfg(feature = "python")]
+            DetectError::Python(err) => Some(err),
+            DetectError::UnsupportedColorType(_)
+            | DetectError::DimensionMismatch { .. }
+            | DetectError::EmptyImage { .. }
+            | DetectError::ImageTooSmall { .. }
+            | DetectError::OriginOutOfRange { .. }
+            | DetectError::TooManySections { .. }
+            | DetectError::TooFewRegions { .. }
+            | DetectError::InvalidRegion { .. } => None,
+        }
+    }
+}
+
+impl From<ConfigError> for DetectError {
+    fn from(err: ConfigError) -> Self {
+        DetectError::InvalidConfig(err)
+    }
+}
+
+impl From<std::io::Error> for DetectError {
+    fn from(err: std::io::Error) -> Self {
+        DetectError::Io(err)
+    }
+}
+
+#[cfg(feature = "tiff")]
+impl From<tiff::TiffError> for DetectError {
+    fn from(err: tiff::TiffError) -> Self {
+        DetectError::Tiff(err)
+    }
+}
+
+#[cfg(feature = "python")]
+impl From<PyErr> for DetectError {
+    fn from(err: PyErr) -> Self {
+        DetectError::Python(err)
+    }
+}
+
+#[cfg(feature = "python")]
+impl From<DetectError> for PyErr {
+    fn from(err: DetectError) -> Self {
+        match err {
+            DetectError::Python(err) => err,
+            DetectError::Io(_) | DetectError::DebugDump(_) => PyIOError::new_err(err.to_string()),
+            _ => PyValueError::new_err(err.to_string()),
+        }
+    }
+}
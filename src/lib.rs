@@ -1,192 +1,5036 @@
-use image::{ImageBuffer, Luma};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+use imageproc::drawing::draw_hollow_rect_mut;
+#[cfg(feature = "python")]
+use numpy::PyReadonlyArray2;
+#[cfg(feature = "python")]
+use pyo3::exceptions::PyValueError;
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
+#[cfg(feature = "python")]
+use pyo3::types::{PyDict, PyString};
+#[cfg(feature = "python")]
 use pyo3::wrap_pyfunction;
+use rayon::prelude::*;
 use rustfft::num_complex::Complex;
-use rustfft::FftPlanner;
+use rustfft::num_traits::Float;
+use rustfft::{Fft, FftNum, FftPlanner};
+
+mod detector;
+mod error;
+mod pixel_source;
+mod region_iter;
+mod symbology;
+#[cfg(feature = "tiff")]
+mod tiff_pages;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(test)]
+mod tests;
+
+pub use detector::{BarcodeDetector, BarcodeDetectorBuilder};
+pub use error::{ConfigError, DetectError};
+use pixel_source::Luma16;
+pub use pixel_source::PixelSource;
+pub use region_iter::{detect_regions_iter, RegionIter};
+pub use symbology::{guess_symbology, region_profile, RegionProfile, Symbology};
+#[cfg(feature = "tiff")]
+pub use tiff_pages::detect_tiff_pages;
+#[cfg(all(feature = "python", feature = "tiff"))]
+use tiff_pages::py_detect_tiff_pages;
 
 /// Represents a region in the image that is identified as a barcode.
-#[pyclass]
-#[derive(Debug, Clone)]
-struct BarcodeRegion {
-    #[pyo3(get)]
-    x_start: u32,
-    #[pyo3(get)]
-    x_end: u32,
-    #[pyo3(get)]
-    y_start: u32,
-    #[pyo3(get)]
-    y_end: u32,
+///
+/// With the `serde` feature regions (de)serialize with their field names;
+/// missing fields take their default, so `{"x_start": .., "x_end": ..,
+/// "y_start": .., "y_end": ..}` is enough to read one back.
+///
+/// The fields are public so results can be read directly; assigning them
+/// by hand skips the span check of `BarcodeRegion::new`.
+#[cfg_attr(feature = "python", pyclass(get_all))]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BarcodeRegion {
+    /// Left edge of the region in pixels.
+    pub x_start: u32,
+    /// Right edge of the region in pixels, exclusive.
+    pub x_end: u32,
+    /// Top edge of the region in pixels.
+    pub y_start: u32,
+    /// Bottom edge of the region in pixels, exclusive.
+    pub y_end: u32,
+    /// Best-effort symbology guess from the region's bar/space structure.
+    pub symbology_guess: Option<Symbology>,
+    /// Whether the region touches the edge of the scanned area and is likely cut off.
+    pub clipped: bool,
+    /// Section magnitude of the region relative to the strongest section in the image.
+    pub confidence: f32,
+    /// Mean summed FFT magnitude of the region's sections, before normalization.
+    pub raw_magnitude: f32,
+    /// Row inside the region whose scanline has the strongest frequency response,
+    /// or the center row when `DetectionConfig.best_scanline` is off. For
+    /// `vertical` regions this is a column instead.
+    pub best_scanline_y: u32,
+    /// Narrow bar width in pixels, estimated from the peak FFT frequency of the
+    /// region's sections; `0.0` when no section had a usable peak.
+    pub estimated_module_width: f32,
+    /// Number of dark bars crossed by the region's center row; `0` when the
+    /// row shows fewer than two bars or the region came from an edge map.
+    pub bar_count: u32,
+    /// Estimated tilt of the bars in degrees, positive when their tops lean
+    /// right (the label is turned clockwise); `0.0` unless
+    /// `DetectionConfig.estimate_skew` is on. The box itself stays axis-aligned.
+    /// For `vertical` regions, whose bars run across, positive still means the
+    /// label is turned clockwise, i.e. the bars' right ends lean down.
+    pub skew_degrees: f32,
+    /// Whether the region was found by the vertical pass (`Orientation::Vertical`
+    /// or `Orientation::Both`), i.e. its bars run horizontally.
+    pub vertical: bool,
+    /// The bounds as fractions of the image, see `BarcodeRegion::normalized`;
+    /// `None` unless `DetectionConfig.normalize` is on.
+    pub normalized_bounds: Option<(f32, f32, f32, f32)>,
+}
+
+impl BarcodeRegion {
+    /// Creates a region from its bounds, checking that they describe a real box.
+    ///
+    /// Both spans must be non-empty (`x_start < x_end`, `y_start < y_end`);
+    /// everything but the bounds takes its default value.
+    ///
+    /// # Arguments
+    ///
+    /// * `x_start`, `x_end` - Horizontal span of the region, end exclusive.
+    /// * `y_start`, `y_end` - Vertical span of the region, end exclusive.
+    ///
+    /// # Returns
+    ///
+    /// The region, or `DetectError::InvalidRegion` for an empty or inverted span.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bar_dec::BarcodeRegion;
+    /// let region = BarcodeRegion::new(100, 200, 50, 80)?;
+    /// assert_eq!((region.x_start, region.x_end), (100, 200));
+    /// assert!(BarcodeRegion::new(200, 100, 50, 80).is_err());
+    /// # Ok::<(), bar_dec::DetectError>(())
+    /// ```
+    pub fn new(x_start: u32, x_end: u32, y_start: u32, y_end: u32) -> Result<Self, DetectError> {
+        if x_start >= x_end || y_start >= y_end {
+            return Err(DetectError::InvalidRegion {
+                x_start,
+                x_end,
+                y_start,
+                y_end,
+            });
+        }
+
+        Ok(BarcodeRegion {
+            x_start,
+            x_end,
+            y_start,
+            y_end,
+            ..Default::default()
+        })
+    }
+
+    /// Grows the region to at least `min_w` x `min_h`, keeping it centered.
+    ///
+    /// The region is expanded symmetrically around its center and clamped to
+    /// the image bounds. When an image edge stops growth on one side, the
+    /// remaining growth goes to the other side instead, so the minimum is
+    /// reached whenever the image is large enough. Sides already at or above
+    /// the minimum are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_w` - The minimum width of the region.
+    /// * `min_h` - The minimum height of the region.
+    /// * `width` - The width of the image.
+    /// * `height` - The height of the image.
+    ///
+    /// # Returns
+    ///
+    /// A new `BarcodeRegion` with the grown bounds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bar_dec::BarcodeRegion;
+    /// let region = BarcodeRegion { x_start: 95, x_end: 105, y_start: 95, y_end: 105, ..Default::default() };
+    /// let grown = region.ensure_min_size(50, 50, 200, 200);
+    /// assert_eq!((grown.x_start, grown.x_end, grown.y_start, grown.y_end), (75, 125, 75, 125));
+    /// ```
+    pub fn ensure_min_size(
+        &self,
+        min_w: u32,
+        min_h: u32,
+        width: u32,
+        height: u32,
+    ) -> BarcodeRegion {
+        let (x_start, x_end) = grow_span(self.x_start, self.x_end, min_w, width);
+        let (y_start, y_end) = grow_span(self.y_start, self.y_end, min_h, height);
+
+        BarcodeRegion {
+            x_start,
+            x_end,
+            y_start,
+            y_end,
+            ..self.clone()
+        }
+    }
+
+    /// Builds a region covering `rect`.
+    ///
+    /// The end coordinates saturate at `u32::MAX` instead of wrapping, so an
+    /// oversized rectangle is clamped rather than turned into a bogus region.
+    ///
+    /// # Arguments
+    ///
+    /// * `rect` - The rectangle to convert.
+    ///
+    /// # Returns
+    ///
+    /// A new `BarcodeRegion` with default values for everything but its bounds.
+    pub fn from_rect(rect: Rect) -> BarcodeRegion {
+        BarcodeRegion {
+            x_start: rect.x,
+            x_end: rect.x.saturating_add(rect.width),
+            y_start: rect.y,
+            y_end: rect.y.saturating_add(rect.height),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the four corners of the region, for decoders that take corner points.
+    ///
+    /// Corners are in clockwise order starting top-left: TL, TR, BR, BL. They
+    /// use the region's own bounds, so the right and bottom corners lie on the
+    /// exclusive `x_end` / `y_end` edges.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bar_dec::BarcodeRegion;
+    /// let region = BarcodeRegion { x_start: 10, x_end: 50, y_start: 20, y_end: 30, ..Default::default() };
+    /// assert_eq!(region.corners(), [(10, 20), (50, 20), (50, 30), (10, 30)]);
+    /// ```
+    pub fn corners(&self) -> [(u32, u32); 4] {
+        [
+            (self.x_start, self.y_start),
+            (self.x_end, self.y_start),
+            (self.x_end, self.y_end),
+            (self.x_start, self.y_end),
+        ]
+    }
+}
+
+/// An axis-aligned rectangle in `x, y, width, height` form.
+///
+/// This matches the argument order of `image::imageops::crop_imm` and the
+/// rectangle types of the `image`/`imageproc` ecosystem.
+#[cfg_attr(feature = "python", pyclass(eq, get_all, set_all))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Rect {
+    #[new]
+    fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+#[cfg_attr(feature = "python", pymethods)]
+impl BarcodeRegion {
+    /// Python constructor; see `BarcodeRegion::new`.
+    #[cfg(feature = "python")]
+    #[new]
+    fn py_new(x_start: u32, x_end: u32, y_start: u32, y_end: u32) -> Result<Self, DetectError> {
+        BarcodeRegion::new(x_start, x_end, y_start, y_end)
+    }
+
+    /// Python binding of `BarcodeRegion::from_rect`.
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "from_rect")]
+    fn py_from_rect(rect: Rect) -> BarcodeRegion {
+        BarcodeRegion::from_rect(rect)
+    }
+
+    /// Python binding of `BarcodeRegion::ensure_min_size`.
+    #[cfg(feature = "python")]
+    #[pyo3(name = "ensure_min_size")]
+    fn py_ensure_min_size(&self, min_w: u32, min_h: u32, width: u32, height: u32) -> BarcodeRegion {
+        self.ensure_min_size(min_w, min_h, width, height)
+    }
+
+    /// Converts the region to an `x, y, width, height` rectangle.
+    ///
+    /// An inverted span (end before start) yields a zero-sized side.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bar_dec::BarcodeRegion;
+    /// use image::{imageops, GenericImageView, GrayImage};
+    ///
+    /// let img = GrayImage::new(800, 600);
+    /// let region = BarcodeRegion { x_start: 100, x_end: 300, y_start: 50, y_end: 80, ..Default::default() };
+    /// let rect = region.to_rect();
+    /// let crop = imageops::crop_imm(&img, rect.x, rect.y, rect.width, rect.height);
+    /// assert_eq!(crop.dimensions(), (200, 30));
+    /// ```
+    pub fn to_rect(&self) -> Rect {
+        Rect {
+            x: self.x_start,
+            y: self.y_start,
+            width: self.x_end.saturating_sub(self.x_start),
+            height: self.y_end.saturating_sub(self.y_start),
+        }
+    }
+
+    /// Returns the bounds as fractions of the image size, for overlaying the
+    /// region on a resized copy of the image.
+    ///
+    /// Every coordinate is clamped to `0.0..=1.0`, so a region ending on the
+    /// right or bottom edge maps to exactly `1.0`.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - The width of the image the region's coordinates refer to.
+    /// * `height` - The height of the image the region's coordinates refer to.
+    ///
+    /// # Returns
+    ///
+    /// `(x_start, x_end, y_start, y_end)`, each in `0.0..=1.0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bar_dec::BarcodeRegion;
+    /// let region = BarcodeRegion { x_start: 200, x_end: 800, y_start: 150, y_end: 600, ..Default::default() };
+    /// assert_eq!(region.normalized(800, 600), (0.25, 1.0, 0.25, 1.0));
+    /// ```
+    pub fn normalized(&self, width: u32, height: u32) -> (f32, f32, f32, f32) {
+        let fraction = |value: u32, size: u32| (value as f32 / size.max(1) as f32).min(1.0);
+        (
+            fraction(self.x_start, width),
+            fraction(self.x_end, width),
+            fraction(self.y_start, height),
+            fraction(self.y_end, height),
+        )
+    }
+}
+
+/// Grows the span `start..end` to at least `min_len`, centered and clamped to `0..limit`.
+fn grow_span(start: u32, end: u32, min_len: u32, limit: u32) -> (u32, u32) {
+    let end = end.min(limit);
+    let start = start.min(end);
+    let target = min_len.min(limit);
+    if end - start >= target {
+        return (start, end);
+    }
+
+    // Split the growth evenly, then shift the span back inside the image
+    let new_start = start.saturating_sub((target - (end - start)) / 2);
+    let new_end = new_start + target;
+    if new_end > limit {
+        (limit - target, limit)
+    } else {
+        (new_start, new_end)
+    }
 }
 
 const VERTICAL_SECTIONS: u32 = 60;
 const HORIZONTAL_SECTIONS: u32 = 100;
 const SECTION_HEIGHT: u32 = 5;
-const THRESHOLD: f32 = 50.0;
+/// Half the v1 value of `50.0`, which was tuned for two-sided sums; see `SpectrumSum`.
+const THRESHOLD: f32 = 25.0;
 const CONSECUTIVE_THRESHOLD: usize = 5;
 const MAX_WHITE_BLACK_WIDTH: usize = 10;
+const CLIP_MARGIN: u32 = 2;
+const VERIFY_PEAK_RATIO: f32 = 4.0;
+/// Scan resolution the `adjust_regions` pixel offsets below are measured at.
+const REFERENCE_DPI: f32 = 300.0;
+/// Pixels `adjust_regions` strips off each end of a region for the guard bars.
+const GUARD_BAR_PX: u32 = 25;
+/// Gap between the bars and the text line below them, in pixels.
+const TEXT_GAP: u32 = 4;
+/// Height of the text line below a barcode, in pixels.
+const TEXT_HEIGHT: u32 = 46;
+/// Outline color `draw_regions` marks regions with.
+const REGION_OUTLINE: Rgb<u8> = Rgb([255, 0, 0]);
+/// Column index `assign_columns` gives regions outside every column.
+const NO_COLUMN: usize = usize::MAX;
+/// Fraction of dark pixels above which `classify_page` treats a page as inked.
+const INK_COVERAGE: f32 = 0.001;
+/// Intersection over union above which `detect_diff` treats two regions as the same barcode.
+const DIFF_IOU: f32 = 0.5;
+/// Intersection over union above which `dedupe_regions` merges two final regions.
+const DEDUPE_IOU: f32 = 0.9;
+/// Expected number of chance runs per image `adaptive_run_cap` tolerates.
+const FALSE_RUN_RATE: f64 = 0.01;
+/// Fraction of the peak bin a bin must reach to count toward `min_active_bins`.
+const ACTIVE_BIN_RATIO: f32 = 0.25;
+/// Luma step between neighbouring pixels that `SignalType::GradientX` counts as an edge.
+const GRADIENT_EDGE_STEP: u8 = 24;
+/// Average width of a bar or space in modules, used to turn the peak FFT
+/// period (one bar plus one space) into a module width.
+const ELEMENT_MODULES: f32 = 2.0;
+/// Largest bar tilt `estimate_skew` tries, in degrees either way.
+const SKEW_MAX_DEGREES: f32 = 5.0;
+/// Step between the tilts `estimate_skew` tries, in degrees.
+const SKEW_STEP_DEGREES: f32 = 1.0;
+/// 64-bit FNV-1a parameters used by `region_content_hash`.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// How `merge_group` combines the confidences of the regions it merges.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfidenceMerge {
+    /// Average confidence of the merged regions.
+    #[default]
+    Mean,
+    /// Confidence of the strongest merged region.
+    Max,
+    /// Sum of the confidences; may exceed 1.0.
+    Sum,
+    /// Average weighted by each region's raw magnitude, so a strong region
+    /// outweighs the faint fragments merged into it.
+    MagnitudeWeighted,
+}
+
+impl ConfidenceMerge {
+    /// Combines the score `value` of each region in `group` into one.
+    fn combine(self, group: &[BarcodeRegion], value: impl Fn(&BarcodeRegion) -> f32) -> f32 {
+        let values = group.iter().map(&value);
+        match self {
+            ConfidenceMerge::Mean => values.sum::<f32>() / group.len() as f32,
+            ConfidenceMerge::Max => values.fold(0.0, f32::max),
+            ConfidenceMerge::Sum => values.sum(),
+            ConfidenceMerge::MagnitudeWeighted => {
+                let total_weight: f32 = group.iter().map(|r| r.raw_magnitude).sum();
+                if total_weight > 0.0 {
+                    group
+                        .iter()
+                        .map(|r| value(r) * r.raw_magnitude)
+                        .sum::<f32>()
+                        / total_weight
+                } else {
+                    ConfidenceMerge::Mean.combine(group, value)
+                }
+            }
+        }
+    }
+}
+
+/// What the pixel values fed into the frequency analysis represent.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignalType {
+    /// Raw luma, binarized before the FFT.
+    #[default]
+    Luma,
+    /// Edge magnitudes from an upstream edge detector, used as-is.
+    Edges,
+    /// Raw luma, differentiated along each line (`|dI/dx|`) so bar edges
+    /// dominate and slow shading that defeats binarization drops out.
+    GradientX,
+}
+
+/// What to do with the rows below the last full band when the image height
+/// isn't a multiple of the band height.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingBand {
+    /// Leave the partial band unscanned.
+    #[default]
+    Ignore,
+    /// Scan the partial band, sampling its rows like a full band.
+    ScanClamped,
+}
+
+/// What to do with the columns right of the last full section when the image
+/// width isn't a multiple of the section count.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingColumns {
+    /// Leave the leftover columns unscanned, as v1 did.
+    Ignore,
+    /// Widen the last section's line to the right edge. Its magnitude is
+    /// rescaled to the width of the other sections, so `threshold` applies
+    /// to it unchanged.
+    #[default]
+    WidenLast,
+}
+
+/// Which bins of a section's spectrum are summed into its magnitude.
+///
+/// The spectrum of a real signal is symmetric: bin `N - k` mirrors bin `k`.
+/// A one-sided sum counts each frequency once, so it comes out at roughly
+/// half the two-sided sum, and thresholds tuned for one need halving (or
+/// doubling) to carry over to the other.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpectrumSum {
+    /// Bins `0..=N/2`, each frequency once.
+    #[default]
+    OneSided,
+    /// All `N` bins, counting every frequency but DC and Nyquist twice; the
+    /// v1 behavior, kept for thresholds tuned against it.
+    TwoSided,
+}
+
+/// How the row each band is scanned along is chosen.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RowPick {
+    /// The row at `line_offset_ratio` of the band, the middle by default.
+    #[default]
+    Center,
+    /// The row with the highest pixel variance, i.e. the sharpest contrast.
+    MaxVariance,
+    /// The darkest row, for faint prints where ink coverage varies by row.
+    MinMean,
+}
+
+/// How a luma line is split into black and white before the FFT.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Binarization {
+    /// A fixed cutoff of 128.
+    #[default]
+    Fixed,
+    /// A per-line cutoff picked by Otsu's method, for under- or over-exposed
+    /// scans where the bars and spaces fall on the same side of 128.
+    Otsu,
+}
+
+/// Window applied to each section line before its FFT.
+///
+/// A line cut out of a longer pattern starts and ends mid-bar, which the
+/// FFT sees as a jump and smears over every bin. Tapering the ends first
+/// concentrates a periodic pattern's energy in its peak bins.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FftWindow {
+    /// The line as is, i.e. a rectangular window.
+    #[default]
+    None,
+    /// `0.5 - 0.5 cos(2πn / (N - 1))`, tapering to zero at both ends.
+    Hann,
+    /// `0.54 - 0.46 cos(2πn / (N - 1))`, with a lower first sidelobe than Hann.
+    Hamming,
+}
+
+/// Where `detect_character_regions` puts each box relative to its barcode.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CharacterBox {
+    /// On the human-readable text line printed below the bars; the box no
+    /// longer overlaps the bars themselves.
+    #[default]
+    TextBelow,
+    /// Over the bars, keeping the detected y-range; only the guard bars are
+    /// stripped, e.g. for labels printing the text above or inside the bars.
+    Bars,
+}
+
+/// Floating-point precision the section FFTs run in.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FftPrecision {
+    /// `f32`, which is plenty for the default section widths.
+    #[default]
+    Single,
+    /// `f64`, for very wide sections whose magnitudes sum thousands of bins
+    /// and would otherwise pick up rounding noise.
+    Double,
+}
+
+/// How the magnitudes of several scan rows of a section are combined.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanLineMerge {
+    /// Average over the rows, so one clean row among bad ones counts partially.
+    #[default]
+    Mean,
+    /// Strongest row, so one clean row is enough.
+    Max,
+}
+
+/// How passing sections are grouped into regions.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// Merge runs that share a band, then bands that touch vertically.
+    #[default]
+    Rows,
+    /// Label 8-connected components of the pass grid and emit one region per
+    /// component, which keeps ragged or skewed barcodes in one piece.
+    ConnectedComponents,
+}
+
+/// What `classify_page` found on a page.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageClass {
+    /// No barcode and practically no ink.
+    Blank,
+    /// Ink but no barcode.
+    TextOnly,
+    /// Barcodes (and the digits printed under them) but no other ink.
+    BarcodePresent,
+    /// Barcodes plus other ink elsewhere on the page.
+    Mixed,
+}
+
+/// Which bar orientations `detect_barcode_regions` looks for.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Orientation {
+    /// Vertical bars, found by scanning rows.
+    #[default]
+    Horizontal,
+    /// Horizontal bars (a barcode rotated by 90 degrees), found by scanning columns.
+    Vertical,
+    /// Both of the above; the passes run concurrently.
+    Both,
+}
+
+/// What `detect_any_orientation` does when a horizontal and a vertical region overlap.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrientationConflict {
+    /// Report both regions.
+    #[default]
+    KeepBoth,
+    /// Report only the region with the higher `raw_magnitude`.
+    KeepStronger,
+    /// Report one region covering both, carrying the stronger one's details.
+    Merge,
+}
+
+/// Optional settings that tune how detection runs.
+///
+/// `DetectionConfig::default()` reproduces the behavior of calling the
+/// detection functions without a config. From Rust, override single fields
+/// with struct update syntax; from Python, pass them as keyword arguments.
+///
+/// # Example
+///
+/// ```
+/// use bar_dec::DetectionConfig;
+///
+/// // A high-resolution scan: taller bands and a stricter threshold
+/// let config = DetectionConfig {
+///     section_height: 10,
+///     threshold: 80.0,
+///     ..Default::default()
+/// };
+/// assert_eq!(config.downscale, 1);
+/// ```
+#[cfg_attr(feature = "python", pyclass(get_all, set_all))]
+#[derive(Debug, Clone)]
+pub struct DetectionConfig {
+    /// Box-downsampling factor applied to the image before detection.
+    /// Output coordinates stay in full resolution. `1` disables downscaling.
+    pub downscale: u32,
+    /// Re-check each region with a focused FFT on its crop and drop regions
+    /// without a strong periodic peak.
+    pub verify: bool,
+    /// Estimate the tilt of each region's bars into `BarcodeRegion.skew_degrees`.
+    /// Each region costs one extra FFT per tried angle, so this is off by default.
+    pub estimate_skew: bool,
+    /// Also report each region's bounds as fractions of the image size in
+    /// `BarcodeRegion.normalized_bounds`, measured against the image passed
+    /// in (the whole image with a ROI) and before `origin` is added.
+    pub normalize: bool,
+    /// How confidences are combined when regions are merged.
+    pub confidence_merge: ConfidenceMerge,
+    /// What the input pixels represent.
+    pub signal: SignalType,
+    /// Whether the Nyquist bin of even-length sections counts toward the magnitude.
+    pub count_nyquist: bool,
+    /// Whether the mirrored upper half of the spectrum is summed too.
+    /// `SpectrumSum.TwoSided` roughly doubles every magnitude, so it needs
+    /// about twice the `threshold`.
+    pub spectrum_sum: SpectrumSum,
+    /// Whether the partial band at the bottom of the image is scanned.
+    pub trailing_band: TrailingBand,
+    /// Whether the columns left over by dividing the width into sections are scanned.
+    pub trailing_columns: TrailingColumns,
+    /// Number of sections across the width. `None` picks it from the image
+    /// orientation; setting it bypasses that guess.
+    pub sections_x: Option<u32>,
+    /// Height of each band in pixels, which sets the number of sections down the image.
+    pub section_height: u32,
+    /// `(x, y)` offset added to every output region, e.g. the position of a
+    /// cropped sub-image within the original page.
+    pub origin: (u32, u32),
+    /// Scan every row of each region to find its strongest scanline instead
+    /// of reporting the center row.
+    pub best_scanline: bool,
+    /// How passing sections are grouped into regions.
+    pub merge_strategy: MergeStrategy,
+    /// Leading and trailing section columns whose mean magnitude is below this
+    /// fraction of the region's mean are trimmed off. `0.0` disables trimming.
+    pub trim_ratio: f32,
+    /// Where within each band the scan line is taken, from `0.0` (top row) to
+    /// `1.0` (bottom row). Values outside that range are clamped.
+    pub line_offset_ratio: f32,
+    /// `(sections, bands)` kernel of a morphological open/close applied to the
+    /// pass grid before regions are extracted. `None` disables smoothing.
+    pub smoothing_kernel: Option<(u32, u32)>,
+    /// How the scan row of each band is chosen.
+    pub row_pick: RowPick,
+    /// Sample the image margins and invert the image first when the
+    /// background is dark. Light-on-dark barcodes are detected either way,
+    /// since the spectrum ignores polarity; inverting only corrects the
+    /// per-region measurements that assume dark bars, like `bar_count` and
+    /// `symbology_guess`. Only applies to `SignalType.Luma`.
+    pub auto_invert: bool,
+    /// Upper bound on the number of sections (and so FFTs) a single image may
+    /// need; larger layouts fail with an error before any work is done.
+    /// `None` disables the cap.
+    pub max_total_sections: Option<u64>,
+    /// Stop scanning once this many regions are confirmed, top to bottom.
+    /// Regions are then merged band by band as in `RegionIter`, which skips
+    /// `smoothing_kernel`, `adaptive_run_cap`, `merge_iou`, `trim_ratio`,
+    /// `dedupe` and `debug_dump_dir`, leaves `estimated_module_width` at
+    /// `0.0` and rates `confidence` against the bands scanned so far, so
+    /// results can differ from a full scan. Only single-pass horizontal
+    /// detection stops early, and only with `MergeStrategy.Rows`,
+    /// `merge_horizontal`, no `max_vertical_gap` and neither `dual_polarity`
+    /// nor `auto_invert`; otherwise, and for other orientations, tiled
+    /// detection, `detect_with_meta` and `detect_with_diagnostics`, the whole
+    /// image is scanned and the first regions top to bottom are kept.
+    /// `None` scans the whole image.
+    pub max_regions: Option<usize>,
+    /// Fewest regions a detection must find; fewer fail with
+    /// `DetectError::TooFewRegions`. `0` accepts an empty result.
+    pub min_regions: usize,
+    /// Merge runs that share a band (`MergeStrategy.Rows` only).
+    pub merge_horizontal: bool,
+    /// Merge regions of vertically touching bands (`MergeStrategy.Rows` only).
+    pub merge_vertical: bool,
+    /// Tallest stretch, in pixels, of bands with no passing section under a
+    /// region that the vertical merge still bridges. Runs triggered elsewhere
+    /// in those bands no longer chain two stacked barcodes into one box.
+    /// `None` merges any touching bands, as before.
+    pub max_vertical_gap: Option<u32>,
+    /// Merge any two regions whose intersection over union exceeds this
+    /// fraction, whatever their y-ranges, so a barcode straddling slightly
+    /// different bands ends up in one box. Runs after the band merges
+    /// (`MergeStrategy.Rows` only). `None` disables the overlap merge.
+    pub merge_iou: Option<f32>,
+    /// Drop regions lying inside another and merge boxes overlapping by more
+    /// than `DEDUPE_IOU` once all other merges are done, so each barcode is
+    /// reported once. Off in `DetectionConfig::pinned()`, which predates it.
+    pub dedupe: bool,
+    /// Pad each section line to the next power of two before the FFT, which
+    /// keeps FFT cost predictable for any section width. The magnitude is
+    /// rescaled to the unpadded bin count so thresholds stay comparable.
+    pub pad_to_power_of_two: bool,
+    /// Directory to write every scanned band to as `band_NNNN.png`, for
+    /// offline inspection of what the FFT saw. Bands are taken from the
+    /// working image, i.e. after any downscale. `None` disables dumping.
+    pub debug_dump_dir: Option<PathBuf>,
+    /// Run detection on both the image and its inverse and union the results.
+    /// This finds no region a single pass misses: the spectrum ignores
+    /// polarity, so both passes find the same sections. The inverted pass
+    /// only gives light-on-dark regions on a mixed page the right bar
+    /// polarity for `bar_count` and `symbology_guess`, at twice the cost.
+    /// Supersedes `auto_invert`. Only applies to `SignalType.Luma`.
+    pub dual_polarity: bool,
+    /// Minimum number of distinct frequency bins a section's spectrum must
+    /// spread its energy over, counting bins of at least `ACTIVE_BIN_RATIO`
+    /// of the peak. Rejects single-tone patterns like screens, whose energy
+    /// sits in one bin where barcodes spread it over many. `0` disables the check.
+    pub min_active_bins: u32,
+    /// Largest share of a section's spectral energy (squared amplitudes of
+    /// bins `1..=N/2`) its strongest bin may hold. A gradient or a single
+    /// thick line puts nearly all of it into one low bin without being
+    /// periodic. `1.0` disables the check.
+    pub max_peak_share: f32,
+    /// Smallest share of a section's spectral energy its strongest bin must
+    /// hold; texture and noise spread their energy evenly and stay below it.
+    /// `0.0` disables the check.
+    pub min_peak_share: f32,
+    /// Leave the DC bin out of the section magnitude. Turn off for inputs that
+    /// are already zero-mean, where bin 0 holds real low-frequency content.
+    pub remove_dc: bool,
+    /// Minimum summed section magnitude for a section to pass, at full resolution.
+    /// The scale depends on `spectrum_sum`: a two-sided sum needs about twice
+    /// the one-sided value.
+    pub threshold: f32,
+    /// Number of consecutive passing sections a run needs to become a region.
+    pub consecutive_threshold: usize,
+    /// Longest run of a single color, in pixels, a scan line may contain
+    /// before the prefilter rejects it.
+    pub max_white_black_width: usize,
+    /// Sections across the width of portrait (or square) images.
+    pub vertical_sections: u32,
+    /// Sections across the width of landscape images.
+    pub horizontal_sections: u32,
+    /// Derive the required run length from how many sections pass on the
+    /// image instead of using `consecutive_threshold`: the shortest run noise
+    /// would produce by chance less than `FALSE_RUN_RATE` times per image,
+    /// capped at this value. `None` keeps the fixed run length.
+    pub adaptive_run_cap: Option<usize>,
+    /// Number of evenly spaced rows analyzed per band. `1` analyzes the single
+    /// row chosen by `row_pick`; more rows make a smudge or glare on one row
+    /// less likely to hide a barcode, at a proportional cost.
+    pub scan_lines: u32,
+    /// How the magnitudes of a section's rows are combined when `scan_lines > 1`.
+    pub scan_line_merge: ScanLineMerge,
+    /// Resolution of the scan in dots per inch. `adjust_regions` scales its
+    /// guard bar and text line offsets by `dpi / 300`, so they cover the same
+    /// physical distance at any resolution; the default of 300 keeps the
+    /// offsets at their reference pixel sizes.
+    pub dpi: f32,
+    /// How luma lines are binarized when `signal` is `SignalType::Luma`.
+    pub binarization: Binarization,
+    /// Precision of the section FFTs and their magnitude sums. Magnitudes come
+    /// out in the same units either way, so `threshold` applies unchanged.
+    pub fft_precision: FftPrecision,
+    /// Window applied to each section line before its FFT. Windows scale the
+    /// line down (Hann by about half), so `threshold` usually needs lowering
+    /// along with it.
+    pub fft_window: FftWindow,
+    /// Where `detect_character_regions` places its boxes: on the text line
+    /// below each barcode, or over the bars.
+    pub character_box: CharacterBox,
+    /// Lowest peak frequency, in cycles per pixel, a section may have. The
+    /// peak is the strongest non-DC bin of the section's spectrum; sections
+    /// whose peak lies outside `min_freq..=max_freq` are rejected, which
+    /// drops textures much finer or coarser than barcode bars.
+    pub min_freq: f32,
+    /// Highest peak frequency, in cycles per pixel, a section may have; `0.5`
+    /// (the Nyquist frequency) accepts every peak.
+    pub max_freq: f32,
+}
+
+impl Default for DetectionConfig {
+    fn default() -> Self {
+        DetectionConfig {
+            downscale: 1,
+            verify: false,
+            estimate_skew: false,
+            normalize: false,
+            confidence_merge: ConfidenceMerge::Mean,
+            signal: SignalType::Luma,
+            count_nyquist: true,
+            spectrum_sum: SpectrumSum::OneSided,
+            trailing_band: TrailingBand::Ignore,
+            trailing_columns: TrailingColumns::WidenLast,
+            sections_x: None,
+            section_height: SECTION_HEIGHT,
+            origin: (0, 0),
+            best_scanline: false,
+            merge_strategy: MergeStrategy::Rows,
+            trim_ratio: 0.0,
+            line_offset_ratio: 0.5,
+            smoothing_kernel: None,
+            row_pick: RowPick::Center,
+            auto_invert: false,
+            max_total_sections: None,
+            max_regions: None,
+            min_regions: 0,
+            merge_horizontal: true,
+            merge_vertical: true,
+            max_vertical_gap: None,
+            merge_iou: None,
+            dedupe: true,
+            pad_to_power_of_two: false,
+            debug_dump_dir: None,
+            dual_polarity: false,
+            min_active_bins: 0,
+            max_peak_share: 1.0,
+            min_peak_share: 0.0,
+            remove_dc: true,
+            threshold: THRESHOLD,
+            consecutive_threshold: CONSECUTIVE_THRESHOLD,
+            max_white_black_width: MAX_WHITE_BLACK_WIDTH,
+            vertical_sections: VERTICAL_SECTIONS,
+            horizontal_sections: HORIZONTAL_SECTIONS,
+            adaptive_run_cap: None,
+            scan_lines: 1,
+            scan_line_merge: ScanLineMerge::Mean,
+            dpi: REFERENCE_DPI,
+            binarization: Binarization::Fixed,
+            fft_precision: FftPrecision::Single,
+            fft_window: FftWindow::None,
+            character_box: CharacterBox::TextBelow,
+            min_freq: 0.0,
+            max_freq: 0.5,
+        }
+    }
+}
+
+impl DetectionConfig {
+    /// Minimum summed magnitude for a section to count.
+    ///
+    /// Section lines are `downscale` times shorter on a reduced image, so the
+    /// threshold is scaled down with the magnitude sum, which grows roughly with N^1.5.
+    fn section_threshold(&self) -> f32 {
+        self.threshold / (self.downscale.max(1) as f32).powf(1.5)
+    }
+
+    /// Whether `max_regions` may stop the scan early, band by band: only when
+    /// nothing asks for grouping or a pass that `RegionIter` can't apply.
+    fn stops_early(&self) -> bool {
+        self.merge_strategy == MergeStrategy::Rows
+            && self.merge_horizontal
+            && self.max_vertical_gap.is_none()
+            && !self.dual_polarity
+            && !self.auto_invert
+    }
+
+    /// Row within a band of `band_height` rows where the scan line is sampled.
+    fn line_offset(&self, band_height: u32) -> u32 {
+        let ratio = self.line_offset_ratio.clamp(0.0, 1.0);
+        ((band_height as f32 * ratio) as u32).min(band_height.saturating_sub(1))
+    }
+
+    /// The config as applied to `grid`, with clamped and derived values filled in.
+    fn effective(&self, grid: &MagnitudeGrid) -> DetectionConfig {
+        DetectionConfig {
+            downscale: self.downscale.max(1),
+            sections_x: Some(grid.sections_per_width),
+            section_height: self.section_height.max(1),
+            line_offset_ratio: self.line_offset_ratio.clamp(0.0, 1.0),
+            consecutive_threshold: self.min_run(grid),
+            ..self.clone()
+        }
+    }
+
+    /// Consecutive passing sections a run on `grid` needs to become a region.
+    ///
+    /// With `adaptive_run_cap` set, every section is treated as passing
+    /// independently with the grid's observed pass rate `p`, so a run of `k`
+    /// starts at a given section with probability `p^k`. The result is the
+    /// smallest `k` for which the expected number of such runs over the grid
+    /// stays below `FALSE_RUN_RATE`, between 1 and the cap.
+    fn min_run(&self, grid: &MagnitudeGrid) -> usize {
+        let Some(cap) = self.adaptive_run_cap else {
+            return self.consecutive_threshold;
+        };
+
+        let pass_grid = grid.pass_grid();
+        let total = pass_grid.iter().map(Vec::len).sum::<usize>();
+        let passed = pass_grid.iter().flatten().filter(|&&p| p).count();
+        if passed == 0 || passed == total {
+            return if passed == 0 { 1 } else { cap.max(1) };
+        }
+
+        let pass_rate = passed as f64 / total as f64;
+        let run = (FALSE_RUN_RATE / total as f64).ln() / pass_rate.ln();
+        (run.ceil() as usize).clamp(1, cap.max(1))
+    }
+
+    /// FFT length for a section line of `line_len` pixels, after any padding.
+    fn fft_len(&self, line_len: usize) -> usize {
+        if self.pad_to_power_of_two {
+            line_len.next_power_of_two()
+        } else {
+            line_len
+        }
+    }
+
+    /// Columns, from the left edge, that `sections` sections of `section_width`
+    /// scan on an image `width` wide, per `trailing_columns`.
+    fn scanned_width(&self, width: u32, section_width: u32, sections: u32) -> u32 {
+        match self.trailing_columns {
+            TrailingColumns::Ignore => clamped_mul(sections as u64, section_width, width),
+            TrailingColumns::WidenLast => width,
+        }
+    }
+
+    /// Longest allowed run of a single color, scaled for any downscale.
+    fn max_run_width(&self) -> usize {
+        (self.max_white_black_width / self.downscale.max(1) as usize).max(1)
+    }
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl DetectionConfig {
+    /// Creates a config from the defaults, overriding any field passed as a keyword argument.
+    #[new]
+    #[pyo3(signature = (**kwargs))]
+    fn new(py: Python<'_>, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        DetectionConfig::default().with_overrides(py, kwargs)
+    }
+
+    /// Python binding of `DetectionConfig::pinned`.
+    #[staticmethod]
+    #[pyo3(name = "pinned")]
+    fn py_pinned() -> DetectionConfig {
+        DetectionConfig::pinned()
+    }
+
+    /// Python binding of `DetectionConfig::validate`; raises `ValueError`.
+    #[pyo3(name = "validate")]
+    fn py_validate(&self) -> Result<(), ConfigError> {
+        self.validate()
+    }
+}
+
+impl DetectionConfig {
+    /// Returns the v1 preset: every parameter spelled out at its original
+    /// value and every adaptive or optional stage switched off.
+    ///
+    /// Unlike `DetectionConfig::default()`, which follows the current
+    /// defaults, this preset never changes between releases, so output that
+    /// must stay reproducible should be produced with it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bar_dec::{detect_barcode_regions_slice, DetectionConfig};
+    /// let pixels = vec![255; 800 * 600];
+    /// let regions = detect_barcode_regions_slice(&pixels, 800, 600, Some(DetectionConfig::pinned()))?;
+    /// assert!(regions.is_empty());
+    /// # Ok::<(), bar_dec::DetectError>(())
+    /// ```
+    pub fn pinned() -> DetectionConfig {
+        DetectionConfig {
+            downscale: 1,
+            verify: false,
+            estimate_skew: false,
+            normalize: false,
+            confidence_merge: ConfidenceMerge::Mean,
+            signal: SignalType::Luma,
+            count_nyquist: true,
+            spectrum_sum: SpectrumSum::TwoSided,
+            trailing_band: TrailingBand::Ignore,
+            trailing_columns: TrailingColumns::Ignore,
+            sections_x: None,
+            section_height: 5,
+            origin: (0, 0),
+            best_scanline: false,
+            merge_strategy: MergeStrategy::Rows,
+            trim_ratio: 0.0,
+            line_offset_ratio: 0.5,
+            smoothing_kernel: None,
+            row_pick: RowPick::Center,
+            auto_invert: false,
+            max_total_sections: None,
+            max_regions: None,
+            min_regions: 0,
+            merge_horizontal: true,
+            merge_vertical: true,
+            max_vertical_gap: None,
+            merge_iou: None,
+            dedupe: false,
+            pad_to_power_of_two: false,
+            debug_dump_dir: None,
+            dual_polarity: false,
+            min_active_bins: 0,
+            max_peak_share: 1.0,
+            min_peak_share: 0.0,
+            remove_dc: true,
+            threshold: 50.0,
+            consecutive_threshold: 5,
+            max_white_black_width: 10,
+            vertical_sections: 60,
+            horizontal_sections: 100,
+            adaptive_run_cap: None,
+            scan_lines: 1,
+            scan_line_merge: ScanLineMerge::Mean,
+            dpi: REFERENCE_DPI,
+            binarization: Binarization::Fixed,
+            fft_precision: FftPrecision::Single,
+            fft_window: FftWindow::None,
+            character_box: CharacterBox::TextBelow,
+            min_freq: 0.0,
+            max_freq: 0.5,
+        }
+    }
+
+    /// Checks that every setting is in a range detection can work with.
+    ///
+    /// Detection runs this before touching any pixels, so a zero
+    /// `section_height` or a `NaN` threshold fails with a message naming the
+    /// field instead of producing garbage deep in the pipeline. Settings the
+    /// docs describe as clamped, like `line_offset_ratio`, only need to be
+    /// numbers.
+    ///
+    /// # Returns
+    ///
+    /// A `ConfigError` naming the first offending field. Detection functions
+    /// report it as `DetectError::InvalidConfig`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use bar_dec::DetectionConfig;
+    /// let config = DetectionConfig { section_height: 0, ..Default::default() };
+    /// let err = config.validate().unwrap_err();
+    /// assert_eq!(err.field, "section_height");
+    /// ```
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let check = |ok: bool, field: &'static str, reason: &str| {
+            if ok {
+                Ok(())
+            } else {
+                Err(ConfigError {
+                    field,
+                    reason: reason.to_string(),
+                })
+            }
+        };
+        let fraction = |value: f32| (0.0..=1.0).contains(&value);
+
+        check(self.downscale >= 1, "downscale", "must be at least 1")?;
+        check(
+            self.section_height >= 1,
+            "section_height",
+            "must be at least 1",
+        )?;
+        check(
+            self.consecutive_threshold >= 1,
+            "consecutive_threshold",
+            "must be at least 1",
+        )?;
+        check(
+            self.max_white_black_width >= 1,
+            "max_white_black_width",
+            "must be at least 1",
+        )?;
+        check(
+            self.vertical_sections >= 1,
+            "vertical_sections",
+            "must be at least 1",
+        )?;
+        check(
+            self.horizontal_sections >= 1,
+            "horizontal_sections",
+            "must be at least 1",
+        )?;
+        check(
+            self.sections_x != Some(0),
+            "sections_x",
+            "must be at least 1",
+        )?;
+        check(self.scan_lines >= 1, "scan_lines", "must be at least 1")?;
+        check(
+            self.adaptive_run_cap != Some(0),
+            "adaptive_run_cap",
+            "must be at least 1",
+        )?;
+        check(
+            self.smoothing_kernel
+                .is_none_or(|(sections, bands)| sections >= 1 && bands >= 1),
+            "smoothing_kernel",
+            "sizes must be at least 1",
+        )?;
+        check(
+            self.threshold.is_finite() && self.threshold >= 0.0,
+            "threshold",
+            "must be a finite, non-negative number",
+        )?;
+        check(
+            self.dpi.is_finite() && self.dpi > 0.0,
+            "dpi",
+            "must be a finite, positive number",
+        )?;
+        check(
+            fraction(self.trim_ratio),
+            "trim_ratio",
+            "must be between 0.0 and 1.0",
+        )?;
+        check(
+            !self.line_offset_ratio.is_nan(),
+            "line_offset_ratio",
+            "must be a number",
+        )?;
+        check(
+            self.merge_iou.is_none_or(fraction),
+            "merge_iou",
+            "must be between 0.0 and 1.0",
+        )?;
+        check(
+            fraction(self.max_peak_share),
+            "max_peak_share",
+            "must be between 0.0 and 1.0",
+        )?;
+        check(
+            fraction(self.min_peak_share) && self.min_peak_share <= self.max_peak_share,
+            "min_peak_share",
+            "must be between 0.0 and max_peak_share",
+        )?;
+        check(
+            (0.0..=0.5).contains(&self.max_freq),
+            "max_freq",
+            "must be between 0.0 and 0.5 cycles per pixel",
+        )?;
+        check(
+            (0.0..=self.max_freq).contains(&self.min_freq),
+            "min_freq",
+            "must be between 0.0 and max_freq",
+        )?;
+        check(
+            self.max_regions
+                .is_none_or(|max_regions| self.min_regions <= max_regions),
+            "min_regions",
+            "must not exceed max_regions",
+        )
+    }
+}
+
+#[cfg(feature = "python")]
+impl DetectionConfig {
+    /// Returns a copy of the config with every field named in `kwargs` set to its value.
+    ///
+    /// Unknown names and values of the wrong type raise the usual Python
+    /// `AttributeError` / `TypeError`.
+    fn with_overrides(
+        self,
+        py: Python<'_>,
+        kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<DetectionConfig> {
+        let config = Bound::new(py, self)?;
+        if let Some(kwargs) = kwargs {
+            for (key, value) in kwargs {
+                config.setattr(key.downcast::<PyString>()?, value)?;
+            }
+        }
+        let config = config.borrow().clone();
+        Ok(config)
+    }
+}
 
 /// Detects barcode-like regions in a grayscale image using frequency analysis.
 ///
+/// The regions cover the bars themselves, exactly as detected: unlike
+/// `detect_character_regions`, no guard bars are stripped and nothing is moved
+/// by `adjust_regions`, so callers can crop with their own margins.
+///
 /// # Arguments
 ///
-/// * `img` - A reference to the grayscale image buffer
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+/// * `orientation` - Which barcode orientations to look for. Regions of the
+///   vertical pass are mapped back to the original coordinates.
+/// * `roi` - Optional `(x, y, w, h)` box to restrict detection to, e.g. to
+///   keep a printed border out; it's clamped to the image. Only pixels inside
+///   are sampled and sections outside it are never computed. For the
+///   horizontal pass the ROI is split into sections as wide as on the whole
+///   image, like `detect_in_rois` does, so a ROI narrower than one section
+///   finds nothing; the vertical pass splits the ROI on its own.
 ///
 /// # Returns
 ///
-/// A vector of `BarcodeRegion` containing detected regions
+/// A vector of `BarcodeRegion` containing detected regions, in full-image
+/// coordinates even with a `roi`.
 ///
 /// # Example
 ///
-/// ```rust
-/// use barcode_detector::{detect_barcode_regions, BarcodeRegion};
-/// use image::GrayImage;
+/// ```python
+/// import house_specific
 ///
-/// let img = GrayImage::new(800, 600);
-/// let regions = detect_barcode_regions(&img);
-/// for region in regions {
-///     println!("{:?}", region);
-/// }
+/// regions = house_specific.detect_barcode_regions(pixels, 800, 600)
+/// for region in regions:
+///     print(region.x_start, region.x_end, region.y_start, region.y_end)
+///
+/// # Skip a 40px border
+/// inner = house_specific.detect_barcode_regions(pixels, 800, 600, roi=(40, 40, 720, 520))
 /// ```
-#[pyfunction]
-fn detect_barcode_regions(img_data: Vec<u8>, width: u32, height: u32) -> Vec<BarcodeRegion> {
-    let img = ImageBuffer::<Luma<u8>, Vec<u8>>::from_vec(width, height, img_data)
-        .expect("Failed to create image buffer");
+///
+/// From Rust, `detect_barcode_regions_slice` and `detect_barcode_regions_image`
+/// scan a borrowed buffer or a `GrayImage` without taking ownership.
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, config = None, orientation = Orientation::Horizontal, roi = None)))]
+pub fn detect_barcode_regions(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+    orientation: Orientation,
+    roi: Option<(u32, u32, u32, u32)>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    if let Some((x, y, w, h)) = roi {
+        let img = image_view(&img_data, width, height)?;
+        let x_end = x.saturating_add(w).min(width);
+        let y_end = y.saturating_add(h).min(height);
+        let x_start = x.min(x_end);
+        let y_start = y.min(y_end);
 
-    let is_ratio = width <= height;
-    let sections_per_width = if is_ratio {
-        VERTICAL_SECTIONS
-    } else {
-        HORIZONTAL_SECTIONS
-    };
-    let section_width = width / sections_per_width;
-    let sections_per_height = (height / SECTION_HEIGHT) as usize;
+        let factor = config.downscale.max(1);
+        let (roi_width, roi_height) = (x_end - x_start, y_end - y_start);
+        let sections_x = roi_width / factor / working_section_width(width, height, &config);
+        // Only the pinned horizontal pass needs a whole-image section to fit across
+        let scannable = match orientation {
+            Orientation::Horizontal => sections_x > 0 && roi_height >= factor,
+            Orientation::Vertical | Orientation::Both => {
+                roi_width >= factor && roi_height >= factor
+            }
+        };
+        if !scannable {
+            return Ok(Vec::new());
+        }
 
-    let mut barcode_regions = Vec::new();
-    let mut planner = FftPlanner::<f32>::new();
+        let crop = ImageBuffer::from_fn(x_end - x_start, y_end - y_start, |x, y| {
+            Luma([img.luma_at(x_start + x, y_start + y)])
+        });
+        let (origin_x, origin_y) = config.origin;
+        let roi_config = DetectionConfig {
+            origin: (
+                origin_x.saturating_add(x_start),
+                origin_y.saturating_add(y_start),
+            ),
+            sections_x: match orientation {
+                Orientation::Horizontal => Some(sections_x),
+                _ => config.sections_x,
+            },
+            ..config.clone()
+        };
+        let (crop_width, crop_height) = crop.dimensions();
+        let mut barcode_regions = detect_in_orientation(
+            crop.into_raw(),
+            crop_width,
+            crop_height,
+            &roi_config,
+            orientation,
+        )?;
+        // The crop measured them against itself
+        normalize_bounds(&mut barcode_regions, &config, width, height);
+        return Ok(barcode_regions);
+    }
 
-    for section_index_y in 0..sections_per_height {
-        let section_y_start = section_index_y as u32 * SECTION_HEIGHT;
+    detect_in_orientation(img_data, width, height, &config, orientation)
+}
 
-        // Calculate the amplitude of each horizontal section
-        let section_magnitudes = compute_section_magnitudes(
-            &img,
-            section_y_start,
-            section_width,
-            sections_per_width,
-            &mut planner,
-        );
+/// Runs `detect_barcode_regions` on the whole of `img_data`, with settings
+/// that already passed `DetectionConfig::validate`.
+fn detect_in_orientation(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: &DetectionConfig,
+    orientation: Orientation,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    match orientation {
+        Orientation::Horizontal => {
+            let img = gray_image(img_data, width, height)?;
+            detect_horizontal(&img, config, &mut FftPlanner::new())
+        }
+        _ => detect_oriented(
+            &img_data,
+            width,
+            height,
+            config,
+            orientation,
+            OrientationConflict::KeepBoth,
+        ),
+    }
+}
 
-        // Detects high amplitude areas as barcode areas
-        detect_regions(
-            &section_magnitudes,
-            section_y_start,
-            section_width,
-            &mut barcode_regions,
+/// Detects barcode-like regions in a 2-D `uint8` NumPy array.
+///
+/// The width and height come from the array's shape, `(height, width)`, and
+/// the pixels are read in place: no `tobytes()` copy is needed, and sliced or
+/// transposed (non-contiguous) arrays work as they are.
+///
+/// # Arguments
+///
+/// * `array` - The grayscale image as a 2-D `uint8` array.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A vector of `BarcodeRegion` containing detected regions. Arrays of another
+/// dtype or dimensionality are rejected with a `TypeError`.
+///
+/// # Example
+///
+/// ```python
+/// img = np.asarray(Image.open("label.png").convert("L"))
+/// regions = house_specific.detect_barcode_regions_array(img)
+/// ```
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(signature = (array, config = None))]
+fn detect_barcode_regions_array(
+    array: PyReadonlyArray2<'_, u8>,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let view = array.as_array();
+    let (height, width) = view.dim();
+    if u32::try_from(width.max(height)).is_err() {
+        return Err(
+            PyValueError::new_err(format!("{width}x{height} array is too large to scan")).into(),
         );
     }
+    detect_in_source(&view, config)
+}
+
+/// Detects barcode-like regions in interleaved RGB or RGBA image data.
+///
+/// The pixels are converted to luma with the ITU-R BT.601 weights
+/// (0.299, 0.587, 0.114) before the usual grayscale pipeline runs; alpha is
+/// ignored. This saves callers holding camera frames a conversion of their own.
+///
+/// # Arguments
+///
+/// * `img_data` - Interleaved pixel data, `channels` bytes per pixel.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `channels` - The number of channels per pixel: 3 for RGB, 4 for RGBA.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A vector of `BarcodeRegion` containing detected regions.
+/// Fails if `channels` isn't 3 or 4, or if `img_data` doesn't hold
+/// `width * height * channels` bytes.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::detect_barcode_regions_rgb;
+/// let frame = vec![255; 1280 * 720 * 4];
+/// let regions = detect_barcode_regions_rgb(frame.to_vec(), 1280, 720, 4, None)?;
+/// assert!(regions.is_empty());
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, channels, config = None)))]
+pub fn detect_barcode_regions_rgb(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    channels: u32,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    if !matches!(channels, 3 | 4) {
+        return Err(DetectError::UnsupportedColorType(format!(
+            "{channels} interleaved channels"
+        )));
+    }
+    let expected = buffer_len(width, height, channels as usize);
+    if img_data.len() != expected {
+        return Err(DetectError::DimensionMismatch {
+            expected,
+            got: img_data.len(),
+        });
+    }
+
+    let luma = interleaved_to_luma(&img_data, channels as usize);
+    detect_barcode_regions_slice(&luma, width, height, config)
+}
+
+/// Detects barcode-like regions in 16-bit grayscale image data.
+///
+/// Medical and industrial scanners keep detail in the low bits that an 8-bit
+/// conversion throws away. Here the pixels are binarized against a 16-bit
+/// `threshold` instead, and the FFT pipeline runs as usual on the result.
+/// Only `Binarization.Fixed` on `SignalType.Luma` sees the full 16-bit
+/// split; settings that look at luma steps or histograms, like
+/// `SignalType.GradientX` or `Binarization.Otsu`, work on an 8-bit rescale.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u16` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `threshold` - 16-bit level separating bars from spaces; `None` takes the
+///   midpoint between the darkest and brightest pixels.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A vector of `BarcodeRegion` containing detected regions.
+/// Fails if `img_data` doesn't hold `width * height` pixels.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::detect_barcode_regions_u16;
+/// // Bars and spaces both sit below 1024 in a dark corner of the scan
+/// let pixels = vec![900; 2480 * 3508];
+/// let regions = detect_barcode_regions_u16(pixels, 2480, 3508, Some(600), None)?;
+/// assert!(regions.is_empty());
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, threshold = None, config = None)))]
+pub fn detect_barcode_regions_u16(
+    img_data: Vec<u16>,
+    width: u32,
+    height: u32,
+    threshold: Option<u16>,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    if width == 0 || height == 0 {
+        return Err(DetectError::EmptyImage { width, height });
+    }
+    let expected = buffer_len(width, height, 1);
+    let got = img_data.len();
+    let img: ImageBuffer<Luma<u16>, Vec<u16>> = ImageBuffer::from_raw(width, height, img_data)
+        .ok_or(DetectError::DimensionMismatch { expected, got })?;
 
-    // merge same pos "y"
-    merge_barcode_regions(&mut barcode_regions);
+    detect_in_source(&Luma16::new(&img, threshold), config)
+}
+
+/// Detects barcode-like regions in a borrowed grayscale buffer.
+///
+/// This is the Rust-side entry point: the pixels are read through an
+/// `ImageBuffer` view of the slice, so callers holding a `&[u8]` don't have to
+/// copy or give up their buffer. It touches no Python objects and never takes
+/// the GIL, so benchmarks and other Rust code can call it directly.
+///
+/// # Arguments
+///
+/// * `img` - A slice of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A vector of `BarcodeRegion` containing detected regions, or
+/// `DetectError::DimensionMismatch` if `img` doesn't hold `width * height` bytes.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::detect_barcode_regions_slice;
+/// let pixels = vec![255; 800 * 600];
+/// let regions = detect_barcode_regions_slice(&pixels, 800, 600, None)?;
+/// assert!(regions.is_empty());
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+pub fn detect_barcode_regions_slice(
+    img: &[u8],
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    BarcodeDetector::new(config.unwrap_or_default()).detect(img, width, height)
+}
 
-    // merge current pos "y" and next pos "y"
-    merge_regions_if_y_matches(&mut barcode_regions);
+/// Detects barcode-like regions in an `image::GrayImage`.
+///
+/// The Rust-native counterpart of `detect_barcode_regions`: the image is
+/// scanned in place, and the Python binding wraps its owned buffer in a
+/// `GrayImage` and delegates here.
+///
+/// # Arguments
+///
+/// * `img` - The grayscale image to scan.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A vector of `BarcodeRegion` containing detected regions, or
+/// `DetectError::EmptyImage` if the image has no pixels.
+///
+/// # Example
+///
+/// ```no_run
+/// use bar_dec::detect_barcode_regions_image;
+///
+/// let img = image::open("label.png")?.to_luma8();
+/// for region in detect_barcode_regions_image(&img, None)? {
+///     println!("{:?}", region);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn detect_barcode_regions_image(
+    img: &GrayImage,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    detect_horizontal(img, &config, &mut FftPlanner::new())
+}
+
+/// Serializes regions to a JSON array, e.g. to cache results or send them over a socket.
+///
+/// Each region is an object keyed by its field names (`x_start`, `x_end`,
+/// `y_start`, `y_end`, ...), so the output reads back with
+/// `serde_json::from_str::<Vec<BarcodeRegion>>`.
+///
+/// # Arguments
+///
+/// * `regions` - The regions to serialize.
+///
+/// # Returns
+///
+/// The JSON text.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{regions_to_json, BarcodeRegion};
+/// let regions = vec![BarcodeRegion::new(100, 300, 50, 80)?];
+/// let json = regions_to_json(&regions);
+/// let restored: Vec<BarcodeRegion> = serde_json::from_str(&json)?;
+/// assert_eq!(restored[0].x_end, 300);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "serde")]
+pub fn regions_to_json(regions: &[BarcodeRegion]) -> String {
+    // Plain structs of numbers, bools and unit enums always serialize
+    serde_json::to_string(regions).expect("BarcodeRegion serializes to JSON")
+}
+
+/// Formats regions as CSV, e.g. to log detections to a spreadsheet.
+///
+/// The first line is the header `x_start,x_end,y_start,y_end,confidence`,
+/// followed by one line per region in the given order. Every line, the last
+/// one included, ends in `\n`.
+///
+/// # Arguments
+///
+/// * `regions` - The regions to format.
+///
+/// # Returns
+///
+/// The CSV text; just the header when `regions` is empty.
+///
+/// # Example
+///
+/// ```no_run
+/// # use bar_dec::{regions_to_csv, BarcodeRegion};
+/// let regions = vec![BarcodeRegion { confidence: 0.92, ..BarcodeRegion::new(820, 1599, 100, 300)? }];
+/// let csv = regions_to_csv(&regions);
+/// // x_start,x_end,y_start,y_end,confidence
+/// // 820,1599,100,300,0.92
+/// std::fs::write("detections.csv", csv)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn regions_to_csv(regions: &[BarcodeRegion]) -> String {
+    let mut csv = String::from("x_start,x_end,y_start,y_end,confidence\n");
+    for region in regions {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            region.x_start, region.x_end, region.y_start, region.y_end, region.confidence
+        ));
+    }
+    csv
+}
+
+/// Python binding for `regions_to_csv`.
+///
+/// # Example
+///
+/// ```python
+/// with open("detections.csv", "w") as f:
+///     f.write(house_specific.regions_to_csv(regions))
+/// ```
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "regions_to_csv")]
+fn py_regions_to_csv(regions: Vec<BarcodeRegion>) -> String {
+    regions_to_csv(&regions)
+}
+
+/// Detects barcode-like regions in any `PixelSource`.
+///
+/// Use this for images that don't live in a `u8` buffer, e.g. a mapped GPU
+/// texture or a custom format: detection reads pixels through the trait, so
+/// the image is never copied as a whole.
+///
+/// # Arguments
+///
+/// * `source` - The image to scan.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A vector of `BarcodeRegion` containing detected regions.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{detect_in_source, PixelSource};
+/// // A stand-in for a GPU texture: pixels are computed, never stored
+/// struct Gradient;
+///
+/// impl PixelSource for Gradient {
+///     fn luma_at(&self, x: u32, _y: u32) -> u8 {
+///         (x / 4) as u8
+///     }
+///
+///     fn dimensions(&self) -> (u32, u32) {
+///         (800, 600)
+///     }
+/// }
+///
+/// let regions = detect_in_source(&Gradient, None)?;
+/// assert!(regions.is_empty());
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+pub fn detect_in_source<P: PixelSource + ?Sized>(
+    source: &P,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    detect_horizontal(source, &config, &mut FftPlanner::new())
+}
+
+/// Single-pass horizontal detection shared by the `Vec`, slice, `GrayImage`
+/// and `PixelSource` entry points, with settings that already passed
+/// `DetectionConfig::validate`.
+pub(crate) fn detect_horizontal<P: PixelSource + ?Sized>(
+    img: &P,
+    config: &DetectionConfig,
+    planner: &mut FftPlanner<f32>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return Err(DetectError::EmptyImage { width, height });
+    }
+
+    detect_and_offset(img, config, planner, &mut Vec::new())
+}
+
+/// Detects barcode-like regions and reports any compromises made along the way.
+///
+/// Warnings are human-readable descriptions of things like a clamped section
+/// count, rows or columns left unscanned, or a downscale being applied.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A tuple of the detected regions and the warnings emitted during detection.
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, config = None)))]
+pub fn detect_with_warnings(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<(Vec<BarcodeRegion>, Vec<String>), DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let mut warnings = Vec::new();
+    let barcode_regions = run_detection(&img_data, width, height, &config, &mut warnings)?;
+    Ok((barcode_regions, warnings))
+}
+
+/// Detects barcode-like regions and returns the configuration actually applied.
+///
+/// The returned config has every value that detection clamps or derives
+/// filled in, such as the section count after clamping to narrow images and
+/// the effective downscale factor, so a result set can be reproduced exactly
+/// by passing it back in. The whole image is scanned to fill it in, so
+/// `max_regions` keeps the first regions top to bottom instead of stopping
+/// early; fewer than `min_regions` regions fail as usual.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A tuple of the detected regions and the effective `DetectionConfig`.
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, config = None)))]
+pub fn detect_with_meta(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<(Vec<BarcodeRegion>, DetectionConfig), DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let img = image_view(&img_data, width, height)?;
+
+    let (mut barcode_regions, grid) = detect_with_grid(&img, &config, &mut Vec::new())?;
+    offset_regions(&mut barcode_regions, &config, width, height)?;
+    let barcode_regions = limit_region_count(barcode_regions, &config)?;
+    Ok((barcode_regions, config.effective(&grid)))
+}
+
+/// Detects barcode-like regions and returns the section data behind them.
+///
+/// Alongside the regions this reports every section's magnitude before the
+/// threshold and whether it passed the threshold and prefilters, so a tuning
+/// decision can be reproduced, or a missed barcode explained, from a dump of
+/// the result. The grid is the one detection worked on: one row per band, one
+/// entry per section, measured on the working image after any downscale.
+/// Every band is scanned to fill it in, so `max_regions` keeps the first
+/// regions top to bottom instead of stopping early; fewer than `min_regions`
+/// regions fail as usual.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A tuple of the detected regions, the section magnitudes and the pass grid.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::detect_with_diagnostics;
+/// let pixels = vec![255; 800 * 600];
+/// let (regions, magnitudes, passed) = detect_with_diagnostics(pixels, 800, 600, None)?;
+/// for (band, row) in magnitudes.iter().enumerate() {
+///     println!("{band}: {row:?} {:?}", passed[band]);
+/// }
+/// assert!(regions.is_empty());
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, config = None)))]
+#[allow(clippy::type_complexity)]
+pub fn detect_with_diagnostics(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<(Vec<BarcodeRegion>, Vec<Vec<f32>>, Vec<Vec<bool>>), DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let img = image_view(&img_data, width, height)?;
+
+    let (mut barcode_regions, grid) = detect_with_grid(&img, &config, &mut Vec::new())?;
+    offset_regions(&mut barcode_regions, &config, width, height)?;
+    let barcode_regions = limit_region_count(barcode_regions, &config)?;
+    let passed = grid.pass_grid();
+    Ok((barcode_regions, grid.raw_magnitudes, passed))
+}
+
+/// Detects barcode-like regions in a precomputed edge-magnitude image.
+///
+/// The frequency analysis runs directly on the edge magnitudes instead of
+/// binarized luma, so an upstream edge detector can be tuned independently.
+/// Symbology guesses are not attached since they need the original pixels.
+///
+/// # Arguments
+///
+/// * `edges` - A vector of `u8` edge magnitudes, one per pixel.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; its `signal` is overridden.
+///
+/// # Returns
+///
+/// A vector of `BarcodeRegion` containing detected regions
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (edges, width, height, config = None)))]
+pub fn detect_from_edges(
+    edges: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let config = DetectionConfig {
+        signal: SignalType::Edges,
+        ..config.unwrap_or_default()
+    };
+    config.validate()?;
+    run_detection(&edges, width, height, &config, &mut Vec::new())
+}
+
+/// Runs the full detection pipeline, appending any compromises to `warnings`.
+fn run_detection(
+    img_data: &[u8],
+    width: u32,
+    height: u32,
+    config: &DetectionConfig,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let img = image_view(img_data, width, height)?;
+    detect_and_offset(&img, config, &mut FftPlanner::new(), warnings)
+}
+
+/// Detects regions in `img` and shifts them by `config.origin`.
+///
+/// With the `tracing` feature this runs inside a `detect` span recording the
+/// image size, the number of sections scanned and the number of regions found.
+fn detect_and_offset<P: PixelSource + ?Sized>(
+    img: &P,
+    config: &DetectionConfig,
+    planner: &mut FftPlanner<f32>,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let (width, height) = img.dimensions();
+
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!(
+        "detect",
+        width,
+        height,
+        sections = tracing::field::Empty,
+        regions = tracing::field::Empty,
+    );
+    #[cfg(feature = "tracing")]
+    let _entered = span.enter();
+
+    // A known region count lets the scan stop early, band by band
+    if let Some(max_regions) = config.max_regions.filter(|_| config.stops_early()) {
+        let barcode_regions: Vec<BarcodeRegion> = RegionIter::new(img, config.clone(), warnings)?
+            .take(max_regions)
+            .collect();
+        #[cfg(feature = "tracing")]
+        span.record("regions", barcode_regions.len());
+        return check_region_count(barcode_regions, config.min_regions);
+    }
+
+    let (mut barcode_regions, _grid) = detect_with_planner(img, config, planner, warnings)?;
+    offset_regions(&mut barcode_regions, config, width, height)?;
+
+    #[cfg(feature = "tracing")]
+    {
+        span.record(
+            "sections",
+            _grid.bands.len() as u64 * _grid.sections_per_width as u64,
+        );
+        span.record("regions", barcode_regions.len());
+    }
+    limit_region_count(barcode_regions, config)
+}
+
+/// Applies `max_regions` and `min_regions` to regions of a scan that covered
+/// the whole image: the first `max_regions` regions top to bottom are kept.
+fn limit_region_count(
+    mut barcode_regions: Vec<BarcodeRegion>,
+    config: &DetectionConfig,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    if let Some(max_regions) = config.max_regions {
+        barcode_regions.sort_by_key(|region| (region.y_end, region.x_start));
+        barcode_regions.truncate(max_regions);
+    }
+    check_region_count(barcode_regions, config.min_regions)
+}
+
+/// Fails with `DetectError::TooFewRegions` when fewer than `min_regions` regions were found.
+fn check_region_count(
+    barcode_regions: Vec<BarcodeRegion>,
+    min_regions: usize,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    if barcode_regions.len() < min_regions {
+        return Err(DetectError::TooFewRegions {
+            found: barcode_regions.len(),
+            min: min_regions,
+        });
+    }
+    Ok(barcode_regions)
+}
+
+/// Shifts regions by `config.origin` so they're expressed in the frame the
+/// image was cropped from, and fills in their normalized bounds when
+/// `config.normalize` is on.
+///
+/// # Arguments
+///
+/// * `barcode_regions` - A mutable reference to the regions to shift.
+/// * `config` - Detection settings holding the `(x, y)` offset to add to every coordinate.
+/// * `width` - The width of the image the regions were detected in.
+/// * `height` - The height of the image the regions were detected in.
+///
+/// # Returns
+///
+/// `DetectError::OriginOutOfRange` if a coordinate inside the image could
+/// overflow `u32` once shifted; the regions are left untouched in that case.
+fn offset_regions(
+    barcode_regions: &mut [BarcodeRegion],
+    config: &DetectionConfig,
+    width: u32,
+    height: u32,
+) -> Result<(), DetectError> {
+    let origin = config.origin;
+    let (origin_x, origin_y) = origin;
+    if origin_x.checked_add(width).is_none() || origin_y.checked_add(height).is_none() {
+        return Err(DetectError::OriginOutOfRange {
+            origin,
+            width,
+            height,
+        });
+    }
+
+    for region in barcode_regions.iter_mut() {
+        region.x_start += origin_x;
+        region.x_end += origin_x;
+        region.y_start += origin_y;
+        region.y_end += origin_y;
+        region.best_scanline_y += if region.vertical { origin_x } else { origin_y };
+    }
+    normalize_bounds(barcode_regions, config, width, height);
+    Ok(())
+}
+
+/// Sets `normalized_bounds` of regions already shifted by `config.origin`,
+/// relative to the `width` x `height` image they were shifted from; does
+/// nothing unless `config.normalize` is on.
+fn normalize_bounds(
+    barcode_regions: &mut [BarcodeRegion],
+    config: &DetectionConfig,
+    width: u32,
+    height: u32,
+) {
+    if !config.normalize {
+        return;
+    }
+    let (origin_x, origin_y) = config.origin;
+    for region in barcode_regions.iter_mut() {
+        let local = BarcodeRegion {
+            x_start: region.x_start.saturating_sub(origin_x),
+            x_end: region.x_end.saturating_sub(origin_x),
+            y_start: region.y_start.saturating_sub(origin_y),
+            y_end: region.y_end.saturating_sub(origin_y),
+            ..BarcodeRegion::default()
+        };
+        region.normalized_bounds = Some(local.normalized(width, height));
+    }
+}
+
+/// Number of values in a `width` x `height` buffer with `channels` values per pixel.
+///
+/// Saturates at `usize::MAX` where the product overflows, as it can on 32-bit
+/// targets like wasm32; no buffer is that long, so a size check still fails.
+fn buffer_len(width: u32, height: u32, channels: usize) -> usize {
+    (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .unwrap_or(usize::MAX)
+}
+
+/// Wraps a borrowed pixel slice in an `ImageBuffer` without copying it.
+///
+/// Fails with `DetectError::EmptyImage` for a zero-sized image and with
+/// `DetectError::DimensionMismatch` when the slice isn't `width * height` long.
+fn image_view(
+    img_data: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<ImageBuffer<Luma<u8>, &[u8]>, DetectError> {
+    if width == 0 || height == 0 {
+        return Err(DetectError::EmptyImage { width, height });
+    }
+    let expected = buffer_len(width, height, 1);
+    ImageBuffer::from_raw(width, height, img_data).ok_or(DetectError::DimensionMismatch {
+        expected,
+        got: img_data.len(),
+    })
+}
+
+/// Takes ownership of a pixel buffer as a `GrayImage`, without copying it.
+///
+/// Fails like `image_view` does for empty or mis-sized buffers.
+fn gray_image(img_data: Vec<u8>, width: u32, height: u32) -> Result<GrayImage, DetectError> {
+    if width == 0 || height == 0 {
+        return Err(DetectError::EmptyImage { width, height });
+    }
+    let expected = buffer_len(width, height, 1);
+    let got = img_data.len();
+    GrayImage::from_raw(width, height, img_data)
+        .ok_or(DetectError::DimensionMismatch { expected, got })
+}
+
+/// Detects barcode regions in both orientations.
+///
+/// The horizontal pass finds barcodes with vertical bars as usual. The vertical
+/// pass runs the same pipeline on the transposed image, finding barcodes rotated
+/// by 90 degrees, and maps its regions back to the original coordinates. Both
+/// passes are independent, so they run in parallel on the rayon thread pool,
+/// each with its own FFT planner.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// * `conflict` - How overlapping regions from the two passes are resolved.
+///
+/// # Returns
+///
+/// The regions from both passes, sorted by position.
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, config = None, conflict = OrientationConflict::KeepBoth)))]
+pub fn detect_any_orientation(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+    conflict: OrientationConflict,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    detect_oriented(
+        &img_data,
+        width,
+        height,
+        &config,
+        Orientation::Both,
+        conflict,
+    )
+}
+
+/// Runs the horizontal and/or vertical pass selected by `orientation`.
+///
+/// The vertical pass scans the transposed image and maps its regions back to
+/// the original coordinates; with `Orientation::Both` the passes run
+/// concurrently and overlaps are resolved per `conflict`.
+///
+/// # Arguments
+///
+/// * `img_data` - The grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Detection settings.
+/// * `orientation` - Which passes to run.
+/// * `conflict` - How overlapping regions from the two passes are resolved.
+fn detect_oriented(
+    img_data: &[u8],
+    width: u32,
+    height: u32,
+    config: &DetectionConfig,
+    orientation: Orientation,
+    conflict: OrientationConflict,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let img = image_view(img_data, width, height)?;
+    let horizontal = || detect_in_image(&img, config, &mut Vec::new());
+    let vertical = || detect_in_image(&transpose(&img), config, &mut Vec::new());
+
+    let (barcode_regions, vertical_regions) = match orientation {
+        Orientation::Horizontal => (horizontal(), Ok(Vec::new())),
+        Orientation::Vertical => (Ok(Vec::new()), vertical()),
+        Orientation::Both => rayon::join(horizontal, vertical),
+    };
+    let vertical_regions = vertical_regions?
+        .into_iter()
+        .map(transpose_region)
+        .collect();
+
+    let mut barcode_regions =
+        resolve_orientation_conflicts(barcode_regions?, vertical_regions, conflict);
+    barcode_regions.sort_by_key(|r| (r.y_start, r.x_start));
+    offset_regions(&mut barcode_regions, config, width, height)?;
+
+    limit_region_count(barcode_regions, config)
+}
+
+/// Combines the regions of the horizontal and vertical passes according to `conflict`.
+///
+/// Each vertical region is checked against the horizontal regions and
+/// resolved with the first one it overlaps; vertical regions without an
+/// overlap are always kept.
+///
+/// # Arguments
+///
+/// * `horizontal` - Regions of the horizontal pass.
+/// * `vertical` - Regions of the vertical pass, already in original coordinates.
+/// * `conflict` - How an overlapping pair is resolved.
+fn resolve_orientation_conflicts(
+    mut horizontal: Vec<BarcodeRegion>,
+    vertical: Vec<BarcodeRegion>,
+    conflict: OrientationConflict,
+) -> Vec<BarcodeRegion> {
+    let mut unmatched = Vec::new();
+
+    for region in vertical {
+        let overlapping = horizontal.iter().position(|h| regions_overlap(h, &region));
+        match (conflict, overlapping) {
+            (OrientationConflict::KeepBoth, _) | (_, None) => unmatched.push(region),
+            (OrientationConflict::KeepStronger, Some(index)) => {
+                if region.raw_magnitude > horizontal[index].raw_magnitude {
+                    horizontal[index] = region;
+                }
+            }
+            (OrientationConflict::Merge, Some(index)) => {
+                let other = &horizontal[index];
+                let stronger = if region.raw_magnitude > other.raw_magnitude {
+                    &region
+                } else {
+                    other
+                };
+                horizontal[index] = BarcodeRegion {
+                    x_start: region.x_start.min(other.x_start),
+                    x_end: region.x_end.max(other.x_end),
+                    y_start: region.y_start.min(other.y_start),
+                    y_end: region.y_end.max(other.y_end),
+                    ..stronger.clone()
+                };
+            }
+        }
+    }
+
+    horizontal.extend(unmatched);
+    horizontal
+}
+
+/// Whether two regions share at least one pixel.
+fn regions_overlap(a: &BarcodeRegion, b: &BarcodeRegion) -> bool {
+    a.x_start < b.x_end && b.x_start < a.x_end && a.y_start < b.y_end && b.y_start < a.y_end
+}
+
+/// Intersection over union of two regions, `0.0` when either is empty.
+fn region_iou(a: &BarcodeRegion, b: &BarcodeRegion) -> f32 {
+    let area = |r: &BarcodeRegion| {
+        r.x_end.saturating_sub(r.x_start) as u64 * r.y_end.saturating_sub(r.y_start) as u64
+    };
+    let overlap = BarcodeRegion {
+        x_start: a.x_start.max(b.x_start),
+        x_end: a.x_end.min(b.x_end),
+        y_start: a.y_start.max(b.y_start),
+        y_end: a.y_end.min(b.y_end),
+        ..Default::default()
+    };
+
+    let intersection = area(&overlap);
+    let union = area(a) + area(b) - intersection;
+    if union == 0 {
+        0.0
+    } else {
+        intersection as f32 / union as f32
+    }
+}
+
+/// Swaps the x and y axes of a grayscale image.
+fn transpose<P: PixelSource + ?Sized>(img: &P) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+    ImageBuffer::from_fn(img.height(), img.width(), |x, y| Luma([img.luma_at(y, x)]))
+}
+
+/// Maps a region found on a transposed image back to the original axes.
+///
+/// The region is marked `vertical`, so its `best_scanline_y` reads as a column.
+/// A transpose mirrors the image, turning a clockwise tilt counterclockwise, so
+/// `skew_degrees` flips its sign.
+fn transpose_region(region: BarcodeRegion) -> BarcodeRegion {
+    BarcodeRegion {
+        x_start: region.y_start,
+        x_end: region.y_end,
+        y_start: region.x_start,
+        y_end: region.x_end,
+        skew_degrees: -region.skew_degrees,
+        vertical: true,
+        ..region
+    }
+}
+
+/// Layout of the analysis sections over an image and their magnitudes.
+struct MagnitudeGrid {
+    /// `(y_start, band_height)` of every scanned band, top to bottom.
+    bands: Vec<(u32, u32)>,
+    /// Width of the analyzed image, after any downscale.
+    width: u32,
+    /// Width of each section.
+    section_width: u32,
+    /// Number of sections across the width.
+    sections_per_width: u32,
+    /// Right edge (exclusive) of the last section, see `DetectionConfig::trailing_columns`.
+    scanned_width: u32,
+    /// `magnitudes[band][section]`; sections that failed the threshold or
+    /// prefilters hold `0.0`.
+    magnitudes: Vec<Vec<f32>>,
+    /// Magnitudes before the threshold; only prefilter rejections hold `0.0`.
+    raw_magnitudes: Vec<Vec<f32>>,
+    /// `peak_frequencies[band][section]`, see `SectionResponse::peak_frequency`.
+    peak_frequencies: Vec<Vec<f32>>,
+}
+
+impl MagnitudeGrid {
+    /// The rightmost scanned x-coordinate (exclusive).
+    fn scanned_width(&self) -> u32 {
+        self.scanned_width
+    }
+
+    /// The bottommost scanned y-coordinate (exclusive).
+    fn scanned_height(&self) -> u32 {
+        self.bands
+            .last()
+            .map_or(0, |&(y_start, band_height)| y_start + band_height)
+    }
+
+    /// Which sections passed the threshold and prefilters.
+    fn pass_grid(&self) -> Vec<Vec<bool>> {
+        self.magnitudes
+            .iter()
+            .map(|band| band.iter().map(|&magnitude| magnitude > 0.0).collect())
+            .collect()
+    }
+
+    /// Applies a morphological open followed by a close to the pass grid.
+    ///
+    /// Opening (erode, then dilate) removes passing specks smaller than the
+    /// kernel and closing (dilate, then erode) fills gaps smaller than it.
+    /// Sections switched off lose their magnitude; sections switched on take
+    /// the mean magnitude of the passing sections around them.
+    ///
+    /// # Arguments
+    ///
+    /// * `kernel` - `(sections, bands)` size of the structuring element; even
+    ///   sizes are rounded up to the next odd size so it stays centered.
+    fn smooth(&mut self, kernel: (u32, u32)) {
+        let (radius_x, radius_y) = ((kernel.0 / 2) as usize, (kernel.1 / 2) as usize);
+        let radius = (radius_x, radius_y);
+        let passed = self.pass_grid();
+
+        // Pad with failing cells so closing can't grow shapes into the border
+        let width = self.sections_per_width as usize;
+        let mut padded = vec![vec![false; width + 2 * radius_x]; passed.len() + 2 * radius_y];
+        for (band, row) in passed.iter().enumerate() {
+            padded[band + radius_y][radius_x..radius_x + width].copy_from_slice(row);
+        }
+        let opened = morph(&morph(&padded, radius, true), radius, false);
+        let closed = morph(&morph(&opened, radius, false), radius, true);
+        let smoothed: Vec<&[bool]> = closed[radius_y..radius_y + passed.len()]
+            .iter()
+            .map(|row| &row[radius_x..radius_x + width])
+            .collect();
+
+        let original = self.magnitudes.clone();
+        for (band, row) in smoothed.iter().enumerate() {
+            for (section, &on) in row.iter().enumerate() {
+                if on == passed[band][section] {
+                    continue;
+                }
+                self.magnitudes[band][section] = if on {
+                    let neighbors: Vec<f32> = window(&original, band, section, radius)
+                        .filter(|&m| m > 0.0)
+                        .collect();
+                    neighbors.iter().sum::<f32>() / neighbors.len() as f32
+                } else {
+                    0.0
+                };
+            }
+        }
+    }
+}
+
+/// Iterates the cells of `grid` within `radius` of `(band, section)`, clipped to the grid.
+fn window<T: Copy>(
+    grid: &[Vec<T>],
+    band: usize,
+    section: usize,
+    radius: (usize, usize),
+) -> impl Iterator<Item = T> + '_ {
+    let (radius_x, radius_y) = radius;
+    let bands = band.saturating_sub(radius_y)..(band + radius_y + 1).min(grid.len());
+    bands.flat_map(move |b| {
+        let row = &grid[b];
+        row[section.saturating_sub(radius_x)..(section + radius_x + 1).min(row.len())]
+            .iter()
+            .copied()
+    })
+}
+
+/// Erodes (`erode = true`) or dilates a boolean grid with a rectangular kernel.
+///
+/// Cells outside the grid are ignored, so the border isn't eroded just for
+/// being at the edge.
+fn morph(grid: &[Vec<bool>], radius: (usize, usize), erode: bool) -> Vec<Vec<bool>> {
+    grid.iter()
+        .enumerate()
+        .map(|(band, row)| {
+            (0..row.len())
+                .map(|section| {
+                    let mut cells = window(grid, band, section, radius);
+                    if erode {
+                        cells.all(|on| on)
+                    } else {
+                        cells.any(|on| on)
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Computes the magnitude grid of the image detection actually runs on,
+/// downscaling it first and smoothing it afterwards if configured.
+fn working_magnitude_grid<P: PixelSource + ?Sized>(
+    img: &P,
+    config: &DetectionConfig,
+    planner: &mut FftPlanner<f32>,
+    warnings: &mut Vec<String>,
+) -> Result<MagnitudeGrid, DetectError> {
+    let factor = config.downscale.max(1);
+    let mut grid = if factor == 1 {
+        compute_magnitude_grid(img, config, planner, warnings)?
+    } else {
+        warnings.push(downscale_warning(factor));
+        compute_magnitude_grid(&box_downscale(img, factor), config, planner, warnings)?
+    };
+
+    // Clean up speckle and dropouts before regions are extracted
+    if let Some(kernel) = config.smoothing_kernel {
+        grid.smooth(kernel);
+    }
+    Ok(grid)
+}
+
+/// The warning reported when detection runs on a copy downscaled by `factor`.
+fn downscale_warning(factor: u32) -> String {
+    format!("image downscaled by {factor}x before detection; regions are accurate to {factor}px")
+}
+
+/// Picks the number of sections across the width.
+///
+/// An explicit `sections_x` in the config wins; otherwise the count is picked
+/// from the image orientation.
+fn sections_across(width: u32, height: u32, config: &DetectionConfig) -> u32 {
+    if let Some(sections_x) = config.sections_x {
+        return sections_x.max(1);
+    }
+
+    let is_ratio = width <= height;
+    if is_ratio {
+        config.vertical_sections
+    } else {
+        config.horizontal_sections
+    }
+}
+
+/// Width of the sections detection uses on a whole image, measured on the
+/// working (possibly downscaled) image.
+fn working_section_width(width: u32, height: u32, config: &DetectionConfig) -> u32 {
+    let sections = sections_across(width, height, config).min(width).max(1);
+    (width / config.downscale.max(1) / sections).max(1)
+}
+
+/// Bands and sections an image is split into, before any FFT work.
+struct GridLayout {
+    /// `(y_start, band_height)` of every band to scan, top to bottom.
+    bands: Vec<(u32, u32)>,
+    /// Width of each section.
+    section_width: u32,
+    /// Number of sections across the width.
+    sections_per_width: u32,
+    /// Right edge (exclusive) of the last section.
+    scanned_width: u32,
+}
+
+/// Splits an image of `width` x `height` into bands and sections.
+///
+/// # Arguments
+///
+/// * `width` - The width of the analyzed image
+/// * `height` - The height of the analyzed image
+/// * `config` - Detection settings
+/// * `warnings` - Collects descriptions of any compromises in the layout
+///
+/// # Returns
+///
+/// The layout, or `DetectError::ImageTooSmall` / `DetectError::TooManySections`
+/// when the image can't be scanned as configured.
+fn grid_layout(
+    width: u32,
+    height: u32,
+    config: &DetectionConfig,
+    warnings: &mut Vec<String>,
+) -> Result<GridLayout, DetectError> {
+    // A downscale larger than the image leaves nothing to divide into sections
+    if width == 0 || height == 0 {
+        return Err(DetectError::ImageTooSmall { width, height });
+    }
+
+    let sections_per_width = sections_across(width, height, config);
+    // Narrow images can't fit one pixel per section, so shrink the section count
+    if width < sections_per_width {
+        warnings.push(format!(
+            "image width {width}px is narrower than {sections_per_width} sections; section count clamped to {width}"
+        ));
+    }
+    let sections_per_width = sections_per_width.min(width);
+    let section_width = width / sections_per_width;
+    let section_height = config.section_height.max(1);
+    let sections_per_height = (height / section_height) as usize;
+
+    let scanned_width = config.scanned_width(width, section_width, sections_per_width);
+    if scanned_width < width {
+        warnings.push(format!(
+            "rightmost {}px not covered by a whole section and skipped",
+            width - scanned_width
+        ));
+    }
+
+    // Each band is (y_start, band_height); the partial trailing band is optional
+    let mut bands: Vec<(u32, u32)> = (0..sections_per_height)
+        .map(|section_index_y| (section_index_y as u32 * section_height, section_height))
+        .collect();
+    let trailing_rows = height % section_height;
+    if sections_per_height == 0 && trailing_rows > 0 {
+        // Too short for a single band (e.g. a linear scanner line): scan what's there
+        bands.push((0, height));
+    } else if trailing_rows > 0 {
+        match config.trailing_band {
+            TrailingBand::Ignore => warnings.push(format!(
+                "bottom {trailing_rows}px form a partial band and were skipped"
+            )),
+            TrailingBand::ScanClamped => bands.push((height - trailing_rows, trailing_rows)),
+        }
+    }
+
+    // Refuse pathological layouts before doing any FFT work
+    let total_sections = bands.len() as u64 * sections_per_width as u64;
+    if let Some(limit) = config.max_total_sections {
+        if total_sections > limit {
+            return Err(DetectError::TooManySections {
+                sections: total_sections,
+                limit,
+            });
+        }
+    }
+
+    Ok(GridLayout {
+        bands,
+        section_width,
+        sections_per_width,
+        scanned_width,
+    })
+}
+
+/// Splits an image into bands and sections and computes every section's magnitude.
+///
+/// # Arguments
+///
+/// * `img` - A reference to the grayscale image buffer
+/// * `config` - Detection settings
+/// * `planner` - FFT planner to use for frequency analysis
+/// * `warnings` - Collects descriptions of any compromises in the layout
+fn compute_magnitude_grid<P: PixelSource + ?Sized>(
+    img: &P,
+    config: &DetectionConfig,
+    planner: &mut FftPlanner<f32>,
+    warnings: &mut Vec<String>,
+) -> Result<MagnitudeGrid, DetectError> {
+    let width = img.width();
+    let GridLayout {
+        bands,
+        section_width,
+        sections_per_width,
+        scanned_width,
+    } = grid_layout(width, img.height(), config, warnings)?;
+
+    if let Some(dir) = &config.debug_dump_dir {
+        dump_bands(img, &bands, dir)?;
+    }
+
+    // Calculate the amplitude of each horizontal section. Every section has
+    // the same length, so its FFT is planned once with the caller's planner
+    let mut plans = FftPlans::new(planner);
+    plans.prepare(config.fft_len(section_width as usize), config.fft_precision);
+
+    // Bands are independent, so with the `parallel` feature they're spread
+    // over the rayon pool. `FftPlanner` isn't shared between threads, but
+    // the plans it made are, so each worker starts from a copy of them
+    #[cfg(feature = "parallel")]
+    let responses: Vec<Vec<SectionResponse>> = {
+        let shared = plans.share();
+        bands
+            .par_iter()
+            .map_init(
+                || FftPlans::from_shared(shared.clone()),
+                |plans, &(section_y_start, band_height)| {
+                    compute_section_magnitudes(
+                        img,
+                        section_y_start,
+                        band_height,
+                        section_width,
+                        sections_per_width,
+                        config,
+                        plans,
+                    )
+                },
+            )
+            .collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let responses: Vec<Vec<SectionResponse>> = bands
+        .iter()
+        .map(|&(section_y_start, band_height)| {
+            compute_section_magnitudes(
+                img,
+                section_y_start,
+                band_height,
+                section_width,
+                sections_per_width,
+                config,
+                &mut plans,
+            )
+        })
+        .collect();
+    let raw_magnitudes: Vec<Vec<f32>> = responses
+        .iter()
+        .map(|band| band.iter().map(|r| r.magnitude).collect())
+        .collect();
+    let peak_frequencies = responses
+        .iter()
+        .map(|band| band.iter().map(|r| r.peak_frequency).collect())
+        .collect();
+    let threshold = config.section_threshold();
+    let magnitudes = raw_magnitudes
+        .iter()
+        .map(|band| apply_threshold(band, threshold))
+        .collect();
+
+    Ok(MagnitudeGrid {
+        bands,
+        width,
+        section_width,
+        sections_per_width,
+        scanned_width,
+        magnitudes,
+        raw_magnitudes,
+        peak_frequencies,
+    })
+}
+
+/// Writes each band of `img` to `dir` as `band_NNNN.png`, numbered top to bottom.
+fn dump_bands<P: PixelSource + ?Sized>(
+    img: &P,
+    bands: &[(u32, u32)],
+    dir: &Path,
+) -> Result<(), DetectError> {
+    std::fs::create_dir_all(dir).map_err(DetectError::DebugDump)?;
+    for (index, &(y_start, band_height)) in bands.iter().enumerate() {
+        let band: ImageBuffer<Luma<u8>, Vec<u8>> =
+            ImageBuffer::from_fn(img.width(), band_height, |x, y| {
+                Luma([img.luma_at(x, y_start + y)])
+            });
+        band.save(dir.join(format!("band_{index:04}.png")))
+            .map_err(|err| DetectError::DebugDump(std::io::Error::other(err)))?;
+    }
+    Ok(())
+}
+
+/// Computes which sections of an image pass the magnitude threshold and prefilters.
+///
+/// This exposes the boolean grid the consecutive-run logic works on, which
+/// is useful when debugging why a region was or wasn't detected.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// One row per band, each holding one `bool` per section.
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, config = None)))]
+pub fn compute_pass_grid(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<Vec<bool>>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let img = image_view(&img_data, width, height)?;
+
+    let mut planner = FftPlanner::<f32>::new();
+    Ok(working_magnitude_grid(&img, &config, &mut planner, &mut Vec::new())?.pass_grid())
+}
+
+/// Computes the peak frequency of every section, e.g. to pick `min_freq` / `max_freq`.
+///
+/// The peak is the strongest non-DC bin of a section's spectrum, in cycles
+/// per pixel (`0.5` is the Nyquist frequency). A barcode with a narrowest bar
+/// of `n` pixels typically peaks near `1 / (2n)`, while speckle peaks higher
+/// and shading lower.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// One row per band, each holding one frequency per section; `0.0` for
+/// sections rejected by the run-width prefilter.
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, config = None)))]
+pub fn section_peak_frequencies(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<Vec<f32>>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let img = image_view(&img_data, width, height)?;
+
+    let mut planner = FftPlanner::<f32>::new();
+    Ok(working_magnitude_grid(&img, &config, &mut planner, &mut Vec::new())?.peak_frequencies)
+}
+
+/// Computes the fraction of sections that pass the threshold in each band.
+///
+/// A page where most bands show moderate coverage is likely uniformly noisy,
+/// while a clean page has a few high-coverage bands and the rest near zero,
+/// which makes this a cheap scan-quality metric.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// One value in `[0, 1]` per band, top to bottom.
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, config = None)))]
+pub fn band_coverage(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<f32>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let img = image_view(&img_data, width, height)?;
+
+    let mut planner = FftPlanner::<f32>::new();
+    let grid = working_magnitude_grid(&img, &config, &mut planner, &mut Vec::new())?;
+    Ok(grid
+        .pass_grid()
+        .iter()
+        .map(|band| band.iter().filter(|&&passed| passed).count() as f32 / band.len() as f32)
+        .collect())
+}
+
+/// Scores how barcode-like a candidate rectangle is, without running full detection.
+///
+/// The box is split into the same bands and section width that detection
+/// would use for the whole image, so the magnitude threshold stays
+/// comparable, and the score is the fraction of those sections that pass the
+/// threshold and prefilters. This makes it easy to fuse with boxes proposed
+/// by another detector.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `region` - The candidate box; its bounds are clamped to the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A score in `[0, 1]`; `0.0` if the box is smaller than one section.
+/// Fails if `img_data` doesn't match the given dimensions.
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, region, config = None)))]
+pub fn score_region(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    region: BarcodeRegion,
+    config: Option<DetectionConfig>,
+) -> Result<f32, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let img = image_view(&img_data, width, height)?;
+
+    let x_end = region.x_end.min(width);
+    let y_end = region.y_end.min(height);
+    let x_start = region.x_start.min(x_end);
+    let y_start = region.y_start.min(y_end);
+    let crop: ImageBuffer<Luma<u8>, Vec<u8>> =
+        ImageBuffer::from_fn(x_end - x_start, y_end - y_start, |x, y| {
+            Luma([img.luma_at(x_start + x, y_start + y)])
+        });
+    let factor = config.downscale.max(1);
+    let crop = if factor > 1 {
+        box_downscale(&crop, factor)
+    } else {
+        crop
+    };
+
+    // Reuse the section width detection would pick for the whole image
+    let section_width = working_section_width(width, height, &config);
+    let sections_per_width = crop.width() / section_width;
+
+    let band_height = config.section_height.min(crop.height());
+    if sections_per_width == 0 || band_height == 0 {
+        return Ok(0.0);
+    }
+
+    let mut planner = FftPlanner::<f32>::new();
+    let mut plans = FftPlans::new(&mut planner);
+    let mut passed = 0;
+    let mut total = 0;
+    for section_y_start in (0..=crop.height() - band_height).step_by(band_height as usize) {
+        let section_magnitudes: Vec<f32> = compute_section_magnitudes(
+            &crop,
+            section_y_start,
+            band_height,
+            section_width,
+            sections_per_width,
+            &config,
+            &mut plans,
+        )
+        .iter()
+        .map(|r| r.magnitude)
+        .collect();
+        passed += apply_threshold(&section_magnitudes, config.section_threshold())
+            .iter()
+            .filter(|&&m| m > 0.0)
+            .count();
+        total += section_magnitudes.len();
+    }
+
+    Ok(passed as f32 / total as f32)
+}
+
+/// Hashes the pixels of a region, e.g. to cache decode results across frames.
+///
+/// The hash is 64-bit FNV-1a over the region's size and its rows of luma
+/// bytes, so it's cheap to compute but not cryptographic. Identical content
+/// hashes equal wherever it sits in the image; any pixel change, or a change
+/// of the clamped region's size, gives a different hash in all but rare
+/// collisions.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `region` - The region to hash; its bounds are clamped to the image.
+///
+/// # Returns
+///
+/// The 64-bit hash of the region's content.
+/// Fails if `img_data` doesn't match the given dimensions.
+///
+/// # Example
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use bar_dec::{region_content_hash, BarcodeRegion};
+/// let (width, height) = (800, 600);
+/// let frame = vec![255; 800 * 600];
+/// let region = BarcodeRegion::new(100, 300, 50, 80)?;
+/// let mut cache: HashMap<u64, String> = HashMap::new();
+///
+/// let key = region_content_hash(frame, width, height, region.clone())?;
+/// if let Some(value) = cache.get(&key) { /* skip decoding */ }
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+pub fn region_content_hash(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    region: BarcodeRegion,
+) -> Result<u64, DetectError> {
+    let img = image_view(&img_data, width, height)?;
+
+    let x_end = region.x_end.min(width);
+    let y_end = region.y_end.min(height);
+    let x_start = region.x_start.min(x_end);
+    let y_start = region.y_start.min(y_end);
+
+    let size = [x_end - x_start, y_end - y_start];
+    let size_bytes = size.iter().flat_map(|v| v.to_le_bytes());
+    let pixels = (y_start..y_end).flat_map(|y| (x_start..x_end).map(move |x| (x, y)));
+    let pixel_bytes = pixels.map(|(x, y)| img.luma_at(x, y));
+
+    Ok(size_bytes
+        .chain(pixel_bytes)
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        }))
+}
+
+/// Renders the image in RGB with every region outlined in red, for eyeballing detections.
+///
+/// Outlines are drawn inside the region bounds, `thickness` pixels wide.
+/// Regions are clamped to the image first, so a box running past an edge is
+/// outlined along that edge.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `regions` - The regions to outline.
+/// * `thickness` - Width of the outlines in pixels.
+///
+/// # Returns
+///
+/// Interleaved RGB bytes, `width * height * 3` of them. Fails if `img_data`
+/// doesn't match the given dimensions.
+///
+/// # Example
+///
+/// ```python
+/// rgb = house_specific.draw_regions(img_data, width, height, regions, thickness=3)
+/// Image.frombytes("RGB", (width, height), bytes(rgb)).show()
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, regions, thickness = 2)))]
+pub fn draw_regions(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    regions: Vec<BarcodeRegion>,
+    thickness: u32,
+) -> Result<Vec<u8>, DetectError> {
+    let img = image_view(&img_data, width, height)?;
+    let mut canvas: RgbImage = ImageBuffer::from_fn(width, height, |x, y| {
+        let luma = img.luma_at(x, y);
+        Rgb([luma, luma, luma])
+    });
+
+    for region in &regions {
+        let x_end = region.x_end.min(width);
+        let y_end = region.y_end.min(height);
+        let x_start = region.x_start.min(x_end);
+        let y_start = region.y_start.min(y_end);
+        // Nest one-pixel outlines inward until the box is used up
+        for inset in 0..thickness {
+            let outline_width = (x_end - x_start).saturating_sub(inset.saturating_mul(2));
+            let outline_height = (y_end - y_start).saturating_sub(inset.saturating_mul(2));
+            if outline_width == 0 || outline_height == 0 {
+                break;
+            }
+            // Both stay below the image size, but may still exceed `i32::MAX`
+            let corner = |start: u32| i32::try_from(start + inset).unwrap_or(i32::MAX);
+            let outline = imageproc::rect::Rect::at(corner(x_start), corner(y_start))
+                .of_size(outline_width, outline_height);
+            draw_hollow_rect_mut(&mut canvas, outline, REGION_OUTLINE);
+        }
+    }
+
+    Ok(canvas.into_raw())
+}
+
+/// Extracts the raw luma profile along a region's scan line for an external decoder.
+///
+/// The row is the region's `best_scanline_y` when it lies inside the region,
+/// otherwise the region's center row. A `vertical` region has its bars running
+/// across, so it's sampled down the column `best_scanline_y` instead, falling
+/// back to the center column. Values are returned as-is, without
+/// binarization, so decoders like zxing can apply their own thresholding.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `region` - The region to sample; its bounds are clamped to the image.
+///
+/// # Returns
+///
+/// One luma value per column from `x_start` to `x_end` (per row from `y_start`
+/// to `y_end` for a `vertical` region), or an empty vector if the region lies
+/// outside the image.
+#[cfg_attr(feature = "python", pyfunction)]
+pub fn region_scanline(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    region: BarcodeRegion,
+) -> Result<Vec<u8>, DetectError> {
+    let img = image_view(&img_data, width, height)?;
+
+    let x_end = region.x_end.min(width);
+    let y_end = region.y_end.min(height);
+    if region.x_start >= x_end || region.y_start >= y_end {
+        return Ok(Vec::new());
+    }
+    // Vertical-pass regions keep a column in `best_scanline_y`
+    if region.vertical {
+        let x = if (region.x_start..x_end).contains(&region.best_scanline_y) {
+            region.best_scanline_y
+        } else {
+            region.x_start + (x_end - region.x_start) / 2
+        };
+        return Ok((region.y_start..y_end).map(|y| img.luma_at(x, y)).collect());
+    }
+
+    let y = if (region.y_start..y_end).contains(&region.best_scanline_y) {
+        region.best_scanline_y
+    } else {
+        region.y_start + (y_end - region.y_start) / 2
+    };
+
+    Ok((region.x_start..x_end).map(|x| img.luma_at(x, y)).collect())
+}
+
+/// Copies every region out of the image as its own grayscale buffer.
+///
+/// Bounds are clamped to the image first, so regions grown past the edges
+/// (e.g. by `adjust_regions` near the bottom of the page) are cut off instead
+/// of reading out of bounds; a region left without pixels yields an empty
+/// buffer of size `0x0`.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `regions` - The regions to crop.
+///
+/// # Returns
+///
+/// One `(pixels, width, height)` tuple per region, in the order of `regions`,
+/// with `pixels` in row-major order. Fails if `img_data` doesn't match the
+/// given dimensions.
+///
+/// # Example
+///
+/// ```python
+/// for pixels, w, h in house_specific.crop_regions(img_data, width, height, regions):
+///     crop = numpy.frombuffer(bytes(pixels), dtype=numpy.uint8).reshape(h, w)
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+pub fn crop_regions(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    regions: Vec<BarcodeRegion>,
+) -> Result<Vec<(Vec<u8>, u32, u32)>, DetectError> {
+    image_view(&img_data, width, height)?;
+
+    Ok(regions
+        .iter()
+        .map(|region| {
+            let x_end = region.x_end.min(width);
+            let y_end = region.y_end.min(height);
+            let x_start = region.x_start.min(x_end);
+            let y_start = region.y_start.min(y_end);
+            if x_start == x_end || y_start == y_end {
+                return (Vec::new(), 0, 0);
+            }
+
+            let pixels = (y_start..y_end)
+                .flat_map(|y| {
+                    let row = y as usize * width as usize;
+                    &img_data[row + x_start as usize..row + x_end as usize]
+                })
+                .copied()
+                .collect();
+            (pixels, x_end - x_start, y_end - y_start)
+        })
+        .collect())
+}
+
+/// Recommends a `DetectionConfig.threshold` from images with labeled barcode boxes.
+///
+/// Every sample is split into sections as detection would with `config`,
+/// and the unthresholded section magnitudes are collected in two
+/// groups: sections lying fully inside a labeled box (skipping those rejected
+/// by the run-width prefilter, which no threshold can recover) and sections
+/// not touching any box. The threshold with the fewest misclassified sections
+/// is picked, and among equally good ones the one with the widest gap to its
+/// neighbouring magnitudes, i.e. the max-margin split.
+///
+/// # Arguments
+///
+/// * `samples` - `(img_data, width, height, boxes)` tuples, one per image.
+/// * `config` - Optional detection settings the threshold is meant for; the
+///   section layout and magnitudes follow them. `None` uses the defaults.
+///
+/// # Returns
+///
+/// The suggested threshold, or the config's threshold if no section fell
+/// inside a labeled box.
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (samples, config = None)))]
+pub fn suggest_threshold(
+    samples: Vec<(Vec<u8>, u32, u32, Vec<BarcodeRegion>)>,
+    config: Option<DetectionConfig>,
+) -> Result<f32, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let mut planner = FftPlanner::<f32>::new();
+    let mut inside = Vec::new();
+    let mut outside = Vec::new();
+
+    for (img_data, width, height, boxes) in &samples {
+        let img = image_view(img_data, *width, *height)?;
+        let grid = compute_magnitude_grid(&img, &config, &mut planner, &mut Vec::new())?;
+
+        for (&(y_start, band_height), band) in grid.bands.iter().zip(&grid.raw_magnitudes) {
+            let y = y_start + config.line_offset(band_height);
+            for (section, &magnitude) in band.iter().enumerate() {
+                let x_start = clamped_mul(section as u64, grid.section_width, grid.width);
+                let x_end =
+                    section_end(section, band.len(), grid.section_width, grid.scanned_width);
+                let on_row = |r: &BarcodeRegion| r.y_start <= y && y < r.y_end;
+
+                if boxes
+                    .iter()
+                    .any(|r| on_row(r) && r.x_start <= x_start && x_end <= r.x_end)
+                {
+                    if magnitude > 0.0 {
+                        inside.push(magnitude);
+                    }
+                } else if !boxes
+                    .iter()
+                    .any(|r| on_row(r) && r.x_start < x_end && x_start < r.x_end)
+                {
+                    outside.push(magnitude);
+                }
+            }
+        }
+    }
+
+    Ok(separating_threshold(&inside, &outside).unwrap_or(config.threshold))
+}
+
+/// Finds the threshold that best separates `inside` (should pass) from
+/// `outside` (should fail) magnitudes.
+///
+/// Candidates lie halfway between consecutive distinct magnitudes, plus one
+/// below the smallest. A magnitude passes when it's strictly above the
+/// threshold.
+///
+/// # Returns
+///
+/// The candidate with the fewest errors, widest margin first among ties, or
+/// `None` if `inside` is empty.
+fn separating_threshold(inside: &[f32], outside: &[f32]) -> Option<f32> {
+    if inside.is_empty() {
+        return None;
+    }
+
+    let mut labeled: Vec<(f32, bool)> = inside
+        .iter()
+        .map(|&m| (m, true))
+        .chain(outside.iter().map(|&m| (m, false)))
+        .collect();
+    labeled.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // A threshold below everything lets every outside magnitude through
+    let lowest = labeled[0].0;
+    let mut errors = outside.len();
+    let mut best = (errors, lowest, lowest / 2.0);
+
+    for (index, &(magnitude, is_inside)) in labeled.iter().enumerate() {
+        // Moving the threshold past this magnitude flips its classification
+        if is_inside {
+            errors += 1;
+        } else {
+            errors -= 1;
+        }
+
+        let Some(&(next, _)) = labeled.get(index + 1) else {
+            break;
+        };
+        let gap = next - magnitude;
+        if gap > 0.0 && (errors < best.0 || (errors == best.0 && gap > best.1)) {
+            best = (errors, gap, magnitude + gap / 2.0);
+        }
+    }
+
+    Some(best.2)
+}
+
+/// Detects barcode regions in a batch of images, keeping each result with its image ID.
+///
+/// Images are processed in parallel on the rayon thread pool. A failure,
+/// e.g. a buffer that doesn't match its dimensions, only affects its own
+/// entry, so one bad input never hides the results of the others.
+///
+/// # Arguments
+///
+/// * `images` - `(id, img_data, width, height)` tuples; IDs are caller-chosen.
+/// * `config` - Optional detection settings applied to every image.
+///
+/// # Returns
+///
+/// One `(id, result)` pair per image, in input order, where a failed image
+/// carries its error message.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::detect_batch;
+/// let img_data = vec![255; 800 * 600];
+/// let results = detect_batch(vec![("page-1".into(), img_data, 800, 600)], None);
+/// for (id, result) in results {
+///     match result {
+///         Ok(regions) => println!("{id}: {} regions", regions.len()),
+///         Err(err) => eprintln!("{id}: {err}"),
+///     }
+/// }
+/// ```
+pub fn detect_batch(
+    images: Vec<(String, Vec<u8>, u32, u32)>,
+    config: Option<DetectionConfig>,
+) -> Vec<(String, Result<Vec<BarcodeRegion>, String>)> {
+    let config = config.unwrap_or_default();
+    if let Err(err) = config.validate() {
+        return images
+            .into_iter()
+            .map(|(id, ..)| (id, Err(err.to_string())))
+            .collect();
+    }
+
+    #[cfg(feature = "tracing")]
+    let batch_span = tracing::info_span!("detect_batch", images = images.len());
+
+    images
+        .into_par_iter()
+        .map(|(id, img_data, width, height)| {
+            // Rayon workers don't inherit the caller's span, so parent each image explicitly
+            #[cfg(feature = "tracing")]
+            let _entered =
+                tracing::info_span!(parent: &batch_span, "detect_image", id = %id).entered();
+
+            let result = run_detection(&img_data, width, height, &config, &mut Vec::new())
+                .map_err(|err| err.to_string());
+            (id, result)
+        })
+        .collect()
+}
+
+/// Detects barcode regions in a batch of images, keeping each result with its image ID.
+///
+/// Python has no `Result`, so each entry is `(id, regions, error)` with
+/// exactly one of `regions` and `error` set.
+///
+/// # Arguments
+///
+/// * `images` - `(id, img_data, width, height)` tuples; IDs are caller-chosen.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// One `(id, regions, error)` tuple per image, in input order.
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "detect_batch", signature = (images, config = None))]
+fn py_detect_batch(
+    images: Vec<(String, Vec<u8>, u32, u32)>,
+    config: Option<DetectionConfig>,
+) -> Vec<(String, Option<Vec<BarcodeRegion>>, Option<String>)> {
+    detect_batch(images, config)
+        .into_iter()
+        .map(|(id, result)| match result {
+            Ok(regions) => (id, Some(regions), None),
+            Err(err) => (id, None, Some(err)),
+        })
+        .collect()
+}
+
+/// Detects barcode regions in an image delivered as horizontal strips.
+///
+/// For scans too large to hold in memory: `next_strip` is called until it
+/// returns `Ok(None)`, each time yielding the bytes of a strip of whole rows
+/// and the y-offset of its first row. Strips are detected one at a time and
+/// only the regions found so far are kept, so memory stays bounded by the
+/// strip size.
+///
+/// A region reaching the last band of a strip may continue in the next one,
+/// so it is held back until that strip arrives: regions of the next strip
+/// that overlap it horizontally and start in that strip's first band (or
+/// above the held region's bottom edge, for overlapping strips) are merged
+/// into it. Strips must come top to bottom and may overlap; strip
+/// heights that are a multiple of `section_height` (times `downscale`) avoid
+/// unscanned rows at the seams. Every strip is split into as many sections as
+/// the whole image would be, but confidences are normalized per strip.
+///
+/// A region merged across a seam is verified and measured again on the rows
+/// it spans, so the rows of the strips a held-back region covers are kept
+/// until it's finished. `clipped` refers to the edges of the whole image, not
+/// of a strip, and `max_regions` keeps the first regions top to bottom once
+/// every strip is scanned.
+///
+/// # Arguments
+///
+/// * `width` - The width of the image, shared by every strip.
+/// * `height` - The height of the whole image, which picks the section count.
+/// * `next_strip` - Yields `(img_data, y_offset)` for the next strip, or
+///   `None` once the image is exhausted. Errors are passed through.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// The regions of the whole image, in full-image coordinates. Fails with
+/// `DetectError::DimensionMismatch` if a strip doesn't hold whole rows.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::detect_barcode_regions_tiled;
+/// // Strips of 500 rows, as they'd be read from a scan too large to hold
+/// let (width, height) = (2400, 1600);
+/// let mut rows = (0..height).step_by(500).map(|y| {
+///     let strip_height = 500.min(height - y);
+///     (y, vec![255; (width * strip_height) as usize])
+/// });
+/// let regions = detect_barcode_regions_tiled(
+///     width,
+///     height,
+///     || Ok(rows.next().map(|(y, bytes)| (bytes, y))),
+///     None,
+/// )?;
+/// assert!(regions.is_empty());
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+pub fn detect_barcode_regions_tiled<F>(
+    width: u32,
+    height: u32,
+    mut next_strip: F,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError>
+where
+    F: FnMut() -> Result<Option<(Vec<u8>, u32)>, DetectError>,
+{
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    // Strips are wide and short, so pin the layout the whole image would get
+    let config = DetectionConfig {
+        sections_x: Some(sections_across(width, height, &config)),
+        ..config
+    };
+    let band_height = config.section_height.max(1) * config.downscale.max(1);
+    let row_len = width as usize;
+    let mut planner = FftPlanner::new();
+
+    let mut finished = Vec::new();
+    let mut pending: Vec<BarcodeRegion> = Vec::new();
+    // Rows from the top of the highest held-back region down, starting at `held_y`
+    let mut held_rows: Vec<u8> = Vec::new();
+    let mut held_y = 0;
+
+    while let Some((strip, y_offset)) = next_strip()? {
+        if width == 0 || strip.is_empty() {
+            continue;
+        }
+        if strip.len() % row_len != 0 {
+            return Err(DetectError::DimensionMismatch {
+                expected: strip.len().div_ceil(row_len) * row_len,
+                got: strip.len(),
+            });
+        }
+        let strip_height = (strip.len() / row_len) as u32;
+        let strip_y = y_offset;
+
+        // Region counts only make sense for the whole image; the origin is applied at the end
+        let strip_config = DetectionConfig {
+            origin: (0, strip_y),
+            max_regions: None,
+            min_regions: 0,
+            ..config.clone()
+        };
+        let regions = run_detection(&strip, width, strip_height, &strip_config, &mut Vec::new())?;
+
+        // Keep this strip's rows next to those of the held-back regions
+        let held_end = held_y + (held_rows.len() / row_len) as u32;
+        if held_rows.is_empty() || strip_y > held_end {
+            held_rows.clear();
+            held_rows.extend_from_slice(&strip);
+            held_y = strip_y;
+        } else {
+            let overlap = (held_end - strip_y).min(strip_height) as usize;
+            held_rows.extend_from_slice(&strip[overlap * row_len..]);
+        }
+
+        // Attach regions continuing a held-back region from the previous strip
+        let mut carried: Vec<Vec<BarcodeRegion>> =
+            pending.drain(..).map(|region| vec![region]).collect();
+        let mut fresh = Vec::new();
+        for region in regions {
+            // Rows between the strips' last and first full bands go unscanned
+            let starts_at_seam =
+                |held: &BarcodeRegion| region.y_start <= held.y_end.max(strip_y + band_height - 1);
+            let continues = |held: &BarcodeRegion| {
+                held.x_start < region.x_end && region.x_start < held.x_end && starts_at_seam(held)
+            };
+            match carried.iter_mut().find(|group| group.iter().any(continues)) {
+                Some(group) => group.push(region),
+                None => fresh.push(region),
+            }
+        }
+
+        // A lone region passes through as detected; a seam group is finished again
+        let held = image_view(&held_rows, width, (held_rows.len() / row_len) as u32)?;
+        let mut merged = Vec::new();
+        for mut group in carried {
+            if group.len() == 1 {
+                merged.extend(group.pop());
+                continue;
+            }
+            let Some(region) = merge_group(&group, config.confidence_merge) else {
+                continue;
+            };
+            if region.y_start < held_y {
+                // The rows above a gap between strips are gone
+                merged.push(region);
+                continue;
+            }
+            let mut local = BarcodeRegion {
+                y_start: region.y_start - held_y,
+                y_end: region.y_end - held_y,
+                ..region
+            };
+            if finish_region(&held, &mut local, &config, &mut planner) {
+                merged.push(BarcodeRegion {
+                    y_start: local.y_start + held_y,
+                    y_end: local.y_end + held_y,
+                    best_scanline_y: local.best_scanline_y + held_y,
+                    ..local
+                });
+            }
+        }
+
+        // Hold back whatever reaches the last band of this strip
+        let strip_bottom = strip_y + strip_height;
+        for region in merged.into_iter().chain(fresh) {
+            if region.y_end.saturating_add(band_height) > strip_bottom {
+                pending.push(region);
+            } else {
+                finished.push(region);
+            }
+        }
+
+        // Rows above the highest held-back region aren't needed any more
+        match pending.iter().map(|region| region.y_start).min() {
+            Some(keep_from) if keep_from > held_y => {
+                let drop_rows = (keep_from - held_y) as usize;
+                held_rows.drain(..(drop_rows * row_len).min(held_rows.len()));
+                held_y = keep_from;
+            }
+            Some(_) => {}
+            None => held_rows.clear(),
+        }
+    }
+
+    finished.append(&mut pending);
+    if config.dedupe {
+        dedupe_regions(&mut finished, config.confidence_merge);
+    }
+
+    // Strips flag regions at their own edges; only the image edges count
+    let factor = config.downscale.max(1);
+    let working_width = width / factor;
+    let sections = sections_across(working_width, height, &config).min(working_width);
+    let scanned_width = clamped_mul(
+        config.scanned_width(working_width, working_width / sections.max(1), sections) as u64,
+        factor,
+        width,
+    );
+    mark_clipped_regions(&mut finished, scanned_width, height);
+    offset_regions(&mut finished, &config, width, height)?;
+
+    // Keep the first regions top to bottom, as the single-image scan would
+    limit_region_count(finished, &config)
+}
+
+/// Detects barcode regions in an image delivered as an iterable of strips.
+///
+/// # Arguments
+///
+/// * `width` - The width of the image, shared by every strip.
+/// * `height` - The height of the whole image.
+/// * `strips` - An iterable of `(img_data, y_offset)` tuples, top to bottom.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// The regions of the whole image, in full-image coordinates.
+///
+/// # Example
+///
+/// ```python
+/// strips = ((page.read_rows(y, 512), y) for y in range(0, page.height, 512))
+/// regions = house_specific.detect_barcode_regions_tiled(page.width, page.height, strips)
+/// ```
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "detect_barcode_regions_tiled", signature = (width, height, strips, config = None))]
+fn py_detect_barcode_regions_tiled(
+    width: u32,
+    height: u32,
+    strips: &Bound<'_, PyAny>,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let mut strips = strips.try_iter()?;
+    detect_barcode_regions_tiled(
+        width,
+        height,
+        || match strips.next() {
+            Some(strip) => Ok(Some(strip?.extract()?)),
+            None => Ok(None),
+        },
+        config,
+    )
+}
+
+/// Runs detection inside each of several regions of interest in one call.
+///
+/// The image is wrapped once and every ROI is cropped from it, so callers with
+/// many template boxes don't pay the conversion per box. Each ROI is split into
+/// sections as wide as detection would use on the whole image, keeping the
+/// magnitude threshold comparable to a full-page run. `max_regions` and
+/// `min_regions` apply to each ROI on its own.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `rois` - The boxes to search; their bounds are clamped to the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// One vector of regions per ROI, in the order given, with coordinates in the
+/// full image frame. ROIs narrower than one section yield an empty vector.
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, rois, config = None)))]
+pub fn detect_in_rois(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    rois: Vec<BarcodeRegion>,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<Vec<BarcodeRegion>>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let img = image_view(&img_data, width, height)?;
+    let section_width = working_section_width(width, height, &config);
+    let factor = config.downscale.max(1);
+    let mut planner = FftPlanner::new();
+
+    rois.iter()
+        .map(|roi| {
+            let x_end = roi.x_end.min(width);
+            let y_end = roi.y_end.min(height);
+            let x_start = roi.x_start.min(x_end);
+            let y_start = roi.y_start.min(y_end);
+            let sections_x = (x_end - x_start) / factor / section_width;
+            if sections_x == 0 || y_end - y_start < factor {
+                return Ok(Vec::new());
+            }
+
+            // Borrowed buffers can't go through `crop_imm(..).to_image()`, so copy by hand
+            let crop = ImageBuffer::from_fn(x_end - x_start, y_end - y_start, |x, y| {
+                Luma([img.luma_at(x_start + x, y_start + y)])
+            });
+            let (origin_x, origin_y) = config.origin;
+            let roi_config = DetectionConfig {
+                sections_x: Some(sections_x),
+                origin: (
+                    origin_x.saturating_add(x_start),
+                    origin_y.saturating_add(y_start),
+                ),
+                ..config.clone()
+            };
+            let mut barcode_regions =
+                detect_and_offset(&crop, &roi_config, &mut planner, &mut Vec::new())?;
+            normalize_bounds(&mut barcode_regions, &config, width, height);
+            Ok(barcode_regions)
+        })
+        .collect()
+}
+
+/// Runs the detection pipeline on a grayscale image buffer.
+fn detect_in_image<P: PixelSource + ?Sized>(
+    img: &P,
+    config: &DetectionConfig,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    Ok(detect_with_grid(img, config, warnings)?.0)
+}
+
+/// Runs the detection pipeline and also returns the magnitude grid it worked on.
+fn detect_with_grid<P: PixelSource + ?Sized>(
+    img: &P,
+    config: &DetectionConfig,
+    warnings: &mut Vec<String>,
+) -> Result<(Vec<BarcodeRegion>, MagnitudeGrid), DetectError> {
+    detect_with_planner(img, config, &mut FftPlanner::new(), warnings)
+}
+
+/// Runs the detection pipeline with a caller-provided FFT planner, so plans
+/// are shared with earlier runs; see `detect_with_grid`.
+fn detect_with_planner<P: PixelSource + ?Sized>(
+    img: &P,
+    config: &DetectionConfig,
+    planner: &mut FftPlanner<f32>,
+    warnings: &mut Vec<String>,
+) -> Result<(Vec<BarcodeRegion>, MagnitudeGrid), DetectError> {
+    if config.dual_polarity && config.signal == SignalType::Luma {
+        let config = DetectionConfig {
+            dual_polarity: false,
+            auto_invert: false,
+            ..config.clone()
+        };
+        let (mut barcode_regions, grid) = detect_with_planner(img, &config, planner, warnings)?;
+
+        // The inverted pass repeats the same layout, so its warnings and dumps are dropped
+        let inverted_config = DetectionConfig {
+            debug_dump_dir: None,
+            ..config
+        };
+        let (inverted_regions, _) = detect_with_planner(
+            &invert_image(img),
+            &inverted_config,
+            planner,
+            &mut Vec::new(),
+        )?;
+        union_regions(&mut barcode_regions, inverted_regions);
+        return Ok((barcode_regions, grid));
+    }
+
+    // Light-on-dark prints are flipped so bars come out as 0.0 like everywhere else
+    if config.auto_invert && config.signal == SignalType::Luma && has_dark_background(img) {
+        warnings.push("dark background detected; image inverted before detection".to_string());
+        let config = DetectionConfig {
+            auto_invert: false,
+            ..config.clone()
+        };
+        return detect_with_planner(&invert_image(img), &config, planner, warnings);
+    }
+
+    // Detect on an anti-aliased, reduced copy when downscaling is requested
+    let factor = config.downscale.max(1);
+    let grid = working_magnitude_grid(img, config, planner, warnings)?;
+    let min_run = config.min_run(&grid);
+
+    let mut barcode_regions = match config.merge_strategy {
+        MergeStrategy::Rows => {
+            let mut barcode_regions = Vec::new();
+            for (&(section_y_start, band_height), section_magnitudes) in
+                grid.bands.iter().zip(&grid.magnitudes)
+            {
+                // Detects high amplitude areas as barcode areas
+                detect_regions(
+                    section_magnitudes,
+                    section_y_start,
+                    band_height,
+                    grid.section_width,
+                    grid.scanned_width,
+                    min_run,
+                    &mut barcode_regions,
+                );
+            }
+
+            // merge same pos "y"
+            if config.merge_horizontal {
+                merge_barcode_regions(&mut barcode_regions, config.confidence_merge);
+            }
+
+            // merge current pos "y" and next pos "y"
+            if config.merge_vertical {
+                match config.max_vertical_gap {
+                    Some(max_gap) => merge_regions_across_gaps(
+                        &mut barcode_regions,
+                        &grid,
+                        max_gap / factor,
+                        config.confidence_merge,
+                    ),
+                    None => {
+                        merge_regions_if_y_matches(&mut barcode_regions, config.confidence_merge)
+                    }
+                }
+            }
+
+            // merge boxes that overlap, whatever their "y"
+            if let Some(min_iou) = config.merge_iou {
+                merge_overlapping_regions(&mut barcode_regions, min_iou, config.confidence_merge);
+            }
+            barcode_regions
+        }
+        MergeStrategy::ConnectedComponents => connected_component_regions(&grid, min_run),
+    };
+
+    // Drop nested and near-duplicate boxes left over by the merges
+    if config.dedupe {
+        dedupe_regions(&mut barcode_regions, config.confidence_merge);
+    }
+
+    // Tighten boxes that end on a low-energy section
+    if config.trim_ratio > 0.0 {
+        trim_quiet_sections(&mut barcode_regions, &grid, config.trim_ratio);
+    }
+
+    // Estimate the narrow bar width while the regions still match the grid
+    for region in barcode_regions.iter_mut() {
+        region.estimated_module_width = estimate_module_width(&grid, region) * factor as f32;
+    }
+
+    // Normalize confidences against the strongest section in the image
+    let max_magnitude = grid
+        .magnitudes
+        .iter()
+        .flatten()
+        .cloned()
+        .fold(0.0, f32::max);
+    if max_magnitude > 0.0 {
+        for region in barcode_regions.iter_mut() {
+            region.confidence = region.raw_magnitude / max_magnitude;
+        }
+    }
+
+    // Map the regions back to full-resolution coordinates
+    if factor > 1 {
+        upscale_regions(&mut barcode_regions, factor, img.width(), img.height());
+    }
+
+    // Verify, then measure each region at full resolution
+    barcode_regions.retain_mut(|region| finish_region(img, region, config, planner));
+
+    // Flag regions running into the edge of the scanned area
+    mark_clipped_regions(
+        &mut barcode_regions,
+        clamped_mul(grid.scanned_width() as u64, factor, img.width()),
+        clamped_mul(grid.scanned_height() as u64, factor, img.height()),
+    );
+
+    Ok((barcode_regions, grid))
+}
+
+/// Runs the full-resolution checks and measurements on one detected region.
+///
+/// With `config.verify` the region is re-checked first; the rest only looks
+/// at pixels inside the region, so it's the same whether regions come from a
+/// whole grid or one at a time from `RegionIter`.
+///
+/// # Arguments
+///
+/// * `img` - A reference to the full-resolution grayscale image buffer
+/// * `region` - The region, in the coordinates of `img`; updated in place.
+/// * `config` - Detection settings selecting the checks
+/// * `planner` - FFT planner to use for frequency analysis
+///
+/// # Returns
+///
+/// `false` if verification rejected the region, which should then be dropped.
+fn finish_region<P: PixelSource + ?Sized>(
+    img: &P,
+    region: &mut BarcodeRegion,
+    config: &DetectionConfig,
+    planner: &mut FftPlanner<f32>,
+) -> bool {
+    // Drop regions whose crop shows no clear periodic structure
+    if config.verify && !verify_region(img, region, config, planner) {
+        return false;
+    }
+
+    // Pick the row a decoder should scan in the region
+    region.best_scanline_y = if config.best_scanline {
+        best_scanline(img, region, config, planner)
+    } else {
+        (region.y_start + region.y_end) / 2
+    };
+
+    // Measure how far the bars lean, for a deskew step downstream
+    if config.estimate_skew {
+        region.skew_degrees = estimate_skew(img, region, planner);
+    }
+
+    // Count bars and attach an approximate symbology guess; edge maps have no bars to read
+    if config.signal != SignalType::Edges {
+        let profile = region_profile(img, region, config);
+        region.symbology_guess = profile.as_ref().and_then(guess_symbology);
+        region.bar_count = profile.map_or(0, |profile| profile.bar_count);
+    }
+    true
+}
+
+/// Detects character-like regions in a grayscale image by leveraging barcode detection logic.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+/// * `kwargs` - `DetectionConfig` fields to override, e.g. `threshold=40.0`;
+///   applied on top of `config`.
+///
+/// # Returns
+///
+/// A vector of `BarcodeRegion` representing detected character regions.
+///
+/// # Example
+///
+/// ```python
+/// regions = house_specific.detect_character_regions(
+///     img_data, width, height, threshold=40.0, section_height=8
+/// )
+/// for region in regions:
+///     print(region.x_start, region.y_start)
+/// ```
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "detect_character_regions", signature = (img_data, width, height, config = None, **kwargs))]
+fn py_detect_character_regions(
+    py: Python<'_>,
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let config = config.unwrap_or_default().with_overrides(py, kwargs)?;
+    detect_character_regions(&img_data, width, height, Some(config))
+}
+
+/// Detects barcodes and turns them into the character regions printed below them.
+///
+/// Each barcode region has its guard bars stripped and, with the default
+/// `CharacterBox::TextBelow`, is moved onto the text line below the bars;
+/// see `DetectionConfig.character_box`.
+///
+/// # Arguments
+///
+/// * `img_data` - A slice of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// A vector of `BarcodeRegion` representing detected character regions.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::detect_character_regions;
+/// let pixels = vec![255; 800 * 600];
+/// let regions = detect_character_regions(&pixels, 800, 600, None)?;
+/// assert!(regions.is_empty());
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+pub fn detect_character_regions(
+    img_data: &[u8],
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let config = config.unwrap_or_default();
+    // Regions come back in the origin's frame, so the image bounds move with it
+    let (origin_x, origin_y) = config.origin;
+    let (dpi, character_box) = (config.dpi, config.character_box);
+
+    // Detect barcode-like regions using the barcode detection logic
+    let mut barcode_regions =
+        detect_barcode_regions_slice(img_data, width, height, Some(config.clone()))?;
+
+    // Adjust the detected regions for better alignment and scaling
+    adjust_regions(
+        &mut barcode_regions,
+        origin_x + width,
+        origin_y + height,
+        dpi,
+        character_box,
+    );
+    normalize_bounds(&mut barcode_regions, &config, width, height);
+
+    Ok(barcode_regions)
+}
+
+/// Detects the barcode regions that appear in `img_b` but not in `img_a`.
+///
+/// Useful for before/after checks, e.g. whether a printer added a barcode.
+/// Both images are scanned with the same settings; a region of `img_b`
+/// counts as unchanged when its intersection over union with some region of
+/// `img_a` reaches `DIFF_IOU`.
+///
+/// # Arguments
+///
+/// * `img_a` - The grayscale "before" image.
+/// * `img_b` - The grayscale "after" image, of the same size.
+/// * `width` - The width of both images.
+/// * `height` - The height of both images.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// The regions of `img_b` without a match in `img_a`, in detection order.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{detect_diff, DetectionConfig};
+/// // The printer added 3px bars over 200..600 x 200..300 to a blank label
+/// let before = vec![255; 800 * 600];
+/// let mut after = before.clone();
+/// for y in 200..300 {
+///     for x in (200..600).filter(|x| x % 6 < 3) {
+///         after[y * 800 + x] = 0;
+///     }
+/// }
+/// let config = DetectionConfig { sections_x: Some(12), ..Default::default() };
+/// let added = detect_diff(before, after, 800, 600, Some(config))?;
+/// assert!(!added.is_empty(), "no barcode was printed");
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_a, img_b, width, height, config = None)))]
+pub fn detect_diff(
+    img_a: Vec<u8>,
+    img_b: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let regions_a = run_detection(&img_a, width, height, &config, &mut Vec::new())?;
+    let regions_b = run_detection(&img_b, width, height, &config, &mut Vec::new())?;
+
+    Ok(regions_b
+        .into_iter()
+        .filter(|b| regions_a.iter().all(|a| region_iou(a, b) < DIFF_IOU))
+        .collect())
+}
+
+/// Classifies a page as blank, text only, barcode only or mixed, for document routing.
+///
+/// Barcodes come from the regular detection pipeline. The rest of the page
+/// is judged by its ink coverage: the fraction of dark (`< 128`) pixels
+/// outside every barcode and the character region below it (see
+/// `adjust_regions`). A page whose coverage stays below `INK_COVERAGE`
+/// counts as having no ink.
+///
+/// # Arguments
+///
+/// * `img_data` - A vector of `u8` representing the grayscale image data.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// The `PageClass` of the page.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{classify_page, PageClass};
+/// let blank = vec![255; 800 * 600];
+/// assert_eq!(classify_page(blank, 800, 600, None)?, PageClass::Blank);
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (img_data, width, height, config = None)))]
+pub fn classify_page(
+    img_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    config: Option<DetectionConfig>,
+) -> Result<PageClass, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let img = image_view(&img_data, width, height)?;
+
+    // Work in image coordinates; the origin only matters to callers
+    let barcode_regions = detect_in_image(&img, &config, &mut Vec::new())?;
+    let mut character_regions = barcode_regions.clone();
+    adjust_regions(
+        &mut character_regions,
+        width,
+        height,
+        config.dpi,
+        CharacterBox::TextBelow,
+    );
+
+    let covered = |x: u32, y: u32| {
+        barcode_regions
+            .iter()
+            .chain(&character_regions)
+            .any(|r| r.x_start <= x && x < r.x_end && r.y_start <= y && y < r.y_end)
+    };
+    let ink = img
+        .enumerate_pixels()
+        .filter(|&(x, y, pixel)| pixel[0] < 128 && !covered(x, y))
+        .count();
+    let has_ink = ink as f32 > INK_COVERAGE * (width as f32 * height as f32);
+
+    Ok(match (barcode_regions.is_empty(), has_ink) {
+        (true, false) => PageClass::Blank,
+        (true, true) => PageClass::TextOnly,
+        (false, false) => PageClass::BarcodePresent,
+        (false, true) => PageClass::Mixed,
+    })
+}
+
+/// Drops regions flagged as clipped by the image border.
+///
+/// Barcodes that are partially outside the frame decode poorly, so callers
+/// that only want complete symbols can apply this after detection.
+///
+/// # Arguments
+///
+/// * `regions` - The detected regions.
+///
+/// # Returns
+///
+/// The regions whose `clipped` flag is `false`, in their original order.
+#[cfg_attr(feature = "python", pyfunction)]
+pub fn drop_clipped_regions(regions: Vec<BarcodeRegion>) -> Vec<BarcodeRegion> {
+    regions.into_iter().filter(|r| !r.clipped).collect()
+}
+
+/// Converts regions to normalized coordinates, see `BarcodeRegion::normalized`.
+///
+/// Detection reports pixels, plus these fractions in `normalized_bounds` when
+/// `DetectionConfig.normalize` is on; this converts regions detected without it.
+///
+/// # Arguments
+///
+/// * `regions` - The regions to convert.
+/// * `width` - The width of the image the regions were detected in.
+/// * `height` - The height of the image the regions were detected in.
+///
+/// # Returns
+///
+/// One `(x_start, x_end, y_start, y_end)` tuple in `0.0..=1.0` per region, in order.
+///
+/// # Example
+///
+/// ```python
+/// regions = house_specific.detect_barcode_regions(data, width, height)
+/// for x0, x1, y0, y1 in house_specific.normalize_regions(regions, width, height):
+///     draw.rectangle((x0 * view_w, y0 * view_h, x1 * view_w, y1 * view_h))
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+pub fn normalize_regions(
+    regions: Vec<BarcodeRegion>,
+    width: u32,
+    height: u32,
+) -> Vec<(f32, f32, f32, f32)> {
+    regions
+        .iter()
+        .map(|region| region.normalized(width, height))
+        .collect()
+}
+
+/// Drops regions that are too small or have the wrong shape to be a barcode.
+///
+/// Noise tends to show up as thin slivers one section tall, or as nearly
+/// square blobs, while barcodes are wide and short. The aspect ratio is
+/// `width / height`; a region of zero width has an aspect ratio of `0.0`, even
+/// when its height is zero too, and any other region of zero height counts
+/// as infinitely wide.
+///
+/// # Arguments
+///
+/// * `regions` - The detected regions.
+/// * `min_width` - Smallest width, in pixels, a region may have.
+/// * `min_height` - Smallest height, in pixels, a region may have.
+/// * `min_aspect` - Smallest `width / height` a region may have.
+/// * `max_aspect` - Largest `width / height` a region may have.
+///
+/// # Returns
+///
+/// The regions within all four bounds, in their original order.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{filter_regions, BarcodeRegion};
+/// let regions = vec![
+///     BarcodeRegion::new(0, 200, 0, 50)?,
+///     BarcodeRegion::new(300, 360, 0, 55)?,
+/// ];
+/// // Keep regions at least 40x10 and at least 1.5 times as wide as tall
+/// let regions = filter_regions(regions, 40, 10, 1.5, f32::INFINITY);
+/// assert_eq!(regions.len(), 1);
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+#[cfg_attr(feature = "python", pyo3(signature = (regions, min_width = 0, min_height = 0, min_aspect = 0.0, max_aspect = f32::INFINITY)))]
+pub fn filter_regions(
+    regions: Vec<BarcodeRegion>,
+    min_width: u32,
+    min_height: u32,
+    min_aspect: f32,
+    max_aspect: f32,
+) -> Vec<BarcodeRegion> {
+    regions
+        .into_iter()
+        .filter(|r| {
+            let width = r.x_end.saturating_sub(r.x_start);
+            let height = r.y_end.saturating_sub(r.y_start);
+            // 0/0 would be NaN and fail every bound
+            let aspect = if width == 0 {
+                0.0
+            } else {
+                width as f32 / height as f32
+            };
+            width >= min_width
+                && height >= min_height
+                && (min_aspect..=max_aspect).contains(&aspect)
+        })
+        .collect()
+}
+
+/// Computes the bounding box of the confident regions, e.g. for auto-cropping.
+///
+/// Regions below `min_confidence` are treated as noise and ignored, so a weak
+/// stray detection doesn't stretch the box across the page.
+///
+/// # Arguments
+///
+/// * `regions` - The detected regions.
+/// * `min_confidence` - Lowest `confidence` a region needs to count.
+///
+/// # Returns
+///
+/// The union of the confident regions, carrying their highest confidence, or
+/// `None` if no region reaches `min_confidence`.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{confident_bounds, BarcodeRegion};
+/// use image::{imageops, GenericImageView, GrayImage};
+///
+/// let img = GrayImage::new(800, 600);
+/// let regions = vec![
+///     BarcodeRegion { confidence: 0.9, ..BarcodeRegion::new(100, 300, 50, 100)? },
+///     BarcodeRegion { confidence: 0.1, ..BarcodeRegion::new(700, 720, 500, 510)? },
+/// ];
+/// if let Some(bounds) = confident_bounds(regions, 0.5) {
+///     let rect = bounds.to_rect();
+///     let crop = imageops::crop_imm(&img, rect.x, rect.y, rect.width, rect.height);
+///     assert_eq!(crop.dimensions(), (200, 50));
+/// }
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+pub fn confident_bounds(regions: Vec<BarcodeRegion>, min_confidence: f32) -> Option<BarcodeRegion> {
+    regions
+        .into_iter()
+        .filter(|r| r.confidence >= min_confidence)
+        .map(|r| BarcodeRegion {
+            x_start: r.x_start,
+            x_end: r.x_end,
+            y_start: r.y_start,
+            y_end: r.y_end,
+            confidence: r.confidence,
+            ..Default::default()
+        })
+        .reduce(|bounds, r| BarcodeRegion {
+            x_start: bounds.x_start.min(r.x_start),
+            x_end: bounds.x_end.max(r.x_end),
+            y_start: bounds.y_start.min(r.y_start),
+            y_end: bounds.y_end.max(r.y_end),
+            confidence: bounds.confidence.max(r.confidence),
+            ..Default::default()
+        })
+}
+
+/// Buckets regions into fixed columns, e.g. for multi-up label sheets.
+///
+/// Column `i` spans `column_edges[i]..column_edges[i + 1]`, so `n + 1` edges
+/// describe `n` columns. A region belongs to the column its horizontal center
+/// falls into; regions outside every column are tagged with `NO_COLUMN`.
+///
+/// # Arguments
+///
+/// * `regions` - The detected regions.
+/// * `column_edges` - Ascending x-coordinates of the column boundaries.
+///
+/// # Returns
+///
+/// Each region paired with its column index, in the original order.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{assign_columns, BarcodeRegion};
+/// let regions = vec![
+///     BarcodeRegion::new(100, 500, 0, 50)?,
+///     BarcodeRegion::new(900, 1300, 0, 50)?,
+/// ];
+/// let tagged = assign_columns(regions, vec![0, 800, 1600, 2400]);
+/// let first_column = tagged.iter().filter(|(column, _)| *column == 0);
+/// assert_eq!(first_column.count(), 1);
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+#[cfg_attr(feature = "python", pyfunction)]
+pub fn assign_columns(
+    regions: Vec<BarcodeRegion>,
+    column_edges: Vec<u32>,
+) -> Vec<(usize, BarcodeRegion)> {
+    regions
+        .into_iter()
+        .map(|region| {
+            let center = region.x_start + region.x_end.saturating_sub(region.x_start) / 2;
+            let column = column_edges
+                .windows(2)
+                .position(|edges| edges[0] <= center && center < edges[1])
+                .unwrap_or(NO_COLUMN);
+            (column, region)
+        })
+        .collect()
+}
+
+/// Decides whether an image has a dark background by sampling its margins.
+///
+/// The outer 5% of rows and columns on every side (at least one pixel) is
+/// treated as background; the image counts as dark when its mean luma is
+/// below the binarization cutoff of 128.
+fn has_dark_background<P: PixelSource + ?Sized>(img: &P) -> bool {
+    let (width, height) = img.dimensions();
+    let margin = (width.min(height) / 20).max(1);
+
+    let mut sum = 0u64;
+    let mut count = 0u64;
+    for y in 0..height {
+        // Border rows are sampled whole, the rest only at their two ends
+        let (left, right) = if y < margin || y + margin >= height {
+            (width, width)
+        } else {
+            (margin.min(width), width.saturating_sub(margin).max(margin))
+        };
+        for x in (0..left).chain(right..width) {
+            sum += img.luma_at(x, y) as u64;
+            count += 1;
+        }
+    }
+
+    count > 0 && sum < 128 * count
+}
+
+/// Returns a copy of `img` with every luma value flipped.
+fn invert_image<P: PixelSource + ?Sized>(img: &P) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+    ImageBuffer::from_fn(img.width(), img.height(), |x, y| {
+        Luma([255 - img.luma_at(x, y)])
+    })
+}
+
+/// Adds the regions of a second detection pass, deduplicating overlaps.
+///
+/// A region overlapping one already in `barcode_regions` is dropped, unless
+/// only it has a symbology guess, in which case it replaces the existing one.
+///
+/// # Arguments
+///
+/// * `barcode_regions` - The regions of the first pass, extended in place.
+/// * `other` - The regions of the second pass.
+fn union_regions(barcode_regions: &mut Vec<BarcodeRegion>, other: Vec<BarcodeRegion>) {
+    for region in other {
+        match barcode_regions
+            .iter_mut()
+            .find(|r| regions_overlap(r, &region))
+        {
+            Some(existing) => {
+                if existing.symbology_guess.is_none() && region.symbology_guess.is_some() {
+                    *existing = region;
+                }
+            }
+            None => barcode_regions.push(region),
+        }
+    }
+}
+
+/// Downsamples a grayscale image by averaging `factor` x `factor` pixel blocks.
+///
+/// Averaging acts as a box filter, so fine detail is anti-aliased instead of
+/// being skipped as plain subsampling would. Trailing rows and columns that
+/// don't fill a whole block are dropped.
+///
+/// # Arguments
+///
+/// * `img` - A reference to the grayscale image buffer
+/// * `factor` - The downsampling factor along each axis.
+///
+/// # Returns
+///
+/// A new image of size `(width / factor, height / factor)`, which is empty
+/// when `factor` exceeds either dimension.
+fn box_downscale<P: PixelSource + ?Sized>(img: &P, factor: u32) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+    // In u64, so neither the area nor the sum of a huge block can overflow
+    let block_area = factor as u64 * factor as u64;
+
+    ImageBuffer::from_fn(img.width() / factor, img.height() / factor, |x, y| {
+        let mut sum = 0;
+        for dy in 0..factor {
+            for dx in 0..factor {
+                sum += img.luma_at(x * factor + dx, y * factor + dy) as u64;
+            }
+        }
+        Luma([(sum / block_area) as u8])
+    })
+}
+
+/// Multiplies a section index or coordinate by a pixel size without overflowing.
+///
+/// The product is computed in `u64`, so very wide images (e.g. panoramic scans
+/// beyond 65536px) can't wrap around `u32`, and the result is clamped to `limit`.
+fn clamped_mul(value: u64, factor: u32, limit: u32) -> u32 {
+    (value * factor as u64).min(limit as u64) as u32
+}
+
+/// Right edge (exclusive) of section `index` out of `sections`.
+///
+/// The last section ends at `scanned_width`, which lies past a multiple of
+/// `section_width` when it takes in the leftover columns.
+fn section_end(index: usize, sections: usize, section_width: u32, scanned_width: u32) -> u32 {
+    if index + 1 >= sections {
+        scanned_width
+    } else {
+        clamped_mul(index as u64 + 1, section_width, scanned_width)
+    }
+}
+
+/// Scales region coordinates up by `factor`, clamping them to the image bounds.
+///
+/// # Arguments
+///
+/// * `barcode_regions` - A mutable reference to the regions to scale.
+/// * `factor` - The factor the image was downsampled by.
+/// * `width` - The full-resolution width of the image.
+/// * `height` - The full-resolution height of the image.
+fn upscale_regions(barcode_regions: &mut [BarcodeRegion], factor: u32, width: u32, height: u32) {
+    for region in barcode_regions.iter_mut() {
+        region.x_start = clamped_mul(region.x_start as u64, factor, width);
+        region.x_end = clamped_mul(region.x_end as u64, factor, width);
+        region.y_start = clamped_mul(region.y_start as u64, factor, height);
+        region.y_end = clamped_mul(region.y_end as u64, factor, height);
+    }
+}
+
+/// Checks that a region's crop contains a strong periodic component.
+///
+/// The center row of the region is binarized as `config.binarization` says,
+/// its mean removed and a single
+/// FFT is run over the whole width. A real barcode concentrates energy around
+/// its module frequency, so the strongest bin stands well above the average
+/// bin, while noise and text spread their energy evenly.
+///
+/// # Arguments
+///
+/// * `img` - A reference to the grayscale image buffer
+/// * `region` - The region to verify; its bounds are clamped to the image.
+/// * `config` - Detection settings controlling the binarization
+/// * `planner` - FFT planner to use for frequency analysis
+///
+/// # Returns
+///
+/// `true` if the peak bin exceeds `VERIFY_PEAK_RATIO` times the mean bin.
+fn verify_region<P: PixelSource + ?Sized>(
+    img: &P,
+    region: &BarcodeRegion,
+    config: &DetectionConfig,
+    planner: &mut FftPlanner<f32>,
+) -> bool {
+    let x_end = region.x_end.min(img.width());
+    if region.x_start + 4 > x_end || img.height() == 0 {
+        return false;
+    }
+    let y = ((region.y_start + region.y_end) / 2).min(img.height() - 1);
+
+    let line: Vec<u8> = (region.x_start..x_end).map(|x| img.luma_at(x, y)).collect();
+    let binary_line = binarize_luma_line(&line, config);
+    let mean = binary_line.iter().sum::<f32>() / binary_line.len() as f32;
+
+    let mut input: Vec<Complex<f32>> = binary_line
+        .iter()
+        .map(|&x| Complex::new(x - mean, 0.0))
+        .collect();
+    planner.plan_fft_forward(input.len()).process(&mut input);
+
+    let magnitudes: Vec<f32> = input[1..=input.len() / 2]
+        .iter()
+        .map(|c| c.norm())
+        .collect();
+    let peak = magnitudes.iter().cloned().fold(0.0, f32::max);
+    let mean_magnitude = magnitudes.iter().sum::<f32>() / magnitudes.len() as f32;
+
+    mean_magnitude > 0.0 && peak > VERIFY_PEAK_RATIO * mean_magnitude
+}
+
+/// Finds the row of a region whose scanline has the strongest frequency response.
+///
+/// Every row of the region is sampled across the region's width and run
+/// through the same signal conversion, run-width prefilter and FFT as the
+/// section analysis, so a row crossed by a scratch or smudge is skipped in
+/// favor of a clean one.
+///
+/// # Arguments
+///
+/// * `img` - A reference to the grayscale image buffer
+/// * `region` - The region to scan; its bounds are clamped to the image.
+/// * `config` - Detection settings controlling the signal type
+/// * `planner` - FFT planner to use for frequency analysis
+///
+/// # Returns
+///
+/// The y-coordinate of the strongest row; the first one wins ties, and
+/// `y_start` is returned when no row passes the prefilter.
+fn best_scanline<P: PixelSource + ?Sized>(
+    img: &P,
+    region: &BarcodeRegion,
+    config: &DetectionConfig,
+    planner: &mut FftPlanner<f32>,
+) -> u32 {
+    let x_end = region.x_end.min(img.width());
+    let y_end = region.y_end.min(img.height());
+    if region.x_start >= x_end || region.y_start >= y_end {
+        return region.y_start;
+    }
+    let fft = planner.plan_fft_forward((x_end - region.x_start) as usize);
+
+    let mut best = (region.y_start, f32::MIN);
+    for y in region.y_start..y_end {
+        let line: Vec<u8> = (region.x_start..x_end).map(|x| img.luma_at(x, y)).collect();
+        // A smear or scratch leaves long runs, which disqualify the row
+        let Some(signal_line) = signal_line(&line, config) else {
+            continue;
+        };
+        let signal_line = window_line(signal_line, config.fft_window);
+
+        let mut spectrum: Vec<Complex<f32>> =
+            signal_line.iter().map(|&x| Complex::new(x, 0.0)).collect();
+        fft.process(&mut spectrum);
+
+        let magnitude = spectrum_magnitude(&spectrum, config);
+        if magnitude > best.1 {
+            best = (y, magnitude);
+        }
+    }
+
+    best.0
+}
+
+/// Estimates how far the bars of a region lean away from vertical.
+///
+/// For every angle from `-SKEW_MAX_DEGREES` to `SKEW_MAX_DEGREES` in
+/// `SKEW_STEP_DEGREES` steps, the region's rows are averaged along lines
+/// tilted by that angle into a single profile. When the tilt matches the bars
+/// the profile keeps their full contrast; otherwise neighbouring bars blur
+/// together. The angle whose profile has the strongest non-DC FFT bin wins.
+///
+/// # Arguments
+///
+/// * `img` - A reference to the grayscale image buffer
+/// * `region` - The region to measure; its bounds are clamped to the image.
+/// * `planner` - FFT planner to use for frequency analysis
+///
+/// # Returns
+///
+/// The tilt in degrees, positive when the tops of the bars lean right; ties
+/// go to the smaller tilt, and `0.0` is returned for an empty region.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// // Bars drawn with their tops shifted 3 px right per 100 rows
+/// let skew = estimate_skew(&img, &region, &mut FftPlanner::new());
+/// assert!((skew - 2.0).abs() <= 1.0);
+/// ```
+fn estimate_skew<P: PixelSource + ?Sized>(
+    img: &P,
+    region: &BarcodeRegion,
+    planner: &mut FftPlanner<f32>,
+) -> f32 {
+    let x_end = region.x_end.min(img.width());
+    let y_end = region.y_end.min(img.height());
+    if region.x_start + 4 > x_end || region.y_start >= y_end {
+        return 0.0;
+    }
+    let center_y = (region.y_start + y_end) as f32 / 2.0;
+    let rows = (y_end - region.y_start) as f32;
+    let fft = planner.plan_fft_forward((x_end - region.x_start) as usize);
+
+    // Smallest tilts first, so they win ties
+    let steps = (SKEW_MAX_DEGREES / SKEW_STEP_DEGREES).round() as i32;
+    let angles = (0..=steps).flat_map(|step| [step, -step]).skip(1);
+
+    let mut best = (0.0, f32::MIN);
+    for angle in angles.map(|step| step as f32 * SKEW_STEP_DEGREES) {
+        let slope = angle.to_radians().tan();
+        let profile: Vec<f32> = (region.x_start..x_end)
+            .map(|x| {
+                (region.y_start..y_end)
+                    .map(|y| {
+                        // Rows above the center shift right for a positive tilt
+                        let shifted = x as f32 + (center_y - y as f32) * slope;
+                        let sample_x = shifted.round().clamp(0.0, (img.width() - 1) as f32);
+                        img.luma_at(sample_x as u32, y) as f32
+                    })
+                    .sum::<f32>()
+                    / rows
+            })
+            .collect();
+        let mean = profile.iter().sum::<f32>() / profile.len() as f32;
+
+        let mut spectrum: Vec<Complex<f32>> = profile
+            .iter()
+            .map(|&value| Complex::new(value - mean, 0.0))
+            .collect();
+        fft.process(&mut spectrum);
+
+        let peak = spectrum[1..=spectrum.len() / 2]
+            .iter()
+            .map(|c| c.norm())
+            .fold(0.0, f32::max);
+        if peak > best.1 {
+            best = (angle, peak);
+        }
+    }
+
+    best.0
+}
+
+/// Chooses the row of a band that the section lines are sampled from.
+///
+/// # Arguments
+///
+/// * `img` - A reference to the grayscale image buffer
+/// * `section_y_start` - The first row of the band
+/// * `band_height` - Height of the band
+/// * `scanned_width` - Number of columns, from the left edge, the sections cover
+/// * `config` - Detection settings selecting the criterion
+///
+/// # Returns
+///
+/// The y-coordinate of the chosen row; ties go to the upper row.
+fn pick_scan_row<P: PixelSource + ?Sized>(
+    img: &P,
+    section_y_start: u32,
+    band_height: u32,
+    scanned_width: u32,
+    config: &DetectionConfig,
+) -> u32 {
+    if config.row_pick == RowPick::Center {
+        return section_y_start + config.line_offset(band_height);
+    }
+
+    let mut best = (section_y_start, f32::MIN);
+    for y in section_y_start..section_y_start + band_height {
+        let pixels = (0..scanned_width).map(|x| img.luma_at(x, y) as f32);
+        let count = scanned_width.max(1) as f32;
+        let mean = pixels.clone().sum::<f32>() / count;
+
+        // Higher score wins
+        let score = match config.row_pick {
+            RowPick::MaxVariance => pixels.map(|p| (p - mean) * (p - mean)).sum::<f32>() / count,
+            RowPick::MinMean | RowPick::Center => -mean,
+        };
+        if score > best.1 {
+            best = (y, score);
+        }
+    }
+    best.0
+}
+
+/// Computes the magnitude of each section's frequency response along a specified horizontal line.
+///
+/// The threshold is not applied here; sections rejected by the run-width
+/// prefilter hold `0.0`. With `config.scan_lines` above 1, that many evenly
+/// spaced rows of the band are analyzed and each section's magnitudes are
+/// combined per `config.scan_line_merge`; the peak frequency is the one of the
+/// strongest row. The last section runs to the right edge per
+/// `config.trailing_columns`.
+///
+/// # Arguments
+///
+/// * `img` - A reference to the grayscale image buffer
+/// * `section_y_start` - The y-coordinate to start from
+/// * `band_height` - Height of the band; the sampled row is chosen by `config.row_pick`
+/// * `section_width` - Width of each section
+/// * `sections_per_width` - Number of sections across the width
+/// * `config` - Detection settings controlling the signal and run limit
+/// * `plans` - FFT plans shared by the sections
+fn compute_section_magnitudes<P: PixelSource + ?Sized>(
+    img: &P,
+    section_y_start: u32,
+    band_height: u32,
+    section_width: u32,
+    sections_per_width: u32,
+    config: &DetectionConfig,
+    plans: &mut FftPlans,
+) -> Vec<SectionResponse> {
+    let scanned_width = config.scanned_width(img.width(), section_width, sections_per_width);
+    let scan_lines = config.scan_lines.clamp(1, band_height.max(1));
+    let rows: Vec<u32> = if scan_lines > 1 {
+        // Evenly spaced strictly inside the band
+        (1..=scan_lines)
+            .map(|i| section_y_start + (i * band_height / (scan_lines + 1)).min(band_height - 1))
+            .collect()
+    } else {
+        vec![pick_scan_row(
+            img,
+            section_y_start,
+            band_height,
+            scanned_width,
+            config,
+        )]
+    };
+
+    (0..sections_per_width)
+        .map(|section_index_x| {
+            let section_x_start = clamped_mul(section_index_x as u64, section_width, img.width());
+            let section_x_end = section_end(
+                section_index_x as usize,
+                sections_per_width as usize,
+                section_width,
+                scanned_width,
+            );
+            let line_width = section_x_end - section_x_start;
+            // A last section widened over the leftover columns sums more bins;
+            // scale it back like `section_threshold` does for a downscale
+            let width_scale = (section_width as f32 / line_width.max(1) as f32).powf(1.5);
+            let responses: Vec<SectionResponse> = rows
+                .iter()
+                .map(|&line_y| {
+                    let response =
+                        section_magnitude(img, section_x_start, line_width, line_y, config, plans);
+                    SectionResponse {
+                        magnitude: response.magnitude * width_scale,
+                        ..response
+                    }
+                })
+                .collect();
+            let strongest = responses
+                .iter()
+                .copied()
+                .max_by(|a, b| a.magnitude.total_cmp(&b.magnitude))
+                .unwrap_or_default();
+            let magnitude = match config.scan_line_merge {
+                ScanLineMerge::Mean => {
+                    responses.iter().map(|r| r.magnitude).sum::<f32>() / responses.len() as f32
+                }
+                ScanLineMerge::Max => strongest.magnitude,
+            };
+            SectionResponse {
+                magnitude,
+                peak_frequency: strongest.peak_frequency,
+            }
+        })
+        .collect()
+}
+
+/// Frequency response of one section.
+#[derive(Debug, Clone, Copy, Default)]
+struct SectionResponse {
+    /// Summed spectrum magnitude; `0.0` when a prefilter rejected the section.
+    magnitude: f32,
+    /// Frequency of the strongest non-DC bin in cycles per pixel, from just
+    /// above `0.0` up to `0.5`; `0.0` when the line failed the run-width prefilter.
+    peak_frequency: f32,
+}
+
+/// Forward FFT plans by length, so the planner is consulted once per length.
+///
+/// `FftPlanner` caches plans itself, but looking one up still costs a hash
+/// lookup and an `Arc` clone; sections share their length, so a local map
+/// skips those for all but the first section.
+///
+/// `f64` plans for `FftPrecision::Double` come from a planner of their own,
+/// created on first use, and so are the `f32` plans of a copy made from
+/// `SharedPlans`, which has the caller's plans but not their planner.
+struct FftPlans<'a> {
+    planner: Option<&'a mut FftPlanner<f32>>,
+    own_planner: Option<FftPlanner<f32>>,
+    plans: HashMap<usize, Arc<dyn Fft<f32>>>,
+    planner_f64: Option<FftPlanner<f64>>,
+    plans_f64: HashMap<usize, Arc<dyn Fft<f64>>>,
+}
+
+impl<'a> FftPlans<'a> {
+    fn new(planner: &'a mut FftPlanner<f32>) -> Self {
+        FftPlans {
+            planner: Some(planner),
+            own_planner: None,
+            plans: HashMap::new(),
+            planner_f64: None,
+            plans_f64: HashMap::new(),
+        }
+    }
+
+    /// Plans the forward FFT of `len` in `precision` ahead of its first use.
+    fn prepare(&mut self, len: usize, precision: FftPrecision) {
+        match precision {
+            FftPrecision::Single => {
+                self.forward(len);
+            }
+            FftPrecision::Double => {
+                self.forward_f64(len);
+            }
+        }
+    }
+
+    /// Copies the plans made so far, without the planners, for other threads.
+    #[cfg(feature = "parallel")]
+    fn share(&self) -> SharedPlans {
+        SharedPlans {
+            plans: self.plans.clone(),
+            plans_f64: self.plans_f64.clone(),
+        }
+    }
+
+    /// Starts from plans made on another thread; lengths they don't cover are
+    /// planned with a planner of this copy's own.
+    #[cfg(feature = "parallel")]
+    fn from_shared(shared: SharedPlans) -> FftPlans<'static> {
+        FftPlans {
+            planner: None,
+            own_planner: None,
+            plans: shared.plans,
+            planner_f64: None,
+            plans_f64: shared.plans_f64,
+        }
+    }
+
+    /// Returns the `f64` forward plan for `len`, planning it on first use.
+    fn forward_f64(&mut self, len: usize) -> &dyn Fft<f64> {
+        let planner = self.planner_f64.get_or_insert_with(FftPlanner::new);
+        &**self
+            .plans_f64
+            .entry(len)
+            .or_insert_with(|| planner.plan_fft_forward(len))
+    }
+
+    /// Returns the forward plan for `len`, planning it on first use.
+    fn forward(&mut self, len: usize) -> &dyn Fft<f32> {
+        let planner = match &mut self.planner {
+            Some(planner) => &mut **planner,
+            None => self.own_planner.get_or_insert_with(FftPlanner::new),
+        };
+        &**self
+            .plans
+            .entry(len)
+            .or_insert_with(|| planner.plan_fft_forward(len))
+    }
+}
+
+/// The plans of an `FftPlans` without its planners, which can't be shared
+/// between threads; the plans themselves are immutable and thread-safe.
+#[cfg(feature = "parallel")]
+#[derive(Clone)]
+struct SharedPlans {
+    plans: HashMap<usize, Arc<dyn Fft<f32>>>,
+    plans_f64: HashMap<usize, Arc<dyn Fft<f64>>>,
+}
+
+/// Computes the frequency response of one section along row `line_y`.
+///
+/// The magnitude is `0.0` when the line fails the run-width prefilter, the
+/// `min_active_bins` check or the `min_freq..=max_freq` peak band.
+fn section_magnitude<P: PixelSource + ?Sized>(
+    img: &P,
+    section_x_start: u32,
+    section_width: u32,
+    line_y: u32,
+    config: &DetectionConfig,
+    plans: &mut FftPlans,
+) -> SectionResponse {
+    let section_line: Vec<u8> = (0..section_width)
+        .map(|x| img.luma_at(section_x_start + x, line_y))
+        .collect();
+
+    // Check the width of the black and white area
+    let Some(signal_line) = signal_line(&section_line, config) else {
+        return SectionResponse::default();
+    };
+    let signal_line = window_line(signal_line, config.fft_window);
+
+    let fft_len = config.fft_len(signal_line.len());
+    match config.fft_precision {
+        FftPrecision::Single => line_response(&signal_line, config, plans.forward(fft_len)),
+        FftPrecision::Double => line_response(&signal_line, config, plans.forward_f64(fft_len)),
+    }
+}
+
+/// Runs the FFT of one signal line in the precision of `fft` and scores it.
+///
+/// The line is zero-padded to the plan's length around its mean; see
+/// `section_magnitude` for when the magnitude is `0.0`.
+fn line_response<T: FftNum + Float>(
+    signal_line: &[f32],
+    config: &DetectionConfig,
+    fft: &dyn Fft<T>,
+) -> SectionResponse {
+    let from_len = |len: usize| T::from_usize(len).unwrap_or_else(T::one);
+    let signal: Vec<T> = signal_line
+        .iter()
+        .map(|&x| T::from_f32(x).unwrap_or_else(T::zero))
+        .collect();
+    let mut spectrum: Vec<Complex<T>> =
+        signal.iter().map(|&x| Complex::new(x, T::zero())).collect();
+
+    // Pad around the line's mean so the padding adds no step, only DC
+    if spectrum.len() < fft.len() {
+        let sum = signal.iter().fold(T::zero(), |sum, &x| sum + x);
+        let mean = sum / from_len(signal.len().max(1));
+        spectrum.resize(fft.len(), Complex::new(mean, T::zero()));
+    }
+    fft.process(&mut spectrum);
+
+    let peak_frequency = peak_frequency(&spectrum);
+    // A single tone isn't a barcode, however strong it is, and neither is
+    // texture much finer or coarser than bars, or energy with no clear peak
+    let peak_share = peak_energy_share(&spectrum);
+    if active_bins(&spectrum) < config.min_active_bins as usize
+        || !(config.min_freq..=config.max_freq).contains(&peak_frequency)
+        || peak_share > config.max_peak_share
+        || peak_share < config.min_peak_share
+    {
+        return SectionResponse {
+            magnitude: 0.0,
+            peak_frequency,
+        };
+    }
+
+    // Padding interpolates the spectrum onto more bins; rescale the sum
+    // back to the unpadded bin count so the threshold keeps its meaning
+    let bin_scale = from_len(signal.len()) / from_len(spectrum.len());
+    let magnitude = spectrum_magnitude(&spectrum, config) * bin_scale;
+    SectionResponse {
+        magnitude: magnitude.to_f32().unwrap_or(f32::MAX),
+        peak_frequency,
+    }
+}
+
+/// Frequency of the strongest bin in `1..=N/2`, in cycles per pixel.
+fn peak_frequency<T: Float>(spectrum: &[Complex<T>]) -> f32 {
+    let half = spectrum.get(1..=spectrum.len() / 2).unwrap_or_default();
+    half.iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.norm().partial_cmp(&b.norm()).unwrap_or(Ordering::Equal))
+        .map_or(0.0, |(index, _)| (index + 1) as f32 / spectrum.len() as f32)
+}
+
+/// Share of the energy in bins `1..=N/2` held by the strongest of them.
+///
+/// Energy is the squared amplitude. Returns `0.0` for a spectrum without
+/// energy outside DC, e.g. a flat line.
+fn peak_energy_share<T: Float>(spectrum: &[Complex<T>]) -> f32 {
+    let half = spectrum.get(1..=spectrum.len() / 2).unwrap_or_default();
+    let energies = half.iter().map(|c| c.norm_sqr());
+    let total = energies.clone().fold(T::zero(), |sum, energy| sum + energy);
+    let peak = energies.fold(T::zero(), T::max);
+    if total <= T::zero() {
+        return 0.0;
+    }
+    (peak / total).to_f32().unwrap_or(0.0)
+}
+
+/// Counts the bins holding at least `ACTIVE_BIN_RATIO` of the spectrum's peak.
+///
+/// Only the non-DC half `1..=N/2` is looked at, since the other half mirrors it.
+fn active_bins<T: Float>(spectrum: &[Complex<T>]) -> usize {
+    let half = spectrum.get(1..=spectrum.len() / 2).unwrap_or_default();
+    let amplitudes: Vec<T> = half.iter().map(|c| c.norm()).collect();
+    let peak = amplitudes.iter().copied().fold(T::zero(), T::max);
+    let cutoff = peak * T::from(ACTIVE_BIN_RATIO).unwrap_or_else(T::zero);
+
+    amplitudes
+        .iter()
+        .filter(|&&amplitude| peak > T::zero() && amplitude >= cutoff)
+        .count()
+}
+
+/// Turns a line of pixels into the signal fed to the FFT, per `config.signal`.
+///
+/// Returns `None` when a luma line fails the run-width prefilter.
+fn signal_line(line: &[u8], config: &DetectionConfig) -> Option<Vec<f32>> {
+    match config.signal {
+        SignalType::Luma => {
+            let binary_line = binarize_luma_line(line, config);
+            (!contains_large_white_black_regions(&binary_line, config.max_run_width()))
+                .then_some(binary_line)
+        }
+        // Edge maps are already the signal; use normalized magnitudes directly
+        SignalType::Edges => Some(line.iter().map(|&e| e as f32 / 255.0).collect()),
+        SignalType::GradientX => {
+            // Binarize at an edge step instead of a gray level, so only bar
+            // edges register; the first pixel has no left neighbour
+            let edge_line: Vec<f32> = std::iter::once(0.0)
+                .chain(line.windows(2).map(|p| {
+                    if p[1].abs_diff(p[0]) > GRADIENT_EDGE_STEP {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }))
+                .collect();
+            (!contains_large_white_black_regions(&edge_line, config.max_run_width()))
+                .then_some(edge_line)
+        }
+    }
+}
+
+/// Applies `window` to a signal line, see `FftWindow`.
+///
+/// The line is centered on its mean before a window is applied, so the
+/// window doesn't spread the line's DC level into the lowest bins.
+/// `FftWindow::None` returns the line unchanged.
+fn window_line(mut line: Vec<f32>, window: FftWindow) -> Vec<f32> {
+    let (a0, a1) = match window {
+        FftWindow::None => return line,
+        FftWindow::Hann => (0.5, 0.5),
+        FftWindow::Hamming => (0.54, 0.46),
+    };
+
+    let mean = line.iter().sum::<f32>() / line.len().max(1) as f32;
+    let span = line.len().saturating_sub(1).max(1) as f32;
+    for (n, value) in line.iter_mut().enumerate() {
+        let weight = a0 - a1 * (2.0 * std::f32::consts::PI * n as f32 / span).cos();
+        *value = (*value - mean) * weight;
+    }
+    line
+}
 
-    barcode_regions
+/// Zeroes every magnitude that doesn't exceed `threshold`.
+fn apply_threshold(section_magnitudes: &[f32], threshold: f32) -> Vec<f32> {
+    section_magnitudes
+        .iter()
+        .map(|&m| if m > threshold { m } else { 0.0 })
+        .collect()
 }
 
-/// Detects character-like regions in a grayscale image by leveraging barcode detection logic.
+/// Sums the amplitudes of a section's spectrum, excluding the DC component
+/// unless `config.remove_dc` is off.
+///
+/// With `SpectrumSum::OneSided` only bins `1..=N/2` are summed, so every
+/// frequency counts once. With `SpectrumSum::TwoSided` the whole spectrum is
+/// summed, so every bin `k` in `1..N/2` is counted twice (once as `k` and
+/// once as its mirror `N - k`), roughly doubling the result. Either way the
+/// Nyquist bin `N/2` of an even length `N` has no mirror and appears exactly
+/// once. Setting `count_nyquist` to `false` drops that bin entirely, e.g. to
+/// experiment with thresholds that ignore energy at the highest
+/// representable frequency.
 ///
 /// # Arguments
 ///
-/// * `img_data` - A vector of `u8` representing the grayscale image data.
-/// * `width` - The width of the image.
-/// * `height` - The height of the image.
+/// * `spectrum` - The FFT output of a section line.
+/// * `config` - Detection settings controlling which bins are counted.
 ///
 /// # Returns
 ///
-/// A vector of `BarcodeRegion` representing detected character regions.
-///
-/// # Example
-///
-/// ```rust
-/// let img_data = vec![0; 800 * 600]; // Example grayscale image data
-/// let width = 800;
-/// let height = 600;
-///
-/// let regions = detect_character_regions(img_data, width, height);
-/// for region in regions {
-///     println!("{:?}", region);
-/// }
-/// ```
-#[pyfunction]
-fn detect_character_regions(img_data: Vec<u8>, width: u32, height: u32) -> Vec<BarcodeRegion> {
-    // Detect barcode-like regions using the barcode detection logic
-    let mut barcode_regions = detect_barcode_regions(img_data, width, height);
+/// The summed amplitude of the counted bins.
+fn spectrum_magnitude<T: Float>(spectrum: &[Complex<T>], config: &DetectionConfig) -> T {
+    let len = spectrum.len();
+    let nyquist = len.is_multiple_of(2).then_some(len / 2);
 
-    // Adjust the detected regions for better alignment and scaling
-    adjust_regions(&mut barcode_regions, width, height);
+    let bins = match config.spectrum_sum {
+        SpectrumSum::OneSided => len / 2 + 1,
+        SpectrumSum::TwoSided => len,
+    };
 
-    barcode_regions
+    spectrum
+        .iter()
+        .take(bins)
+        .enumerate()
+        .skip(usize::from(config.remove_dc))
+        .filter(|&(bin, _)| config.count_nyquist || Some(bin) != nyquist)
+        .map(|(_, c)| (c.re * c.re + c.im * c.im).sqrt())
+        .fold(T::zero(), |sum, amplitude| sum + amplitude)
 }
 
-/// Computes the magnitude of each section's frequency response along a specified horizontal line.
+/// Converts interleaved RGB or RGBA bytes to luma using the ITU-R BT.601 weights.
 ///
 /// # Arguments
 ///
-/// * `img` - A reference to the grayscale image buffer
-/// * `section_y_start` - The y-coordinate to start from
-/// * `section_width` - Width of each section
-/// * `sections_per_width` - Number of sections across the width
-/// * `planner` - FFT planner to use for frequency analysis
-fn compute_section_magnitudes(
-    img: &ImageBuffer<Luma<u8>, Vec<u8>>,
-    section_y_start: u32,
-    section_width: u32,
-    sections_per_width: u32,
-    planner: &mut FftPlanner<f32>,
-) -> Vec<f32> {
-    let mut section_magnitudes = Vec::new();
-
-    for section_index_x in 0..sections_per_width {
-        let section_x_start = section_index_x * section_width;
+/// * `data` - Interleaved pixel data, `channels` bytes per pixel.
+/// * `channels` - The number of channels per pixel (3 or 4); alpha is ignored.
+fn interleaved_to_luma(data: &[u8], channels: usize) -> Vec<u8> {
+    data.chunks_exact(channels)
+        .map(|px| {
+            (0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32).round() as u8
+        })
+        .collect()
+}
 
-        let section_line: Vec<u8> = (0..section_width)
-            .map(|x| img.get_pixel(section_x_start + x, section_y_start + SECTION_HEIGHT / 2)[0])
-            .collect();
+/// Binarizes a line of grayscale pixels into 1.0 (white) and 0.0 (black).
+fn binarize_line(line: &[u8]) -> Vec<f32> {
+    binarize_line_at(line, 128)
+}
 
-        let binary_line: Vec<f32> = section_line
-            .iter()
-            .map(|&pixel| if pixel > 128 { 1.0 } else { 0.0 })
-            .collect();
+/// Binarizes a line of grayscale pixels with the cutoff `config.binarization` picks.
+fn binarize_luma_line(line: &[u8], config: &DetectionConfig) -> Vec<f32> {
+    match config.binarization {
+        Binarization::Fixed => binarize_line(line),
+        Binarization::Otsu => binarize_line_at(line, otsu_threshold(line)),
+    }
+}
 
-        // Check the width of the black and white area
-        if contains_large_white_black_regions(&binary_line, MAX_WHITE_BLACK_WIDTH) {
-            section_magnitudes.push(0.0);
-            continue;
-        }
+/// Binarizes a line into 1.0 for pixels above `cutoff` and 0.0 for the rest.
+fn binarize_line_at(line: &[u8], cutoff: u8) -> Vec<f32> {
+    line.iter()
+        .map(|&pixel| if pixel > cutoff { 1.0 } else { 0.0 })
+        .collect()
+}
 
-        let mut input: Vec<Complex<f32>> =
-            binary_line.iter().map(|&x| Complex::new(x, 0.0)).collect();
-        let mut output = vec![Complex::new(0.0, 0.0); input.len()];
+/// Picks the cutoff that best separates a line's pixels into two classes.
+///
+/// Otsu's method: every cutoff splits the histogram into dark (`<= cutoff`)
+/// and light pixels, and the one maximizing the between-class variance wins.
+/// A line of a single gray level has no split and yields its own level, so
+/// the whole line binarizes to black and fails the run-width prefilter.
+fn otsu_threshold(line: &[u8]) -> u8 {
+    let mut histogram = [0u32; 256];
+    for &pixel in line {
+        histogram[pixel as usize] += 1;
+    }
 
-        let fft = planner.plan_fft_forward(input.len());
-        fft.process(&mut input);
-        output.copy_from_slice(&input);
+    let total = line.len() as f64;
+    let total_sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, &count)| value as f64 * count as f64)
+        .sum();
 
-        let section_magnitude: f32 = output
-            .iter()
-            .skip(1)
-            .map(|c| (c.re * c.re + c.im * c.im).sqrt())
-            .sum();
+    let mut dark_count = 0.0;
+    let mut dark_sum = 0.0;
+    let mut best = (0.0, line.first().copied().unwrap_or(128));
+    for (value, &count) in histogram.iter().enumerate() {
+        dark_count += count as f64;
+        dark_sum += value as f64 * count as f64;
+        let light_count = total - dark_count;
+        if dark_count == 0.0 || light_count == 0.0 {
+            continue;
+        }
 
-        section_magnitudes.push(if section_magnitude > THRESHOLD {
-            section_magnitude
-        } else {
-            0.0
-        });
+        let dark_mean = dark_sum / dark_count;
+        let light_mean = (total_sum - dark_sum) / light_count;
+        let between_variance = dark_count * light_count * (dark_mean - light_mean).powi(2);
+        if between_variance > best.0 {
+            best = (between_variance, value as u8);
+        }
     }
-
-    section_magnitudes
+    best.1
 }
 
 /// Checks if a binary line contains any white or black region
@@ -201,15 +5045,17 @@ fn compute_section_magnitudes(
 /// # Returns
 ///
 /// Returns `true` if any region of white or black exceeds the specified maximum width,
-/// otherwise returns `false`.
+/// otherwise returns `false`. The run touching the end of the line counts too;
+/// an empty line has no runs and returns `false`.
 ///
 /// # Example
 ///
-/// ```rust
+/// ```rust,ignore
 /// let binary_line = vec![1.0, 1.0, 0.0, 0.0, 0.0, 1.0];
 /// let max_width = 2;
 /// let result = contains_large_white_black_regions(&binary_line, max_width);
 /// assert_eq!(result, true); // The black region exceeds the maximum width of 2.
+/// assert_eq!(contains_large_white_black_regions(&[], max_width), false);
 /// ```
 ///
 /// # Notes
@@ -218,8 +5064,11 @@ fn compute_section_magnitudes(
 /// continuous regions of the same color (white or black) are not desired.
 ///
 fn contains_large_white_black_regions(binary_line: &[f32], max_width: usize) -> bool {
+    let Some(&first) = binary_line.first() else {
+        return false;
+    };
     let mut count = 0;
-    let mut current_value = binary_line[0];
+    let mut current_value = first;
 
     for &value in binary_line {
         if value == current_value {
@@ -242,12 +5091,18 @@ fn contains_large_white_black_regions(binary_line: &[f32], max_width: usize) ->
 ///
 /// * `section_magnitudes` - Vector of magnitudes for each section
 /// * `section_y_start` - Starting y-coordinate of the section
+/// * `band_height` - Height of the band the magnitudes were sampled from
 /// * `section_width` - Width of each section
+/// * `scanned_width` - Right edge (exclusive) of the last section; x-coordinates are clamped to it
+/// * `min_run` - Consecutive passing sections needed to report a region
 /// * `barcode_regions` - Vector to store detected regions
 fn detect_regions(
     section_magnitudes: &[f32],
     section_y_start: u32,
+    band_height: u32,
     section_width: u32,
+    scanned_width: u32,
+    min_run: usize,
     barcode_regions: &mut Vec<BarcodeRegion>,
 ) {
     let mut consecutive_count = 0;
@@ -260,14 +5115,22 @@ fn detect_regions(
             }
             consecutive_count += 1;
 
-            if consecutive_count >= CONSECUTIVE_THRESHOLD {
+            if consecutive_count >= min_run {
                 if let Some(start) = start_index {
                     let end = section_index;
+                    let run = &section_magnitudes[start..=end];
                     barcode_regions.push(BarcodeRegion {
-                        x_start: start as u32 * section_width,
-                        x_end: (end + 1) as u32 * section_width,
+                        x_start: clamped_mul(start as u64, section_width, scanned_width),
+                        x_end: section_end(
+                            end,
+                            section_magnitudes.len(),
+                            section_width,
+                            scanned_width,
+                        ),
                         y_start: section_y_start,
-                        y_end: section_y_start + SECTION_HEIGHT,
+                        y_end: section_y_start + band_height,
+                        raw_magnitude: run.iter().sum::<f32>() / run.len() as f32,
+                        ..Default::default()
                     });
                 }
             }
@@ -278,6 +5141,150 @@ fn detect_regions(
     }
 }
 
+/// Groups passing sections into regions by 2D connected-component labeling.
+///
+/// Only sections that belong to a horizontal run of at least `min_run`
+/// passing sections take part, matching what
+/// `detect_regions` would accept. Those cells are flood-filled with
+/// 8-connectivity, so sections touching diagonally end up in the same
+/// component, and each component becomes one region spanning its bounding box.
+///
+/// # Arguments
+///
+/// * `grid` - The magnitude grid of the analyzed image.
+/// * `min_run` - Consecutive passing sections a run needs to take part.
+///
+/// # Returns
+///
+/// One `BarcodeRegion` per component, ordered by their top-left section, with
+/// `raw_magnitude` set to the mean magnitude of the component's sections.
+fn connected_component_regions(grid: &MagnitudeGrid, min_run: usize) -> Vec<BarcodeRegion> {
+    // Keep only the cells of runs long enough to count as a detection
+    let mut mask: Vec<Vec<bool>> = grid
+        .magnitudes
+        .iter()
+        .map(|band| {
+            let mut row = vec![false; band.len()];
+            let mut start = 0;
+            for index in 0..=band.len() {
+                if index == band.len() || band[index] <= 0.0 {
+                    if index - start >= min_run {
+                        row[start..index].fill(true);
+                    }
+                    start = index + 1;
+                }
+            }
+            row
+        })
+        .collect();
+
+    let mut barcode_regions = Vec::new();
+    for band in 0..mask.len() {
+        for section in 0..mask[band].len() {
+            if !mask[band][section] {
+                continue;
+            }
+
+            // Flood-fill the component, tracking its bounding box and magnitude
+            mask[band][section] = false;
+            let mut stack = vec![(band, section)];
+            let (mut min_band, mut max_band) = (band, band);
+            let (mut min_section, mut max_section) = (section, section);
+            let mut magnitude_sum = 0.0;
+            let mut cells = 0;
+            while let Some((b, s)) = stack.pop() {
+                min_band = min_band.min(b);
+                max_band = max_band.max(b);
+                min_section = min_section.min(s);
+                max_section = max_section.max(s);
+                magnitude_sum += grid.magnitudes[b][s];
+                cells += 1;
+
+                for nb in b.saturating_sub(1)..=(b + 1).min(mask.len() - 1) {
+                    for ns in s.saturating_sub(1)..=(s + 1).min(mask[nb].len() - 1) {
+                        if mask[nb][ns] {
+                            mask[nb][ns] = false;
+                            stack.push((nb, ns));
+                        }
+                    }
+                }
+            }
+
+            let (y_start, _) = grid.bands[min_band];
+            let (last_y_start, last_band_height) = grid.bands[max_band];
+            barcode_regions.push(BarcodeRegion {
+                x_start: clamped_mul(min_section as u64, grid.section_width, grid.width),
+                x_end: section_end(
+                    max_section,
+                    grid.sections_per_width as usize,
+                    grid.section_width,
+                    grid.scanned_width,
+                ),
+                y_start,
+                y_end: last_y_start + last_band_height,
+                raw_magnitude: magnitude_sum / cells as f32,
+                ..Default::default()
+            });
+        }
+    }
+
+    barcode_regions
+}
+
+/// Trims low-energy section columns off both ends of each region.
+///
+/// A run extends its region up to the last passing section, and merging takes
+/// the union of runs, so a box can end on columns that barely carry any
+/// signal. For every section column a region covers, the magnitudes of the
+/// bands inside the region are averaged; columns at either end whose average is
+/// below `ratio` times the region's overall mean are dropped. At least one
+/// column is always kept.
+///
+/// # Arguments
+///
+/// * `barcode_regions` - A mutable reference to the regions to trim, in the
+///   coordinates of the analyzed image.
+/// * `grid` - The magnitude grid the regions were built from.
+/// * `ratio` - Fraction of the region's mean magnitude a column must reach.
+fn trim_quiet_sections(barcode_regions: &mut [BarcodeRegion], grid: &MagnitudeGrid, ratio: f32) {
+    let section_width = grid.section_width;
+    for region in barcode_regions.iter_mut() {
+        let first = (region.x_start / section_width) as usize;
+        let last =
+            (region.x_end.div_ceil(section_width) as usize).min(grid.sections_per_width as usize);
+        let bands: Vec<&Vec<f32>> = grid
+            .bands
+            .iter()
+            .zip(&grid.magnitudes)
+            .filter(|(&(y_start, _), _)| y_start >= region.y_start && y_start < region.y_end)
+            .map(|(_, magnitudes)| magnitudes)
+            .collect();
+        if first >= last || bands.is_empty() {
+            continue;
+        }
+
+        let column_means: Vec<f32> = (first..last)
+            .map(|section| bands.iter().map(|band| band[section]).sum::<f32>() / bands.len() as f32)
+            .collect();
+        let cutoff = ratio * column_means.iter().sum::<f32>() / column_means.len() as f32;
+
+        let keep_start = column_means.iter().position(|&m| m >= cutoff).unwrap_or(0);
+        let keep_end = column_means
+            .iter()
+            .rposition(|&m| m >= cutoff)
+            .map_or(column_means.len(), |index| index + 1)
+            .max(keep_start + 1);
+
+        region.x_start = clamped_mul((first + keep_start) as u64, section_width, grid.width);
+        region.x_end = section_end(
+            first + keep_end - 1,
+            grid.sections_per_width as usize,
+            section_width,
+            grid.scanned_width,
+        );
+    }
+}
+
 /// Merges overlapping or adjacent barcode regions with the same vertical range.
 ///
 /// This function takes a mutable vector of `BarcodeRegion` objects, groups regions
@@ -288,10 +5295,11 @@ fn detect_regions(
 ///
 /// * `barcode_regions` - A mutable reference to a vector of `BarcodeRegion` objects
 ///   that will be merged if their vertical ranges (`y_start` and `y_end`) match.
+/// * `confidence_merge` - How the confidences of merged regions are combined.
 ///
 /// # Example
 ///
-/// ```rust
+/// ```rust,ignore
 /// let mut regions = vec![
 ///     BarcodeRegion { x_start: 10, x_end: 20, y_start: 50, y_end: 60 },
 ///     BarcodeRegion { x_start: 21, x_end: 30, y_start: 50, y_end: 60 },
@@ -299,16 +5307,19 @@ fn detect_regions(
 ///     BarcodeRegion { x_start: 16, x_end: 25, y_start: 70, y_end: 80 },
 /// ];
 ///
-/// merge_barcode_regions(&mut regions);
+/// merge_barcode_regions(&mut regions, ConfidenceMerge::Mean);
 ///
 /// assert_eq!(regions, vec![
 ///     BarcodeRegion { x_start: 10, x_end: 30, y_start: 50, y_end: 60 },
 ///     BarcodeRegion { x_start: 5, x_end: 25, y_start: 70, y_end: 80 },
 /// ]);
 /// ```
-fn merge_barcode_regions(barcode_regions: &mut Vec<BarcodeRegion>) {
+fn merge_barcode_regions(
+    barcode_regions: &mut Vec<BarcodeRegion>,
+    confidence_merge: ConfidenceMerge,
+) {
     // Sort regions by their vertical range (y_start, y_end)
-    barcode_regions.sort_by(|a, b| (a.y_start, a.y_end).cmp(&(b.y_start, b.y_end)));
+    barcode_regions.sort_by_key(|a| (a.y_start, a.y_end));
 
     let mut merged_regions = Vec::new();
     let mut current_group = Vec::new();
@@ -322,7 +5333,7 @@ fn merge_barcode_regions(barcode_regions: &mut Vec<BarcodeRegion>) {
                 current_group.push(region);
             } else {
                 // Merge the current group and start a new one
-                merged_regions.push(merge_group(&current_group));
+                merged_regions.extend(merge_group(&current_group, confidence_merge));
                 current_group.clear();
                 current_group.push(region);
             }
@@ -331,19 +5342,156 @@ fn merge_barcode_regions(barcode_regions: &mut Vec<BarcodeRegion>) {
 
     // Merge the final group
     if !current_group.is_empty() {
-        merged_regions.push(merge_group(&current_group));
+        merged_regions.extend(merge_group(&current_group, confidence_merge));
     }
 
     // Replace the original vector with the merged results
     *barcode_regions = merged_regions;
 }
 
+/// Merges vertically touching regions like `merge_regions_if_y_matches`, but
+/// not across a tall gap under the region above.
+///
+/// Each stack of touching regions is walked top to bottom. A region whose
+/// band has no passing section in the columns of the last region above it
+/// with any is quiet: it only touches the stack because something triggered
+/// elsewhere in its band. When more than `max_gap` pixels of quiet bands
+/// follow each other, the stack is cut before and after them, so the
+/// barcodes above and below come out as separate boxes and the quiet bands
+/// as a box of their own. Shorter quiet stretches are merged as usual.
+///
+/// # Arguments
+///
+/// * `regions` - A mutable reference to a vector of `BarcodeRegion` to be processed.
+/// * `grid` - The magnitude grid the regions were extracted from.
+/// * `max_gap` - Tallest quiet stretch that is bridged, in pixels of the grid's image.
+/// * `confidence_merge` - How the confidences of merged regions are combined.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// // Barcodes in bands 0..200 and 300..500, with text at the far left of 200..300
+/// merge_regions_across_gaps(&mut regions, &grid, 50, ConfidenceMerge::Mean);
+///
+/// assert_eq!(regions.len(), 3);
+/// ```
+fn merge_regions_across_gaps(
+    regions: &mut Vec<BarcodeRegion>,
+    grid: &MagnitudeGrid,
+    max_gap: u32,
+    confidence_merge: ConfidenceMerge,
+) {
+    regions.sort_by(|a, b| {
+        a.y_start
+            .cmp(&b.y_start)
+            .then_with(|| a.y_end.cmp(&b.y_end))
+    });
+
+    let mut merged_regions = Vec::new();
+    let mut current_group: Vec<BarcodeRegion> = Vec::new();
+    // Trailing quiet regions not yet known to be bridged, and their height
+    let mut quiet: Vec<BarcodeRegion> = Vec::new();
+    let mut quiet_height = 0;
+    // Last region of the stack with energy under it
+    let mut anchor: Option<BarcodeRegion> = None;
+
+    for region in regions.drain(..) {
+        let touches = quiet
+            .last()
+            .or(current_group.last())
+            .is_some_and(|last| last.y_end == region.y_start);
+        let Some(anchor_region) = anchor.as_ref().filter(|_| touches) else {
+            // A new stack starts
+            close_gap_stack(
+                &mut merged_regions,
+                &mut current_group,
+                &mut quiet,
+                quiet_height > max_gap,
+                confidence_merge,
+            );
+            quiet_height = 0;
+            anchor = Some(region.clone());
+            current_group.push(region);
+            continue;
+        };
+
+        if band_passes_under(grid, region.y_start, anchor_region) {
+            if quiet_height > max_gap {
+                close_gap_stack(
+                    &mut merged_regions,
+                    &mut current_group,
+                    &mut quiet,
+                    true,
+                    confidence_merge,
+                );
+            } else {
+                current_group.append(&mut quiet);
+            }
+            quiet_height = 0;
+            anchor = Some(region.clone());
+            current_group.push(region);
+        } else {
+            quiet_height += region.y_end - region.y_start;
+            quiet.push(region);
+        }
+    }
+    close_gap_stack(
+        &mut merged_regions,
+        &mut current_group,
+        &mut quiet,
+        quiet_height > max_gap,
+        confidence_merge,
+    );
+
+    *regions = merged_regions;
+}
+
+/// Merges and emits the finished part of a stack for `merge_regions_across_gaps`.
+///
+/// The trailing `quiet` regions become a box of their own when `split_quiet`
+/// is set, and are merged into `group` otherwise. Both vectors are left empty.
+fn close_gap_stack(
+    merged_regions: &mut Vec<BarcodeRegion>,
+    group: &mut Vec<BarcodeRegion>,
+    quiet: &mut Vec<BarcodeRegion>,
+    split_quiet: bool,
+    confidence_merge: ConfidenceMerge,
+) {
+    if !split_quiet {
+        group.append(quiet);
+    }
+    for part in [group, quiet] {
+        if !part.is_empty() {
+            merged_regions.extend(merge_group(part, confidence_merge));
+            part.clear();
+        }
+    }
+}
+
+/// Whether any section of the band starting at `y_start` under `region`'s columns passed.
+fn band_passes_under(grid: &MagnitudeGrid, y_start: u32, region: &BarcodeRegion) -> bool {
+    let Some(band) = grid.bands.iter().position(|&(y, _)| y == y_start) else {
+        return false;
+    };
+    let sections = grid.magnitudes[band].len();
+    grid.magnitudes[band]
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| {
+            let x_start = clamped_mul(index as u64, grid.section_width, grid.width);
+            let x_end = section_end(index, sections, grid.section_width, grid.scanned_width);
+            x_start < region.x_end && region.x_start < x_end
+        })
+        .any(|(_, &magnitude)| magnitude > 0.0)
+}
+
 /// Merges regions in a vector of `BarcodeRegion` if their `y_end` and `y_start` are consecutive.
 /// This function modifies the original vector by replacing it with the merged regions.
 ///
 /// # Arguments
 ///
 /// * `regions` - A mutable reference to a vector of `BarcodeRegion` to be processed.
+/// * `confidence_merge` - How the confidences of merged regions are combined.
 ///
 /// # Details
 ///
@@ -354,21 +5502,21 @@ fn merge_barcode_regions(barcode_regions: &mut Vec<BarcodeRegion>) {
 ///
 /// # Example
 ///
-/// ```rust
+/// ```rust,ignore
 /// let mut regions = vec![
 ///     BarcodeRegion { x_start: 10, x_end: 20, y_start: 0, y_end: 5 },
 ///     BarcodeRegion { x_start: 15, x_end: 25, y_start: 5, y_end: 10 },
 ///     BarcodeRegion { x_start: 30, x_end: 40, y_start: 20, y_end: 25 },
 /// ];
 ///
-/// merge_regions_if_y_matches(&mut regions);
+/// merge_regions_if_y_matches(&mut regions, ConfidenceMerge::Mean);
 ///
 /// assert_eq!(regions, vec![
 ///     BarcodeRegion { x_start: 10, x_end: 25, y_start: 0, y_end: 10 },
 ///     BarcodeRegion { x_start: 30, x_end: 40, y_start: 20, y_end: 25 },
 /// ]);
 /// ```
-fn merge_regions_if_y_matches(regions: &mut Vec<BarcodeRegion>) {
+fn merge_regions_if_y_matches(regions: &mut Vec<BarcodeRegion>, confidence_merge: ConfidenceMerge) {
     // Sort regions by their vertical position (`y_start`, then `y_end`) for consistent merging.
     regions.sort_by(|a, b| {
         a.y_start
@@ -393,7 +5541,7 @@ fn merge_regions_if_y_matches(regions: &mut Vec<BarcodeRegion>) {
             } else {
                 // If the regions are not vertically continuous, merge the current group
                 // and start a new group with the current region.
-                merged_regions.push(merge_group(&current_group));
+                merged_regions.extend(merge_group(&current_group, confidence_merge));
                 current_group.clear();
                 current_group.push(region);
             }
@@ -402,100 +5550,461 @@ fn merge_regions_if_y_matches(regions: &mut Vec<BarcodeRegion>) {
 
     // Merge the final group if there are any remaining regions.
     if !current_group.is_empty() {
-        merged_regions.push(merge_group(&current_group));
+        merged_regions.extend(merge_group(&current_group, confidence_merge));
     }
 
     // Replace the original regions with the merged results.
     *regions = merged_regions;
 }
 
-/// Merges a group of `BarcodeRegion` objects into a single region.
+/// Merges regions whose bounding boxes overlap by more than `min_iou`.
 ///
-/// The function calculates the smallest `x_start` and the largest `x_end`
-/// within the group. It assumes all regions in the group have the same
-/// `y_start` and `y_end`.
+/// Unlike `merge_barcode_regions`, the y-ranges don't have to match: any two
+/// regions whose intersection over union exceeds `min_iou` end up in the same
+/// group, transitively. Each group is replaced by its bounding box, and the
+/// pass repeats until no merged boxes overlap any more, since a grown box can
+/// reach regions its parts didn't.
 ///
 /// # Arguments
 ///
-/// * `group` - A slice of `BarcodeRegion` objects to be merged. All regions
-///   must have the same `y_start` and `y_end`.
+/// * `regions` - A mutable reference to the regions to merge.
+/// * `min_iou` - The overlap, as intersection over union, two regions need
+///   to be merged.
+/// * `confidence_merge` - How the confidences of merged regions are combined.
 ///
-/// # Returns
+/// # Example
+///
+/// ```rust,ignore
+/// let mut regions = vec![
+///     BarcodeRegion { x_start: 10, x_end: 50, y_start: 0, y_end: 20, ..Default::default() },
+///     BarcodeRegion { x_start: 12, x_end: 48, y_start: 5, y_end: 25, ..Default::default() },
+/// ];
+///
+/// merge_overlapping_regions(&mut regions, 0.5, ConfidenceMerge::Mean);
+///
+/// assert_eq!(regions.len(), 1);
+/// assert_eq!((regions[0].y_start, regions[0].y_end), (0, 25));
+/// ```
+fn merge_overlapping_regions(
+    regions: &mut Vec<BarcodeRegion>,
+    min_iou: f32,
+    confidence_merge: ConfidenceMerge,
+) {
+    fn root(parent: &mut [usize], mut index: usize) -> usize {
+        while parent[index] != index {
+            parent[index] = parent[parent[index]];
+            index = parent[index];
+        }
+        index
+    }
+
+    loop {
+        // Union-find over every pair that overlaps enough
+        let mut parent: Vec<usize> = (0..regions.len()).collect();
+        for i in 0..regions.len() {
+            for j in i + 1..regions.len() {
+                if region_iou(&regions[i], &regions[j]) > min_iou {
+                    let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                    parent[b] = a;
+                }
+            }
+        }
+
+        let mut groups: Vec<Vec<BarcodeRegion>> = Vec::new();
+        let mut group_of_root = HashMap::new();
+        for (index, region) in regions.drain(..).enumerate() {
+            let group_root = root(&mut parent, index);
+            let group = *group_of_root.entry(group_root).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(region);
+        }
+
+        // Lone regions keep their measurements
+        let merged_any = groups.iter().any(|group| group.len() > 1);
+        for mut group in groups {
+            if group.len() == 1 {
+                regions.extend(group.pop());
+            } else {
+                regions.extend(merge_group(&group, confidence_merge));
+            }
+        }
+        if !merged_any {
+            break;
+        }
+    }
+}
+
+/// Removes duplicate and nested regions from a final result set.
+///
+/// Boxes overlapping by more than `DEDUPE_IOU` are merged into their bounding
+/// box first; any region lying entirely within another is then dropped, so
+/// each barcode is reported once.
+///
+/// # Arguments
+///
+/// * `regions` - A mutable reference to the regions to clean up.
+/// * `confidence_merge` - How the confidences of merged regions are combined.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut regions = vec![
+///     BarcodeRegion { x_start: 0, x_end: 100, y_start: 0, y_end: 50, ..Default::default() },
+///     BarcodeRegion { x_start: 20, x_end: 40, y_start: 10, y_end: 30, ..Default::default() },
+/// ];
+///
+/// dedupe_regions(&mut regions, ConfidenceMerge::Mean);
 ///
-/// A new `BarcodeRegion` that spans the entire horizontal range of the group.
+/// assert_eq!(regions.len(), 1);
+/// assert_eq!((regions[0].x_start, regions[0].x_end), (0, 100));
+/// ```
+fn dedupe_regions(regions: &mut Vec<BarcodeRegion>, confidence_merge: ConfidenceMerge) {
+    merge_overlapping_regions(regions, DEDUPE_IOU, confidence_merge);
+
+    let contains = |outer: &BarcodeRegion, inner: &BarcodeRegion| {
+        outer.x_start <= inner.x_start
+            && inner.x_end <= outer.x_end
+            && outer.y_start <= inner.y_start
+            && inner.y_end <= outer.y_end
+    };
+    // Of two identical (e.g. empty) boxes only the later one is dropped
+    let nested: Vec<bool> = regions
+        .iter()
+        .enumerate()
+        .map(|(index, inner)| {
+            regions.iter().enumerate().any(|(other, outer)| {
+                other != index
+                    && contains(outer, inner)
+                    && (other < index || !contains(inner, outer))
+            })
+        })
+        .collect();
+
+    let mut nested = nested.into_iter();
+    regions.retain(|_| !nested.next().unwrap());
+}
+
+/// Merges a group of `BarcodeRegion` objects into a single region.
+///
+/// The function calculates the smallest `x_start` / `y_start` and the
+/// largest `x_end` / `y_end` within the group, so the result is the bounding
+/// box of all its regions regardless of the order they're passed in. Confidences and raw magnitudes are combined
+/// according to `confidence_merge`.
 ///
-/// # Panics
+/// # Arguments
+///
+/// * `group` - A slice of `BarcodeRegion` objects to be merged.
+/// * `confidence_merge` - How the confidences of the group are combined.
+///
+/// # Returns
 ///
-/// This function will panic if the input slice is empty.
+/// A new `BarcodeRegion` that spans the entire range of the group, or
+/// `None` if the group is empty.
 ///
 /// # Example
 ///
-/// ```rust
+/// ```rust,ignore
+/// // The group doesn't need to be sorted
 /// let group = vec![
+///     BarcodeRegion { x_start: 15, x_end: 25, y_start: 60, y_end: 70 },
 ///     BarcodeRegion { x_start: 10, x_end: 20, y_start: 50, y_end: 60 },
-///     BarcodeRegion { x_start: 15, x_end: 25, y_start: 50, y_end: 60 },
 /// ];
 ///
-/// let merged = merge_group(&group);
+/// let merged = merge_group(&group, ConfidenceMerge::Mean);
 ///
-/// assert_eq!(merged, BarcodeRegion { x_start: 10, x_end: 25, y_start: 50, y_end: 60 });
+/// assert_eq!(merged, Some(BarcodeRegion { x_start: 10, x_end: 25, y_start: 50, y_end: 70 }));
 /// ```
-fn merge_group(group: &[BarcodeRegion]) -> BarcodeRegion {
-    if group.is_empty() {
-        panic!("merge_group: Group is empty and cannot be merged.");
+fn merge_group(
+    group: &[BarcodeRegion],
+    confidence_merge: ConfidenceMerge,
+) -> Option<BarcodeRegion> {
+    let x_start = group.iter().map(|r| r.x_start).min()?;
+    let x_end = group.iter().map(|r| r.x_end).max()?;
+    let y_start = group.iter().map(|r| r.y_start).min()?;
+    let y_end = group.iter().map(|r| r.y_end).max()?;
+
+    let confidence = confidence_merge.combine(group, |r| r.confidence);
+    let raw_magnitude = confidence_merge.combine(group, |r| r.raw_magnitude);
+
+    Some(BarcodeRegion {
+        x_start,
+        x_end,
+        y_start,
+        y_end,
+        confidence,
+        raw_magnitude,
+        ..Default::default()
+    })
+}
+
+/// Estimates the module width of a region from the peak frequencies of its sections.
+///
+/// A peak period spans one bar and one space, each `ELEMENT_MODULES` wide on
+/// average, so the module width is `1 / (2 * ELEMENT_MODULES * f)` for the
+/// mean peak frequency `f` of the passing sections the region covers.
+///
+/// # Arguments
+///
+/// * `grid` - The magnitude grid the region was extracted from.
+/// * `region` - The region, in grid (working image) coordinates.
+///
+/// # Returns
+///
+/// The estimated module width in working image pixels, or `0.0` if no
+/// covered section has a peak.
+fn estimate_module_width(grid: &MagnitudeGrid, region: &BarcodeRegion) -> f32 {
+    let first = (region.x_start / grid.section_width) as usize;
+    let last = region.x_end.div_ceil(grid.section_width) as usize;
+
+    let peaks: Vec<f32> = grid
+        .bands
+        .iter()
+        .zip(grid.magnitudes.iter().zip(&grid.peak_frequencies))
+        .filter(|(&(y_start, band_height), _)| {
+            y_start < region.y_end && region.y_start < y_start + band_height
+        })
+        .flat_map(|(_, (magnitudes, peaks))| {
+            let last = last.min(peaks.len());
+            magnitudes[first.min(last)..last]
+                .iter()
+                .zip(&peaks[first.min(last)..last])
+        })
+        .filter(|&(&magnitude, &peak)| magnitude > 0.0 && peak > 0.0)
+        .map(|(_, &peak)| peak)
+        .collect();
+
+    if peaks.is_empty() {
+        return 0.0;
+    }
+    let mean_peak = peaks.iter().sum::<f32>() / peaks.len() as f32;
+    1.0 / (2.0 * ELEMENT_MODULES * mean_peak)
+}
+
+/// Flags regions that touch the edge of the scanned area.
+///
+/// Detection only sees whole sections, so the reference edges are the last
+/// scanned column and row rather than the raw image size. A region whose
+/// bounds come within `CLIP_MARGIN` pixels of any of these edges is marked
+/// as `clipped`.
+///
+/// # Arguments
+///
+/// * `barcode_regions` - A mutable reference to the regions to flag.
+/// * `scanned_width` - The rightmost scanned x-coordinate (exclusive).
+/// * `scanned_height` - The bottommost scanned y-coordinate (exclusive).
+fn mark_clipped_regions(
+    barcode_regions: &mut [BarcodeRegion],
+    scanned_width: u32,
+    scanned_height: u32,
+) {
+    for region in barcode_regions.iter_mut() {
+        region.clipped = region.x_start <= CLIP_MARGIN
+            || region.y_start <= CLIP_MARGIN
+            || region.x_end.saturating_add(CLIP_MARGIN) >= scanned_width
+            || region.y_end.saturating_add(CLIP_MARGIN) >= scanned_height;
     }
+}
 
-    let x_start = group.iter().map(|r| r.x_start).min().unwrap();
-    let x_end = group.iter().map(|r| r.x_end).max().unwrap();
-    let y_start = group.first().unwrap().y_start;
-    let y_end = group.last().unwrap().y_end;
+/// Strips the guard bars (the `*` start/stop characters) off both ends of a region.
+///
+/// Both ends move inward by `px`. A region narrower than `2 * px` has no room
+/// for guard bars on both sides and keeps its extent, so small regions near the
+/// image edge survive; an inverted span is collapsed to zero width at `x_start`.
+/// Either way `x_start <= x_end` holds afterwards.
+///
+/// # Arguments
+///
+/// * `region` - The region to shrink.
+/// * `px` - How far each end moves inward, in pixels.
+///
+/// # Returns
+///
+/// A new `BarcodeRegion` with the narrowed x-range; the y-range is unchanged.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let region = BarcodeRegion { x_start: 100, x_end: 200, ..Default::default() };
+/// let stripped = strip_guard_bars(&region, 25);
+/// assert_eq!((stripped.x_start, stripped.x_end), (125, 175));
+///
+/// let narrow = BarcodeRegion { x_start: 5, x_end: 15, ..Default::default() };
+/// assert_eq!(strip_guard_bars(&narrow, 25).x_end, 15);
+/// ```
+fn strip_guard_bars(region: &BarcodeRegion, px: u32) -> BarcodeRegion {
+    let width = region.x_end.saturating_sub(region.x_start);
+    if width < px.saturating_mul(2) {
+        return BarcodeRegion {
+            x_end: region.x_end.max(region.x_start),
+            ..region.clone()
+        };
+    }
+    let x_start = region.x_start + px;
+    let x_end = region.x_end - px;
 
     BarcodeRegion {
         x_start,
         x_end,
+        ..region.clone()
+    }
+}
+
+/// Moves a region to the line of text printed below it.
+///
+/// The new box starts `gap` pixels below the region's bottom edge and is
+/// `text_height` pixels tall, both clamped to the image height.
+///
+/// # Arguments
+///
+/// * `region` - The barcode region the text belongs to.
+/// * `gap` - Space between the bottom of the bars and the top of the text.
+/// * `text_height` - Height of the text line.
+/// * `height` - The height of the image.
+///
+/// # Returns
+///
+/// A new `BarcodeRegion` covering the text; the x-range is unchanged.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let region = BarcodeRegion { y_start: 100, y_end: 150, ..Default::default() };
+/// let text = shift_to_text_below(&region, 4, 46, 200);
+/// assert_eq!((text.y_start, text.y_end), (154, 200));
+/// ```
+fn shift_to_text_below(
+    region: &BarcodeRegion,
+    gap: u32,
+    text_height: u32,
+    height: u32,
+) -> BarcodeRegion {
+    let y_start = region.y_end.saturating_add(gap).min(height);
+    let y_end = y_start.saturating_add(text_height).min(height);
+
+    BarcodeRegion {
         y_start,
         y_end,
+        ..region.clone()
     }
 }
-/// Adjusts the dimensions of barcode regions by expanding or shrinking their coordinates.
+
+/// Turns detected barcode regions into character regions.
 ///
-/// This function modifies each region's coordinates to expand its size while ensuring
-/// the new coordinates do not exceed the image boundaries. Specifically:
-/// - `x_start` and `y_start` are reduced by 50 pixels if they are greater than or equal to 50.
-/// - `x_end` and `y_end` are increased by 50 pixels but are capped at the image's width and height, respectively.
+/// Each region has its guard bars stripped (`GUARD_BAR_PX` off each end).
+/// With `CharacterBox::TextBelow` it is then moved, on purpose, off the bars
+/// and onto the text line below them (`TEXT_GAP` pixels down, `TEXT_HEIGHT`
+/// pixels tall), so the result doesn't overlap the detected barcode at all;
+/// `CharacterBox::Bars` keeps the detected y-range instead. See
+/// `strip_guard_bars` and `shift_to_text_below`. The offsets are given at
+/// `REFERENCE_DPI` and scaled to the scan's `dpi`.
 ///
 /// # Arguments
 ///
 /// * `barcode_regions` - A mutable reference to a vector of `BarcodeRegion` objects to adjust.
-/// * `width` - The width of the image. Used to cap `x_end`.
-/// * `height` - The height of the image. Used to cap `y_end`.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image. Used to cap `y_start` and `y_end`.
+/// * `dpi` - Resolution of the scan in dots per inch.
+/// * `character_box` - Whether boxes move to the text line or stay on the bars.
 ///
 /// # Example
 ///
-/// ```rust
-/// let mut regions = vec![
-///     BarcodeRegion { x_start: 100, x_end: 200, y_start: 100, y_end: 150 }
-/// ];
-///
-/// adjust_regions(&mut regions, 300, 200);
+/// ```rust,ignore
+/// let barcode = BarcodeRegion { x_start: 100, x_end: 200, y_start: 100, y_end: 150 };
 ///
+/// // The text line below the bars
+/// let mut regions = vec![barcode.clone()];
+/// adjust_regions(&mut regions, 300, 200, 300.0, CharacterBox::TextBelow);
 /// assert_eq!(regions, vec![
 ///     BarcodeRegion { x_start: 125, x_end: 175, y_start: 154, y_end: 200 }
 /// ]);
+///
+/// // The bars themselves
+/// let mut regions = vec![barcode];
+/// adjust_regions(&mut regions, 300, 200, 300.0, CharacterBox::Bars);
+/// assert_eq!(regions, vec![
+///     BarcodeRegion { x_start: 125, x_end: 175, y_start: 100, y_end: 150 }
+/// ]);
 /// ```
-fn adjust_regions(barcode_regions: &mut [BarcodeRegion], _width: u32, height: u32) {
+fn adjust_regions(
+    barcode_regions: &mut [BarcodeRegion],
+    _width: u32,
+    height: u32,
+    dpi: f32,
+    character_box: CharacterBox,
+) {
+    let scale = |px: u32| (px as f32 * dpi / REFERENCE_DPI).round() as u32;
+    let (guard_bar_px, text_gap, text_height) =
+        (scale(GUARD_BAR_PX), scale(TEXT_GAP), scale(TEXT_HEIGHT));
+
     // TODO: Optimize the process of removing * from both ends of the barcode
     for region in barcode_regions.iter_mut() {
-        region.x_start += 25;
-        region.x_end -= 25;
-        region.y_start = region.y_end + 4;
-        region.y_end = (region.y_end + 50).min(height);
+        let stripped = strip_guard_bars(region, guard_bar_px);
+        *region = match character_box {
+            CharacterBox::TextBelow => {
+                shift_to_text_below(&stripped, text_gap, text_height, height)
+            }
+            CharacterBox::Bars => stripped,
+        };
     }
 }
 
 /// A Python module implemented in Rust.
+#[cfg(feature = "python")]
 #[pymodule]
 fn house_specific(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(detect_character_regions, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_barcode_regions, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_barcode_regions_array, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_barcode_regions_rgb, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_barcode_regions_u16, m)?)?;
+    m.add_function(wrap_pyfunction!(py_detect_character_regions, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_with_warnings, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_with_meta, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_with_diagnostics, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_from_edges, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_any_orientation, m)?)?;
+    m.add_function(wrap_pyfunction!(compute_pass_grid, m)?)?;
+    m.add_function(wrap_pyfunction!(band_coverage, m)?)?;
+    m.add_function(wrap_pyfunction!(section_peak_frequencies, m)?)?;
+    m.add_function(wrap_pyfunction!(score_region, m)?)?;
+    m.add_function(wrap_pyfunction!(region_content_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_in_rois, m)?)?;
+    m.add_function(wrap_pyfunction!(suggest_threshold, m)?)?;
+    m.add_function(wrap_pyfunction!(region_scanline, m)?)?;
+    m.add_function(wrap_pyfunction!(crop_regions, m)?)?;
+    m.add_function(wrap_pyfunction!(draw_regions, m)?)?;
+    #[cfg(feature = "tiff")]
+    m.add_function(wrap_pyfunction!(py_detect_tiff_pages, m)?)?;
+    m.add_function(wrap_pyfunction!(drop_clipped_regions, m)?)?;
+    m.add_function(wrap_pyfunction!(filter_regions, m)?)?;
+    m.add_function(wrap_pyfunction!(normalize_regions, m)?)?;
+    m.add_function(wrap_pyfunction!(assign_columns, m)?)?;
+    m.add_function(wrap_pyfunction!(classify_page, m)?)?;
+    m.add_function(wrap_pyfunction!(py_detect_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(py_detect_barcode_regions_tiled, m)?)?;
+    m.add_function(wrap_pyfunction!(detect_diff, m)?)?;
+    m.add_function(wrap_pyfunction!(confident_bounds, m)?)?;
+    m.add_function(wrap_pyfunction!(py_regions_to_csv, m)?)?;
+    m.add("NO_COLUMN", NO_COLUMN)?;
+    m.add_class::<DetectionConfig>()?;
+    m.add_class::<ConfidenceMerge>()?;
+    m.add_class::<SignalType>()?;
+    m.add_class::<TrailingBand>()?;
+    m.add_class::<TrailingColumns>()?;
+    m.add_class::<MergeStrategy>()?;
+    m.add_class::<RowPick>()?;
+    m.add_class::<ScanLineMerge>()?;
+    m.add_class::<Binarization>()?;
+    m.add_class::<FftPrecision>()?;
+    m.add_class::<CharacterBox>()?;
+    m.add_class::<SpectrumSum>()?;
+    m.add_class::<FftWindow>()?;
+    m.add_class::<Symbology>()?;
+    m.add_class::<PageClass>()?;
+    m.add_class::<Orientation>()?;
+    m.add_class::<OrientationConflict>()?;
+    m.add_class::<BarcodeRegion>()?;
+    m.add_class::<Rect>()?;
     Ok(())
 }
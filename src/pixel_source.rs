@@ -0,0 +1,124 @@
+use std::ops::Deref;
+
+use image::{ImageBuffer, Luma};
+
+/// A grayscale image detection can read pixels from.
+///
+/// Detection only ever asks for single luma values and the image size, so any
+/// storage (a GPU texture mapping, a custom format, a procedural generator)
+/// can be scanned directly by implementing this trait, without first copying
+/// it into a `Vec<u8>`. Grayscale `ImageBuffer`s implement it already.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{detect_in_source, PixelSource};
+/// struct Stripes;
+///
+/// impl PixelSource for Stripes {
+///     fn luma_at(&self, x: u32, _y: u32) -> u8 {
+///         if (x / 3) % 2 == 0 { 0 } else { 255 }
+///     }
+///
+///     fn dimensions(&self) -> (u32, u32) {
+///         (2400, 1600)
+///     }
+/// }
+///
+/// let regions = detect_in_source(&Stripes, None)?;
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+///
+/// Sources must be `Sync`, so that the `parallel` feature can scan bands on
+/// several threads at once; the bound is the same with or without it.
+pub trait PixelSource: Sync {
+    /// Returns the luma value at `(x, y)`; both are within `dimensions()`.
+    fn luma_at(&self, x: u32, y: u32) -> u8;
+
+    /// Returns the `(width, height)` of the image.
+    fn dimensions(&self) -> (u32, u32);
+
+    /// Returns the width of the image.
+    fn width(&self) -> u32 {
+        self.dimensions().0
+    }
+
+    /// Returns the height of the image.
+    fn height(&self) -> u32 {
+        self.dimensions().1
+    }
+}
+
+impl<C: Deref<Target = [u8]> + Sync> PixelSource for ImageBuffer<Luma<u8>, C> {
+    fn luma_at(&self, x: u32, y: u32) -> u8 {
+        self.get_pixel(x, y)[0]
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        ImageBuffer::dimensions(self)
+    }
+}
+
+/// Lets NumPy arrays from the Python bindings be scanned in place, whatever their strides.
+#[cfg(feature = "python")]
+impl PixelSource for numpy::ndarray::ArrayView2<'_, u8> {
+    fn luma_at(&self, x: u32, y: u32) -> u8 {
+        self[[y as usize, x as usize]]
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        let (rows, cols) = self.dim();
+        (cols as u32, rows as u32)
+    }
+}
+
+/// A 16-bit grayscale image read as 8-bit luma centered on a 16-bit threshold.
+///
+/// Pixels above `threshold` map to `129..=255` and the rest to `0..=128`,
+/// each side stretched over the image's own range, so the 8-bit binarization
+/// at `128` splits the image exactly where the 16-bit threshold does however
+/// close to it the pixels lie. Small contrasts in the shadows that a plain
+/// `>> 8` would flatten to a single value survive that way.
+pub(crate) struct Luma16<'a, C> {
+    img: &'a ImageBuffer<Luma<u16>, C>,
+    threshold: u16,
+    /// Distance from the darkest pixel up to `threshold`, at least 1.
+    below: u32,
+    /// Distance from `threshold` up to the brightest pixel, at least 1.
+    above: u32,
+}
+
+impl<'a, C: Deref<Target = [u16]>> Luma16<'a, C> {
+    /// Wraps `img`, splitting at `threshold` or, for `None`, halfway between
+    /// its darkest and brightest pixels.
+    pub fn new(img: &'a ImageBuffer<Luma<u16>, C>, threshold: Option<u16>) -> Self {
+        let pixels = img.as_raw().iter().copied();
+        let darkest = pixels.clone().min().unwrap_or(0);
+        let brightest = pixels.max().unwrap_or(u16::MAX);
+        let threshold = threshold.unwrap_or(((darkest as u32 + brightest as u32) / 2) as u16);
+
+        Luma16 {
+            img,
+            threshold,
+            below: threshold.saturating_sub(darkest).max(1) as u32,
+            above: brightest.saturating_sub(threshold).max(1) as u32,
+        }
+    }
+}
+
+impl<C: Deref<Target = [u16]> + Sync> PixelSource for Luma16<'_, C> {
+    fn luma_at(&self, x: u32, y: u32) -> u8 {
+        let value = self.img.get_pixel(x, y)[0];
+        if value > self.threshold {
+            let offset = (value - self.threshold - 1) as u32 * 126 / self.above;
+            (129 + offset.min(126)) as u8
+        } else {
+            let offset = (self.threshold - value) as u32 * 128 / self.below;
+            (128 - offset.min(128)) as u8
+        }
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.img.dimensions()
+    }
+}
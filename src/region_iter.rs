@@ -0,0 +1,247 @@
+use rustfft::FftPlanner;
+
+use crate::{
+    apply_threshold, box_downscale, clamped_mul, compute_section_magnitudes, detect_regions,
+    downscale_warning, finish_region, grid_layout, mark_clipped_regions, merge_group,
+    offset_regions, upscale_regions, BarcodeRegion, DetectError, DetectionConfig, FftPlans,
+    GrayImage, GridLayout, PixelSource, SectionResponse,
+};
+
+/// Lazily yields barcode regions from the top of an image down.
+///
+/// Bands are analyzed only when `next` is called, and a region is yielded as
+/// soon as the first band below it comes up empty, so `.take(1)` on an image
+/// with a barcode near the top skips the FFT work for the rest of the page.
+/// Create one with `detect_regions_iter`.
+///
+/// Regions are grouped the way the default `MergeStrategy::Rows` pipeline
+/// groups them: runs within a band are merged, and with `merge_vertical` the
+/// bands of an unbroken stack are merged too. Everything that needs the whole
+/// grid is skipped: `dual_polarity`, `auto_invert`, `smoothing_kernel`,
+/// `adaptive_run_cap`, `merge_iou`, `trim_ratio` and `dedupe`, and
+/// `estimated_module_width` stays `0.0`. `confidence` is relative to the
+/// strongest section scanned so far rather than in the whole image.
+///
+/// `min_regions` and `max_regions` are ignored: the iterator yields every
+/// region it finds, so cap the count with `take` and check it on the
+/// collected result. With `verify` each region is checked as it's closed; a
+/// rejected one is dropped and scanning carries on below it, so `.take(1)`
+/// reads further down the page when the first candidate fails.
+pub struct RegionIter<'a, P: PixelSource + ?Sized> {
+    img: &'a P,
+    /// The downscaled copy sections are analyzed on, when `config.downscale` asks for one.
+    working: Option<GrayImage>,
+    config: DetectionConfig,
+    planner: FftPlanner<f32>,
+    layout: GridLayout,
+    /// Index into `layout.bands` of the next band to analyze.
+    next_band: usize,
+    /// Per-band regions of the stack of bands currently being merged.
+    group: Vec<BarcodeRegion>,
+    /// Strongest section magnitude seen so far.
+    max_magnitude: f32,
+    /// Bottom of the last band, in full-resolution pixels.
+    scanned_height: u32,
+}
+
+/// Detects barcode-like regions one at a time, analyzing bands as they're needed.
+///
+/// See `RegionIter` for which settings apply, including why `min_regions`
+/// and `max_regions` have no effect here; use `detect_in_source` when the
+/// whole-grid passes matter.
+///
+/// # Arguments
+///
+/// * `img` - The image to scan.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// An iterator over the regions, ordered by the band they end in, or
+/// `DetectError::EmptyImage` for a zero-sized image. Layout errors such as
+/// `DetectError::TooManySections` are reported here, before any region.
+///
+/// # Example
+///
+/// ```no_run
+/// # use bar_dec::detect_regions_iter;
+/// let img = image::open("label.png")?.to_luma8();
+/// let first = detect_regions_iter(&img, None)?.find(|region| region.raw_magnitude > 80.0);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn detect_regions_iter<P: PixelSource + ?Sized>(
+    img: &P,
+    config: Option<DetectionConfig>,
+) -> Result<RegionIter<'_, P>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    RegionIter::new(img, config, &mut Vec::new())
+}
+
+impl<'a, P: PixelSource + ?Sized> RegionIter<'a, P> {
+    /// Sets up the scan of `img` with settings that already passed
+    /// `DetectionConfig::validate`; see `detect_regions_iter`. Notes on the
+    /// downscale and the grid layout are pushed onto `warnings`.
+    pub(crate) fn new(
+        img: &'a P,
+        config: DetectionConfig,
+        warnings: &mut Vec<String>,
+    ) -> Result<Self, DetectError> {
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return Err(DetectError::EmptyImage { width, height });
+        }
+        // Check the origin once, so shifting each region later can't fail
+        offset_regions(&mut [], &config, width, height)?;
+
+        let factor = config.downscale.max(1);
+        let working = (factor > 1).then(|| {
+            warnings.push(downscale_warning(factor));
+            box_downscale(img, factor)
+        });
+        let (working_width, working_height) = working
+            .as_ref()
+            .map_or((width, height), |working| working.dimensions());
+        let layout = grid_layout(working_width, working_height, &config, warnings)?;
+        let scanned_height = layout.bands.last().map_or(0, |&(y_start, band_height)| {
+            clamped_mul((y_start + band_height) as u64, factor, height)
+        });
+
+        Ok(RegionIter {
+            img,
+            working,
+            config,
+            planner: FftPlanner::new(),
+            layout,
+            next_band: 0,
+            group: Vec::new(),
+            max_magnitude: 0.0,
+            scanned_height,
+        })
+    }
+}
+
+/// Computes the section responses of the band `(section_y_start, band_height)` of `working`.
+fn band_responses<W: PixelSource + ?Sized>(
+    working: &W,
+    (section_y_start, band_height): (u32, u32),
+    layout: &GridLayout,
+    config: &DetectionConfig,
+    planner: &mut FftPlanner<f32>,
+) -> Vec<SectionResponse> {
+    compute_section_magnitudes(
+        working,
+        section_y_start,
+        band_height,
+        layout.section_width,
+        layout.sections_per_width,
+        config,
+        &mut FftPlans::new(planner),
+    )
+}
+
+impl<P: PixelSource + ?Sized> RegionIter<'_, P> {
+    /// Merges the current group into one region and finishes it at full resolution.
+    ///
+    /// Returns `None` when the group is empty or `config.verify` rejects the region.
+    fn close_group(&mut self) -> Option<BarcodeRegion> {
+        let mut region = merge_group(&self.group, self.config.confidence_merge)?;
+        self.group.clear();
+
+        if self.max_magnitude > 0.0 {
+            region.confidence = region.raw_magnitude / self.max_magnitude;
+        }
+        let (width, height) = self.img.dimensions();
+        let factor = self.config.downscale.max(1);
+        if factor > 1 {
+            upscale_regions(std::slice::from_mut(&mut region), factor, width, height);
+        }
+
+        if !finish_region(self.img, &mut region, &self.config, &mut self.planner) {
+            return None;
+        }
+        mark_clipped_regions(
+            std::slice::from_mut(&mut region),
+            clamped_mul(self.layout.scanned_width as u64, factor, width),
+            self.scanned_height,
+        );
+        offset_regions(
+            std::slice::from_mut(&mut region),
+            &self.config,
+            width,
+            height,
+        )
+        .expect("origin checked in detect_regions_iter");
+        Some(region)
+    }
+}
+
+impl<P: PixelSource + ?Sized> Iterator for RegionIter<'_, P> {
+    type Item = BarcodeRegion;
+
+    fn next(&mut self) -> Option<BarcodeRegion> {
+        loop {
+            let Some(&(section_y_start, band_height)) = self.layout.bands.get(self.next_band)
+            else {
+                // Out of bands: whatever is still open ends at the bottom
+                if self.group.is_empty() {
+                    return None;
+                }
+                match self.close_group() {
+                    Some(region) => return Some(region),
+                    None => continue,
+                }
+            };
+            self.next_band += 1;
+
+            let band = (section_y_start, band_height);
+            let responses = match &self.working {
+                Some(working) => {
+                    band_responses(working, band, &self.layout, &self.config, &mut self.planner)
+                }
+                None => band_responses(
+                    self.img,
+                    band,
+                    &self.layout,
+                    &self.config,
+                    &mut self.planner,
+                ),
+            };
+            let raw_magnitudes: Vec<f32> = responses.iter().map(|r| r.magnitude).collect();
+            let magnitudes = apply_threshold(&raw_magnitudes, self.config.section_threshold());
+            self.max_magnitude = magnitudes
+                .iter()
+                .cloned()
+                .fold(self.max_magnitude, f32::max);
+
+            let mut band_regions = Vec::new();
+            detect_regions(
+                &magnitudes,
+                section_y_start,
+                band_height,
+                self.layout.section_width,
+                self.layout.scanned_width,
+                self.config.consecutive_threshold,
+                &mut band_regions,
+            );
+
+            // An empty band ends the stack above it
+            if band_regions.is_empty() {
+                if !self.group.is_empty() {
+                    if let Some(region) = self.close_group() {
+                        return Some(region);
+                    }
+                }
+                continue;
+            }
+
+            self.group
+                .extend(merge_group(&band_regions, self.config.confidence_merge));
+            if !self.config.merge_vertical {
+                if let Some(region) = self.close_group() {
+                    return Some(region);
+                }
+            }
+        }
+    }
+}
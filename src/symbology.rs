@@ -0,0 +1,266 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+use crate::{binarize_luma_line, BarcodeRegion, DetectionConfig, PixelSource};
+
+/// A rough guess at the symbology of a detected barcode.
+///
+/// Guesses are derived from the bar/space structure of a single scanline and
+/// are only approximate: a noisy scan or a loose region easily shifts the
+/// counts they rely on, so treat them as hints rather than decoded facts.
+#[cfg_attr(feature = "python", pyclass(eq, eq_int))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Symbology {
+    Ean13,
+    Ean8,
+    Code128,
+    Code39,
+    Itf,
+}
+
+/// Bar/space structure measured along one scanline of a region.
+///
+/// Build one with `region_profile` from an image or with
+/// `RegionProfile::from_binary_line` from an already binarized scanline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionProfile {
+    /// Total width of the symbol expressed in modules.
+    pub module_count: u32,
+    /// Number of dark bars crossed by the scanline.
+    pub bar_count: u32,
+    /// Number of distinct element widths, in whole modules.
+    pub width_classes: u32,
+}
+
+impl RegionProfile {
+    /// Builds a profile from a binarized scanline (1.0 = white, 0.0 = black).
+    ///
+    /// Leading and trailing white runs are treated as quiet zone and ignored.
+    /// Returns `None` when the line crosses fewer than two bars.
+    pub fn from_binary_line(binary_line: &[f32]) -> Option<Self> {
+        let start = binary_line.iter().position(|&v| v == 0.0)?;
+        let end = binary_line.iter().rposition(|&v| v == 0.0)? + 1;
+
+        let mut runs = Vec::new();
+        let mut bar_count = 0;
+        let mut current_value = binary_line[start];
+        let mut count = 0;
+
+        for &value in &binary_line[start..end] {
+            if value == current_value {
+                count += 1;
+            } else {
+                if current_value == 0.0 {
+                    bar_count += 1;
+                }
+                runs.push(count);
+                current_value = value;
+                count = 1;
+            }
+        }
+        runs.push(count);
+        bar_count += 1;
+
+        if bar_count < 2 {
+            return None;
+        }
+
+        // Average the runs that look like single modules to soften pixel jitter
+        let narrowest = *runs.iter().min()? as f32;
+        let single_modules: Vec<f32> = runs
+            .iter()
+            .map(|&run| run as f32)
+            .filter(|&run| run < narrowest * 1.5)
+            .collect();
+        let module_width = single_modules.iter().sum::<f32>() / single_modules.len() as f32;
+
+        let mut widths: Vec<u32> = runs
+            .iter()
+            .map(|&run| ((run as f32 / module_width).round() as u32).max(1))
+            .collect();
+        let module_count = widths.iter().sum();
+        widths.sort_unstable();
+        widths.dedup();
+
+        Some(RegionProfile {
+            module_count,
+            bar_count,
+            width_classes: widths.len() as u32,
+        })
+    }
+}
+
+/// Makes a best-effort symbology guess from a region's bar/space profile.
+///
+/// The heuristics only look at bar counts, module counts and the number of
+/// distinct element widths:
+/// - EAN-13 / UPC-A: 30 bars over 95 modules
+/// - EAN-8: 22 bars over 67 modules
+/// - Code 39: two element widths, 5 bars per character
+/// - ITF: two element widths, 5 bars per digit pair plus start/stop
+/// - Code 128: 3 bars / 11 modules per symbol plus a 4 bar / 13 module stop
+///
+/// # Arguments
+///
+/// * `profile` - The scanline profile of the region.
+///
+/// # Returns
+///
+/// `Some(Symbology)` when one of the heuristics matches, otherwise `None`.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{guess_symbology, RegionProfile, Symbology};
+/// let profile = RegionProfile { module_count: 95, bar_count: 30, width_classes: 4 };
+/// assert_eq!(guess_symbology(&profile), Some(Symbology::Ean13));
+/// ```
+pub fn guess_symbology(profile: &RegionProfile) -> Option<Symbology> {
+    // Allow roughly 5% slack on module counts for rounding and blur
+    let modules_near = |expected: u32| profile.module_count.abs_diff(expected) <= expected / 20 + 1;
+    let bars = profile.bar_count;
+
+    if bars == 30 && modules_near(95) {
+        return Some(Symbology::Ean13);
+    }
+    if bars == 22 && modules_near(67) {
+        return Some(Symbology::Ean8);
+    }
+
+    if profile.width_classes <= 2 {
+        if bars >= 15 && bars.is_multiple_of(5) {
+            return Some(Symbology::Code39);
+        }
+        if bars >= 9 && (bars - 4).is_multiple_of(5) {
+            return Some(Symbology::Itf);
+        }
+        return None;
+    }
+
+    if bars >= 10 && (bars - 4).is_multiple_of(3) && modules_near(11 * (bars - 4) / 3 + 13) {
+        return Some(Symbology::Code128);
+    }
+
+    None
+}
+
+/// Samples the center row of a region and measures its bar/space profile.
+///
+/// # Arguments
+///
+/// * `img` - A reference to the grayscale image buffer
+/// * `region` - The region to analyse; its bounds are clamped to the image.
+/// * `config` - Detection settings; `binarization` picks the row's cutoff.
+///
+/// # Returns
+///
+/// The profile of the center row, or `None` when it crosses fewer than two bars.
+///
+/// # Example
+///
+/// ```
+/// # use bar_dec::{region_profile, BarcodeRegion, DetectionConfig};
+/// # use image::GrayImage;
+/// // Vertical bars 3 pixels wide with 3 pixel gaps
+/// let img = GrayImage::from_fn(200, 50, |x, _| image::Luma([if x % 6 < 3 { 0 } else { 255 }]));
+/// let region = BarcodeRegion::new(10, 190, 0, 50)?;
+/// let profile = region_profile(&img, &region, &DetectionConfig::default()).unwrap();
+/// assert_eq!(profile.bar_count, 30);
+/// assert_eq!(profile.width_classes, 1);
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+pub fn region_profile<P: PixelSource + ?Sized>(
+    img: &P,
+    region: &BarcodeRegion,
+    config: &DetectionConfig,
+) -> Option<RegionProfile> {
+    let x_end = region.x_end.min(img.width());
+    let y = ((region.y_start + region.y_end) / 2).min(img.height().checked_sub(1)?);
+    if region.x_start >= x_end {
+        return None;
+    }
+
+    let line: Vec<u8> = (region.x_start..x_end).map(|x| img.luma_at(x, y)).collect();
+
+    RegionProfile::from_binary_line(&binarize_luma_line(&line, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Left-hand odd-parity (L) patterns of the EAN digits; `true` is a dark module.
+    const EAN_L: [&str; 10] = [
+        "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111", "0111011",
+        "0110111", "0001011",
+    ];
+    /// Which left-hand digits use even parity (G), picked by the leading digit.
+    const EAN_PARITY: [&str; 10] = [
+        "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG", "LGGLLG", "LGGGLG", "LGLGLG", "LGLGGL",
+        "LGGLGL",
+    ];
+
+    /// The 95 modules of the EAN-13 symbol for `digits`, guards included.
+    fn ean13_modules(digits: &str) -> Vec<bool> {
+        let digits: Vec<usize> = digits.bytes().map(|b| (b - b'0') as usize).collect();
+        let bits = |pattern: &str| pattern.bytes().map(|b| b == b'1').collect::<Vec<_>>();
+        let mut modules = bits("101");
+        for (&digit, parity) in digits[1..7].iter().zip(EAN_PARITY[digits[0]].bytes()) {
+            let l = bits(EAN_L[digit]);
+            if parity == b'G' {
+                // G is the R pattern mirrored, and R is L inverted
+                modules.extend(l.iter().rev().map(|&dark| !dark));
+            } else {
+                modules.extend(l);
+            }
+        }
+        modules.extend(bits("01010"));
+        for &digit in &digits[7..] {
+            modules.extend(bits(EAN_L[digit]).iter().map(|&dark| !dark));
+        }
+        modules.extend(bits("101"));
+        modules
+    }
+
+    /// A binarized scanline of `modules`, `module` pixels each, inside a quiet zone.
+    fn scanline(modules: &[bool], module: usize) -> Vec<f32> {
+        let quiet = vec![1.0; 10 * module];
+        let symbol = modules
+            .iter()
+            .flat_map(|&dark| std::iter::repeat_n(if dark { 0.0 } else { 1.0 }, module));
+        quiet
+            .iter()
+            .copied()
+            .chain(symbol)
+            .chain(quiet.clone())
+            .collect()
+    }
+
+    #[test]
+    fn ean13_profile_spans_95_modules() {
+        let modules = ean13_modules("4006381333931");
+        assert_eq!(modules.len(), 95);
+
+        let profile = RegionProfile::from_binary_line(&scanline(&modules, 3)).unwrap();
+        assert_eq!(profile.module_count, 95);
+        assert_eq!(profile.bar_count, 30);
+        assert_eq!(guess_symbology(&profile), Some(Symbology::Ean13));
+    }
+
+    #[test]
+    fn line_without_two_bars_has_no_profile() {
+        assert_eq!(RegionProfile::from_binary_line(&[1.0; 40]), None);
+        assert_eq!(RegionProfile::from_binary_line(&scanline(&[true], 4)), None);
+    }
+
+    #[test]
+    fn unmatched_profile_guesses_nothing() {
+        let profile = RegionProfile {
+            module_count: 40,
+            bar_count: 7,
+            width_classes: 4,
+        };
+        assert_eq!(guess_symbology(&profile), None);
+    }
+}
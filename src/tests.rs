@@ -0,0 +1,3328 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+use super::*;
+
+/// The bounds of `region` as `(x_start, x_end, y_start, y_end)`.
+fn bounds(region: &BarcodeRegion) -> (u32, u32, u32, u32) {
+    (region.x_start, region.x_end, region.y_start, region.y_end)
+}
+
+/// A region with the given bounds and scores.
+fn region(
+    (x_start, x_end, y_start, y_end): (u32, u32, u32, u32),
+    confidence: f32,
+    raw_magnitude: f32,
+) -> BarcodeRegion {
+    BarcodeRegion {
+        x_start,
+        x_end,
+        y_start,
+        y_end,
+        confidence,
+        raw_magnitude,
+        ..BarcodeRegion::default()
+    }
+}
+
+/// A white `width` x `height` image with a barcode of `module`-pixel bars
+/// drawn over `x_start..x_end` x `y_start..y_end`.
+fn barcode_image(
+    (width, height): (u32, u32),
+    (x_start, x_end, y_start, y_end): (u32, u32, u32, u32),
+    module: u32,
+) -> Vec<u8> {
+    let mut img = vec![255; (width * height) as usize];
+    draw_barcode(&mut img, width, (x_start, x_end, y_start, y_end), module);
+    img
+}
+
+/// Draws bars and spaces of one to three modules, in a fixed pseudo-random order.
+fn draw_barcode(
+    img: &mut [u8],
+    width: u32,
+    (x_start, x_end, y_start, y_end): (u32, u32, u32, u32),
+    module: u32,
+) {
+    let mut seed = 12345u32;
+    let mut pattern = Vec::new();
+    let mut bar = true;
+    while (pattern.len() as u32) < x_end - x_start {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        let element = ((seed >> 16) % 3 + 1) * module;
+        pattern.extend((0..element).map(|_| if bar { 0 } else { 255 }));
+        bar = !bar;
+    }
+    for y in y_start..y_end {
+        let row = (y * width) as usize;
+        img[row + x_start as usize..row + x_end as usize]
+            .copy_from_slice(&pattern[..(x_end - x_start) as usize]);
+    }
+}
+
+/// The `width` x `height` image `img` with rows and columns swapped.
+fn transposed(img: &[u8], width: u32, height: u32) -> Vec<u8> {
+    (0..width)
+        .flat_map(|x| (0..height).map(move |y| img[(y * width + x) as usize]))
+        .collect()
+}
+
+/// Settings that split an 800px-wide test image into sections wide enough for a barcode.
+fn small_image_config() -> DetectionConfig {
+    DetectionConfig {
+        sections_x: Some(12),
+        ..DetectionConfig::default()
+    }
+}
+
+/// Runs tiled detection on `img` cut into strips of `strip_height` rows.
+fn detect_in_strips(
+    img: &[u8],
+    (width, height): (u32, u32),
+    strip_height: u32,
+    config: DetectionConfig,
+) -> Result<Vec<BarcodeRegion>, DetectError> {
+    let mut strips = img
+        .chunks((width * strip_height) as usize)
+        .zip((0..).step_by(strip_height as usize));
+    detect_barcode_regions_tiled(
+        width,
+        height,
+        || Ok(strips.next().map(|(strip, y)| (strip.to_vec(), y))),
+        Some(config),
+    )
+}
+
+/// Every ordering of `items`.
+fn permutations<T: Clone>(items: &[T]) -> Vec<Vec<T>> {
+    if items.len() <= 1 {
+        return vec![items.to_vec()];
+    }
+    let mut all = Vec::new();
+    for i in 0..items.len() {
+        let mut rest = items.to_vec();
+        let first = rest.remove(i);
+        for mut tail in permutations(&rest) {
+            tail.insert(0, first.clone());
+            all.push(tail);
+        }
+    }
+    all
+}
+
+#[test]
+fn merge_group_is_order_independent() {
+    // Scores are powers of two so every summation order is exact
+    let group = [
+        region((40, 90, 16, 24), 0.5, 64.0),
+        region((10, 60, 0, 8), 0.25, 32.0),
+        region((70, 120, 8, 16), 1.0, 128.0),
+        region((30, 50, 24, 32), 0.125, 16.0),
+    ];
+    for confidence_merge in [
+        ConfidenceMerge::Mean,
+        ConfidenceMerge::Max,
+        ConfidenceMerge::Sum,
+        ConfidenceMerge::MagnitudeWeighted,
+    ] {
+        let expected = merge_group(&group, confidence_merge).unwrap();
+        assert_eq!(bounds(&expected), (10, 120, 0, 32));
+        for shuffled in permutations(&group) {
+            let merged = merge_group(&shuffled, confidence_merge).unwrap();
+            assert_eq!(bounds(&merged), bounds(&expected));
+            assert_eq!(merged.confidence, expected.confidence);
+            assert_eq!(merged.raw_magnitude, expected.raw_magnitude);
+        }
+    }
+}
+
+#[test]
+fn merge_group_of_nothing_is_none() {
+    assert!(merge_group(&[], ConfidenceMerge::Mean).is_none());
+}
+
+#[test]
+fn tiled_detection_merges_a_barcode_across_a_seam() {
+    let size = (800, 600);
+    let img = barcode_image(size, (200, 600, 150, 300), 3);
+    let whole =
+        detect_barcode_regions_slice(&img, size.0, size.1, Some(small_image_config())).unwrap();
+    assert_eq!(whole.len(), 1);
+
+    // The seam at y = 200 cuts through the barcode
+    let tiled = detect_in_strips(&img, size, 100, small_image_config()).unwrap();
+    assert_eq!(tiled.len(), 1);
+    assert_eq!(bounds(&tiled[0]), bounds(&whole[0]));
+    assert!(!tiled[0].clipped);
+    assert_eq!(tiled[0].bar_count, whole[0].bar_count);
+    assert_eq!(tiled[0].symbology_guess, whole[0].symbology_guess);
+    assert_eq!(tiled[0].best_scanline_y, whole[0].best_scanline_y);
+}
+
+#[test]
+fn tiled_detection_keeps_a_region_ending_at_a_strip_edge() {
+    let size = (800, 600);
+    let img = barcode_image(size, (200, 600, 100, 200), 3);
+    let whole =
+        detect_barcode_regions_slice(&img, size.0, size.1, Some(small_image_config())).unwrap();
+
+    // The barcode ends on the seam, so it is held back but never merged
+    let tiled = detect_in_strips(&img, size, 200, small_image_config()).unwrap();
+    assert_eq!(tiled.len(), 1);
+    assert_eq!(bounds(&tiled[0]), bounds(&whole[0]));
+    assert!(!tiled[0].clipped);
+    assert_eq!(tiled[0].bar_count, whole[0].bar_count);
+}
+
+#[test]
+fn tiled_detection_flags_clipping_at_image_edges_only() {
+    let size = (800, 600);
+    let img = barcode_image(size, (200, 600, 500, 600), 3);
+    let tiled = detect_in_strips(&img, size, 100, small_image_config()).unwrap();
+    assert_eq!(tiled.len(), 1);
+    assert!(tiled[0].clipped);
+}
+
+#[test]
+fn tiled_detection_keeps_the_first_max_regions() {
+    let size = (800, 600);
+    let mut img = barcode_image(size, (200, 600, 350, 450), 3);
+    draw_barcode(&mut img, size.0, (200, 600, 100, 200), 3);
+    assert_eq!(
+        detect_in_strips(&img, size, 100, small_image_config())
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let config = DetectionConfig {
+        max_regions: Some(1),
+        ..small_image_config()
+    };
+    let tiled = detect_in_strips(&img, size, 100, config).unwrap();
+    assert_eq!(tiled.len(), 1);
+    assert_eq!(tiled[0].y_start, 100);
+}
+
+#[test]
+fn narrow_roi_still_scans_vertical_barcodes() {
+    // Bars run across a column narrower than one whole-image section
+    let (width, height) = (2000, 1000);
+    let img = transposed(
+        &barcode_image((height, width), (200, 800, 520, 680), 3),
+        height,
+        width,
+    );
+    let config = DetectionConfig {
+        sections_x: Some(8),
+        ..DetectionConfig::default()
+    };
+    let roi = Some((500, 100, 200, 800));
+
+    let horizontal = detect_barcode_regions(
+        img.clone(),
+        width,
+        height,
+        Some(config.clone()),
+        Orientation::Horizontal,
+        roi,
+    )
+    .unwrap();
+    assert!(horizontal.is_empty());
+
+    for orientation in [Orientation::Vertical, Orientation::Both] {
+        let regions = detect_barcode_regions(
+            img.clone(),
+            width,
+            height,
+            Some(config.clone()),
+            orientation,
+            roi,
+        )
+        .unwrap();
+        assert_eq!(regions.len(), 1, "{orientation:?}");
+        let region = &regions[0];
+        assert!(
+            500 <= region.x_start && region.x_end <= 700,
+            "{orientation:?}"
+        );
+        assert!(
+            region.y_start <= 210 && 790 <= region.y_end,
+            "{orientation:?}"
+        );
+    }
+}
+
+/// Wraps a pixel source and counts the pixels read from it.
+struct CountingSource<'a, P: PixelSource + ?Sized> {
+    inner: &'a P,
+    reads: AtomicU64,
+    /// Bottom-most row read so far.
+    last_row: AtomicU32,
+}
+
+impl<'a, P: PixelSource + ?Sized> CountingSource<'a, P> {
+    fn new(inner: &'a P) -> Self {
+        CountingSource {
+            inner,
+            reads: AtomicU64::new(0),
+            last_row: AtomicU32::new(0),
+        }
+    }
+}
+
+impl<P: PixelSource + ?Sized> PixelSource for CountingSource<'_, P> {
+    fn luma_at(&self, x: u32, y: u32) -> u8 {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        self.last_row.fetch_max(y, Ordering::Relaxed);
+        self.inner.luma_at(x, y)
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.inner.dimensions()
+    }
+}
+
+#[test]
+fn dark_background_reads_only_the_border() {
+    // A dark frame 10px wide (5% of 200) around a white page
+    let (width, height) = (300, 200);
+    let img = GrayImage::from_fn(width, height, |x, y| {
+        let border = x < 10 || y < 10 || x >= width - 10 || y >= height - 10;
+        Luma([if border { 20 } else { 255 }])
+    });
+    let counting = CountingSource::new(&img);
+    assert!(has_dark_background(&counting));
+    let border_pixels = (width * height - (width - 20) * (height - 20)) as u64;
+    assert_eq!(counting.reads.load(Ordering::Relaxed), border_pixels);
+
+    let light = GrayImage::from_pixel(width, height, Luma([200]));
+    assert!(!has_dark_background(&light));
+}
+
+#[test]
+fn dark_background_handles_images_thinner_than_the_margin() {
+    for (width, height) in [(1, 1), (1, 40), (40, 1), (2, 3)] {
+        let dark = GrayImage::from_pixel(width, height, Luma([0]));
+        let counting = CountingSource::new(&dark);
+        assert!(has_dark_background(&counting));
+        // Every pixel is on the border, and each is read once
+        assert_eq!(
+            counting.reads.load(Ordering::Relaxed),
+            (width * height) as u64
+        );
+    }
+}
+
+#[test]
+fn region_iter_take_one_stops_below_the_first_barcode() {
+    let (width, height) = (800, 600);
+    let pixels = barcode_image((width, height), (200, 600, 20, 120), 3);
+    let img = GrayImage::from_raw(width, height, pixels).unwrap();
+
+    let counting = CountingSource::new(&img);
+    let first: Vec<_> = detect_regions_iter(&counting, Some(small_image_config()))
+        .unwrap()
+        .take(1)
+        .collect();
+    assert_eq!(first.len(), 1);
+    assert!(first[0].y_start <= 25 && 115 <= first[0].y_end);
+    // The region closes on the first empty band below it
+    assert!(counting.last_row.load(Ordering::Relaxed) < 150);
+
+    let everything = CountingSource::new(&img);
+    assert_eq!(
+        detect_regions_iter(&everything, Some(small_image_config()))
+            .unwrap()
+            .count(),
+        1
+    );
+    assert!(everything.last_row.load(Ordering::Relaxed) >= 590);
+}
+
+#[test]
+fn barcode_flush_right_is_clipped() {
+    let size = (4000, 600);
+    let img = barcode_image(size, (2000, 4000, 150, 300), 3);
+    let regions =
+        detect_barcode_regions(img, size.0, size.1, None, Orientation::Horizontal, None).unwrap();
+    assert_eq!(regions.len(), 1);
+    assert!(regions[0].clipped);
+    assert!(drop_clipped_regions(regions).is_empty());
+
+    let img = barcode_image(size, (1000, 3000, 150, 300), 3);
+    let regions =
+        detect_barcode_regions(img, size.0, size.1, None, Orientation::Horizontal, None).unwrap();
+    assert_eq!(regions.len(), 1);
+    assert!(!regions[0].clipped);
+    assert_eq!(drop_clipped_regions(regions).len(), 1);
+}
+
+#[test]
+fn detection_survives_2x_downscale() {
+    let size = (4000, 600);
+    let img = barcode_image(size, (1000, 3000, 150, 300), 3);
+    let full = detect_barcode_regions(
+        img.clone(),
+        size.0,
+        size.1,
+        None,
+        Orientation::Horizontal,
+        None,
+    )
+    .unwrap();
+    let config = DetectionConfig {
+        downscale: 2,
+        ..DetectionConfig::default()
+    };
+    let half = detect_barcode_regions(
+        img,
+        size.0,
+        size.1,
+        Some(config),
+        Orientation::Horizontal,
+        None,
+    )
+    .unwrap();
+
+    // Boxes come back in full-resolution coordinates
+    assert_eq!(full.len(), 1);
+    assert_eq!(half.len(), 1);
+    assert_eq!(bounds(&half[0]), bounds(&full[0]));
+}
+
+#[test]
+fn downscale_past_the_image_size_is_an_error() {
+    let img = barcode_image((800, 600), (200, 600, 150, 300), 3);
+    let config = DetectionConfig {
+        downscale: 70_000,
+        ..small_image_config()
+    };
+    assert!(matches!(
+        detect_barcode_regions_slice(&img, 800, 600, Some(config.clone())),
+        Err(DetectError::ImageTooSmall {
+            width: 0,
+            height: 0
+        })
+    ));
+    assert!(matches!(
+        detect_regions_iter(&image_view(&img, 800, 600).unwrap(), Some(config)),
+        Err(DetectError::ImageTooSmall { .. })
+    ));
+}
+
+/// Fills `x_start..x_end` x `y_start..y_end` of `img` with black and white pixel noise.
+fn draw_noise(img: &mut [u8], width: u32, (x_start, x_end, y_start, y_end): (u32, u32, u32, u32)) {
+    let mut seed = 7u32;
+    for y in y_start..y_end {
+        for x in x_start..x_end {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            img[(y * width + x) as usize] = if (seed >> 16) & 1 == 0 { 0 } else { 255 };
+        }
+    }
+}
+
+#[test]
+fn verify_drops_regions_without_periodic_content() {
+    let size = (4000, 600);
+    let mut img = barcode_image(size, (1000, 3000, 100, 200), 3);
+    draw_noise(&mut img, size.0, (1000, 3000, 350, 450));
+
+    let unverified = detect_barcode_regions(
+        img.clone(),
+        size.0,
+        size.1,
+        None,
+        Orientation::Horizontal,
+        None,
+    )
+    .unwrap();
+    assert!(unverified.iter().any(|region| region.y_start >= 350));
+
+    let config = DetectionConfig {
+        verify: true,
+        ..DetectionConfig::default()
+    };
+    let verified = detect_barcode_regions(
+        img,
+        size.0,
+        size.1,
+        Some(config),
+        Orientation::Horizontal,
+        None,
+    )
+    .unwrap();
+    assert_eq!(verified.len(), 1);
+    assert_eq!(bounds(&verified[0]), (1000, 3000, 100, 200));
+}
+
+#[test]
+fn narrow_image_warns_about_the_section_clamp() {
+    // Portrait images are split into 60 sections, more than 50 columns hold
+    let (width, height) = (50, 200);
+    let (_, warnings) = detect_with_warnings(vec![255; 50 * 200], width, height, None).unwrap();
+    assert!(
+        warnings
+            .iter()
+            .any(|warning| warning.contains("narrower than 60 sections")),
+        "{warnings:?}"
+    );
+
+    let (_, warnings) = detect_with_warnings(vec![255; 4000 * 600], 4000, 600, None).unwrap();
+    assert!(!warnings.iter().any(|warning| warning.contains("narrower")));
+}
+
+#[test]
+fn max_regions_keeps_layout_warnings_and_whole_grid_settings() {
+    let (width, height) = (50, 200);
+    let config = DetectionConfig {
+        max_regions: Some(1),
+        ..DetectionConfig::default()
+    };
+    let (_, warnings) =
+        detect_with_warnings(vec![255; 50 * 200], width, height, Some(config)).unwrap();
+    assert!(
+        warnings
+            .iter()
+            .any(|warning| warning.contains("narrower than 60 sections")),
+        "{warnings:?}"
+    );
+
+    // Settings the band-by-band scan can't apply fall back to the full scan
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 350, 450), 3);
+    draw_barcode(&mut img, width, (200, 600, 100, 200), 3);
+    for config in [
+        DetectionConfig {
+            merge_strategy: MergeStrategy::ConnectedComponents,
+            ..small_image_config()
+        },
+        DetectionConfig {
+            merge_horizontal: false,
+            ..small_image_config()
+        },
+        DetectionConfig {
+            max_vertical_gap: Some(200),
+            ..small_image_config()
+        },
+        DetectionConfig {
+            dual_polarity: true,
+            ..small_image_config()
+        },
+    ] {
+        let detect = |max_regions| {
+            let config = DetectionConfig {
+                max_regions,
+                ..config.clone()
+            };
+            let mut found: Vec<_> = detect_barcode_regions_slice(&img, width, height, Some(config))
+                .unwrap()
+                .iter()
+                .map(bounds)
+                .collect();
+            found.sort_by_key(|&(x_start, _, _, y_end)| (y_end, x_start));
+            found
+        };
+        let full = detect(None);
+        assert_eq!(detect(Some(100)), full, "{config:?}");
+        assert_eq!(detect(Some(1)), full[..1], "{config:?}");
+    }
+}
+
+#[test]
+fn max_confidence_merge_picks_the_strongest_component() {
+    let group = [
+        region((0, 10, 0, 5), 0.25, 10.0),
+        region((10, 20, 0, 5), 0.75, 30.0),
+        region((20, 30, 0, 5), 0.5, 20.0),
+    ];
+    let max = merge_group(&group, ConfidenceMerge::Max).unwrap();
+    assert_eq!(max.confidence, 0.75);
+    assert_eq!(max.raw_magnitude, 30.0);
+
+    assert_eq!(ConfidenceMerge::default(), ConfidenceMerge::Mean);
+    let mean = merge_group(&group, ConfidenceMerge::Mean).unwrap();
+    assert_eq!(mean.confidence, 0.5);
+    let sum = merge_group(&group, ConfidenceMerge::Sum).unwrap();
+    assert_eq!(sum.confidence, 1.5);
+}
+
+#[test]
+fn edge_map_finds_the_same_regions_as_pixels() {
+    let (width, height) = (4000, 600);
+    // Spans whole sections, so the edge at the barcode's end falls on a section border
+    let img = barcode_image((width, height), (1000, 3000, 150, 300), 3);
+    let edges: Vec<u8> = img
+        .chunks(width as usize)
+        .flat_map(|row| {
+            let mut gradient = vec![0];
+            gradient.extend(row.windows(2).map(|pair| pair[0].abs_diff(pair[1])));
+            gradient
+        })
+        .collect();
+
+    let from_pixels =
+        detect_barcode_regions(img, width, height, None, Orientation::Horizontal, None).unwrap();
+    let from_edges = detect_from_edges(edges, width, height, None).unwrap();
+    assert_eq!(from_pixels.len(), 1);
+    assert_eq!(
+        from_edges.iter().map(bounds).collect::<Vec<_>>(),
+        from_pixels.iter().map(bounds).collect::<Vec<_>>()
+    );
+    // Edge maps have no bars to read
+    assert_eq!(from_edges[0].symbology_guess, None);
+    assert_eq!(from_edges[0].bar_count, 0);
+}
+
+#[test]
+fn nyquist_bin_counts_once_or_not_at_all() {
+    // An alternating line of even length puts all its energy in bin N/2
+    let len = 16;
+    let mut spectrum: Vec<Complex<f32>> = (0..len)
+        .map(|n| Complex::new(if n % 2 == 0 { 1.0 } else { -1.0 }, 0.0))
+        .collect();
+    FftPlanner::new()
+        .plan_fft_forward(len)
+        .process(&mut spectrum);
+
+    for spectrum_sum in [SpectrumSum::OneSided, SpectrumSum::TwoSided] {
+        let counted = DetectionConfig {
+            spectrum_sum,
+            ..DetectionConfig::default()
+        };
+        assert!((spectrum_magnitude(&spectrum, &counted) - len as f32).abs() < 1e-3);
+
+        let dropped = DetectionConfig {
+            count_nyquist: false,
+            ..counted
+        };
+        assert!(spectrum_magnitude(&spectrum, &dropped).abs() < 1e-3);
+    }
+}
+
+#[cfg(feature = "tiff")]
+#[test]
+fn tiff_pages_are_detected_one_list_per_page() {
+    use tiff::encoder::{colortype::Gray8, TiffEncoder};
+
+    let (width, height) = (4000, 600);
+    let path = std::env::temp_dir().join(format!("bar_dec_two_pages_{}.tif", std::process::id()));
+    {
+        let mut encoder = TiffEncoder::new(std::fs::File::create(&path).unwrap()).unwrap();
+        let barcode = barcode_image((width, height), (1000, 3000, 150, 300), 3);
+        encoder
+            .write_image::<Gray8>(width, height, &barcode)
+            .unwrap();
+        let blank = vec![255; (width * height) as usize];
+        encoder.write_image::<Gray8>(width, height, &blank).unwrap();
+    }
+
+    let pages = detect_tiff_pages(&path, None);
+    std::fs::remove_file(&path).unwrap();
+    let pages = pages.unwrap();
+    assert_eq!(pages.len(), 2);
+    assert_eq!(
+        pages[0].iter().map(bounds).collect::<Vec<_>>(),
+        [(1000, 3000, 150, 300)]
+    );
+    assert!(pages[1].is_empty());
+}
+
+#[test]
+fn both_orientations_match_the_passes_run_one_by_one() {
+    // A horizontal barcode above a vertical one
+    let (width, height) = (2400, 2400);
+    let mut img = barcode_image((width, height), (600, 1800, 100, 400), 3);
+    let vertical = transposed(
+        &barcode_image((height, width), (1200, 2200, 960, 1440), 3),
+        height,
+        width,
+    );
+    for y in 1200..2200 {
+        let row = (y * width) as usize;
+        img[row + 960..row + 1440].copy_from_slice(&vertical[row + 960..row + 1440]);
+    }
+
+    let mut sequential: Vec<_> = detect_barcode_regions(
+        img.clone(),
+        width,
+        height,
+        None,
+        Orientation::Horizontal,
+        None,
+    )
+    .unwrap()
+    .iter()
+    .map(bounds)
+    .collect();
+    let vertical_pass = detect_barcode_regions(
+        transposed(&img, width, height),
+        height,
+        width,
+        None,
+        Orientation::Horizontal,
+        None,
+    )
+    .unwrap();
+    sequential.extend(
+        vertical_pass
+            .into_iter()
+            .map(|region| bounds(&transpose_region(region))),
+    );
+    assert_eq!(sequential.len(), 2);
+
+    let both = || {
+        detect_any_orientation(
+            img.clone(),
+            width,
+            height,
+            None,
+            OrientationConflict::KeepBoth,
+        )
+        .unwrap()
+    };
+    // The passes run on separate pool threads
+    let both = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap()
+        .install(both);
+    assert_eq!(both.iter().map(bounds).collect::<Vec<_>>(), sequential);
+}
+
+#[test]
+fn trailing_band_policy_on_a_partial_band() {
+    // 603 rows leave a 3-row band after 120 full ones, with bars in it
+    let (width, height) = (4000, 603);
+    let img = barcode_image((width, height), (1000, 3000, 600, 603), 3);
+
+    assert!(detect_barcode_regions(
+        img.clone(),
+        width,
+        height,
+        None,
+        Orientation::Horizontal,
+        None
+    )
+    .unwrap()
+    .is_empty());
+
+    let config = DetectionConfig {
+        trailing_band: TrailingBand::ScanClamped,
+        ..DetectionConfig::default()
+    };
+    let scanned = detect_barcode_regions(
+        img,
+        width,
+        height,
+        Some(config),
+        Orientation::Horizontal,
+        None,
+    )
+    .unwrap();
+    assert_eq!(
+        scanned.iter().map(bounds).collect::<Vec<_>>(),
+        [(1000, 3000, 600, 603)]
+    );
+}
+
+#[test]
+fn ensure_min_size_grows_around_the_center() {
+    let boxed = |x_start, x_end, y_start, y_end| BarcodeRegion {
+        x_start,
+        x_end,
+        y_start,
+        y_end,
+        ..BarcodeRegion::default()
+    };
+    let grown = boxed(95, 105, 45, 55).ensure_min_size(50, 50, 400, 300);
+    assert_eq!(bounds(&grown), (75, 125, 25, 75));
+
+    // Against the top-left corner the growth goes right and down instead
+    let corner = boxed(0, 10, 5, 15);
+    assert_eq!(
+        bounds(&corner.ensure_min_size(50, 50, 400, 300)),
+        (0, 50, 0, 50)
+    );
+
+    // Never past the image, and large enough sides stay as they are
+    let wide = boxed(10, 200, 0, 10);
+    assert_eq!(
+        bounds(&wide.ensure_min_size(50, 50, 400, 30)),
+        (10, 200, 0, 30)
+    );
+}
+
+#[test]
+fn pass_grid_marks_the_barcode_sections() {
+    let (width, height) = (4000, 600);
+    let img = barcode_image((width, height), (1000, 3000, 150, 300), 3);
+    let grid = compute_pass_grid(img, width, height, None).unwrap();
+
+    assert_eq!(grid.len(), 120);
+    for (band, row) in grid.iter().enumerate() {
+        assert_eq!(row.len(), 100);
+        for (section, &passed) in row.iter().enumerate() {
+            let on_barcode = (30..60).contains(&band) && (25..75).contains(&section);
+            assert_eq!(passed, on_barcode, "band {band} section {section}");
+        }
+    }
+}
+
+#[test]
+fn panoramic_image_keeps_exact_x_coordinates() {
+    // 100 sections of 700px; the barcode covers sections 86..96
+    let (width, height) = (70_000, 10);
+    let img = barcode_image((width, height), (60_200, 67_200, 0, 10), 3);
+    let regions =
+        detect_barcode_regions(img, width, height, None, Orientation::Horizontal, None).unwrap();
+
+    assert_eq!(regions.len(), 1);
+    assert_eq!((regions[0].x_start, regions[0].x_end), (60_200, 67_200));
+    assert!(regions[0].x_end <= width);
+}
+
+#[test]
+fn barcode_box_scores_higher_than_a_blank_one() {
+    let (width, height) = (4000, 600);
+    let img = barcode_image((width, height), (1000, 3000, 150, 300), 3);
+    let score = |y_start, y_end| {
+        let region = BarcodeRegion {
+            x_start: 1000,
+            x_end: 3000,
+            y_start,
+            y_end,
+            ..BarcodeRegion::default()
+        };
+        score_region(img.clone(), width, height, region, None).unwrap()
+    };
+
+    let barcode = score(150, 300);
+    let blank = score(400, 550);
+    assert!(barcode > 0.5, "{barcode}");
+    assert_eq!(blank, 0.0);
+}
+
+#[test]
+fn explicit_section_counts_ignore_the_aspect_ratio() {
+    // Portrait would pick 60 sections and landscape 100; neither applies here
+    for (width, height) in [(300, 700), (700, 300)] {
+        let config = DetectionConfig {
+            sections_x: Some(7),
+            section_height: 20,
+            ..DetectionConfig::default()
+        };
+        let grid = compute_pass_grid(
+            vec![255; (width * height) as usize],
+            width,
+            height,
+            Some(config),
+        )
+        .unwrap();
+        assert_eq!(grid.len(), (height / 20) as usize);
+        assert!(grid.iter().all(|row| row.len() == 7));
+    }
+}
+
+#[test]
+fn slice_api_matches_the_owned_api() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+
+    let borrowed: &[u8] = &img;
+    let from_slice =
+        detect_barcode_regions_slice(borrowed, width, height, Some(small_image_config())).unwrap();
+    let from_vec = detect_barcode_regions(
+        img.clone(),
+        width,
+        height,
+        Some(small_image_config()),
+        Orientation::Horizontal,
+        None,
+    )
+    .unwrap();
+    assert_eq!(from_slice.len(), 1);
+    assert_eq!(
+        from_slice.iter().map(bounds).collect::<Vec<_>>(),
+        from_vec.iter().map(bounds).collect::<Vec<_>>()
+    );
+
+    assert!(matches!(
+        detect_barcode_regions_slice(&img[1..], width, height, None),
+        Err(DetectError::DimensionMismatch { .. })
+    ));
+}
+
+#[test]
+fn origin_shifts_every_region() {
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 100, 200), 3);
+    draw_barcode(&mut img, width, (200, 600, 350, 450), 3);
+    let detect = |origin| {
+        detect_barcode_regions_slice(
+            &img,
+            width,
+            height,
+            Some(DetectionConfig {
+                origin,
+                ..small_image_config()
+            }),
+        )
+    };
+
+    let plain = detect((0, 0)).unwrap();
+    let shifted = detect((1000, 2000)).unwrap();
+    assert_eq!(plain.len(), 2);
+    assert_eq!(shifted.len(), plain.len());
+    for (plain, shifted) in plain.iter().zip(&shifted) {
+        assert_eq!(shifted.x_start, plain.x_start + 1000);
+        assert_eq!(shifted.x_end, plain.x_end + 1000);
+        assert_eq!(shifted.y_start, plain.y_start + 2000);
+        assert_eq!(shifted.y_end, plain.y_end + 2000);
+        assert_eq!(shifted.best_scanline_y, plain.best_scanline_y + 2000);
+    }
+
+    assert!(matches!(
+        detect((u32::MAX - 100, 0)),
+        Err(DetectError::OriginOutOfRange { .. })
+    ));
+}
+
+#[test]
+fn best_scanline_skips_a_scratched_row() {
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    // A black scratch across the top half, center row included
+    for y in 150..260 {
+        let row = (y * width) as usize;
+        img[row + 200..row + 600].fill(0);
+    }
+    let img = ImageBuffer::<Luma<u8>, Vec<u8>>::from_raw(width, height, img).unwrap();
+    let region = region((200, 600, 150, 300), 1.0, 1.0);
+
+    let config = DetectionConfig {
+        best_scanline: true,
+        ..DetectionConfig::default()
+    };
+    let y = best_scanline(&img, &region, &config, &mut FftPlanner::new());
+    assert!((260..300).contains(&y), "{y}");
+}
+
+#[test]
+fn confidence_is_normalized_and_raw_magnitude_is_not() {
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 100, 200), 3);
+    draw_barcode(&mut img, width, (200, 600, 350, 450), 2);
+    let regions =
+        detect_barcode_regions_slice(&img, width, height, Some(small_image_config())).unwrap();
+
+    assert_eq!(regions.len(), 2);
+    for region in &regions {
+        assert!((0.0..=1.0).contains(&region.confidence), "{region:?}");
+        assert!(region.raw_magnitude > THRESHOLD, "{region:?}");
+    }
+    let strongest = regions
+        .iter()
+        .max_by(|a, b| a.raw_magnitude.total_cmp(&b.raw_magnitude))
+        .unwrap();
+    assert!(strongest.confidence > 0.9);
+}
+
+/// A magnitude grid drawn as text: `#` is a passing section of magnitude 50,
+/// anything else a failing one. Sections are 10px wide, bands 5px tall.
+fn grid_from_pattern(pattern: &[&str]) -> MagnitudeGrid {
+    let magnitudes: Vec<Vec<f32>> = pattern
+        .iter()
+        .map(|row| {
+            row.chars()
+                .map(|cell| if cell == '#' { 50.0 } else { 0.0 })
+                .collect()
+        })
+        .collect();
+    let sections = magnitudes[0].len() as u32;
+    MagnitudeGrid {
+        bands: (0..pattern.len() as u32)
+            .map(|band| (band * 5, 5))
+            .collect(),
+        width: sections * 10,
+        section_width: 10,
+        sections_per_width: sections,
+        scanned_width: sections * 10,
+        raw_magnitudes: magnitudes.clone(),
+        peak_frequencies: vec![vec![0.0; sections as usize]; pattern.len()],
+        magnitudes,
+    }
+}
+
+/// The regions the default `MergeStrategy::Rows` pipeline builds from `grid`.
+fn row_merged_regions(grid: &MagnitudeGrid, min_run: usize) -> Vec<BarcodeRegion> {
+    let mut regions = Vec::new();
+    for (&(y_start, band_height), magnitudes) in grid.bands.iter().zip(&grid.magnitudes) {
+        detect_regions(
+            magnitudes,
+            y_start,
+            band_height,
+            grid.section_width,
+            grid.scanned_width,
+            min_run,
+            &mut regions,
+        );
+    }
+    merge_barcode_regions(&mut regions, ConfidenceMerge::Mean);
+    merge_regions_if_y_matches(&mut regions, ConfidenceMerge::Mean);
+    regions
+}
+
+#[test]
+fn connected_components_keep_an_l_shape_whole_and_apart_from_its_neighbor() {
+    let grid = grid_from_pattern(&[
+        "#####...........",
+        "#####......#####",
+        "#####......#####",
+        "##########......",
+    ]);
+
+    // The L (left arm and foot) is one component, the block on the right another
+    let components: Vec<_> = connected_component_regions(&grid, 5)
+        .iter()
+        .map(bounds)
+        .collect();
+    assert_eq!(components, [(0, 100, 0, 20), (110, 160, 5, 15)]);
+
+    // Row merging fuses every run that shares a band into one box
+    let rows: Vec<_> = row_merged_regions(&grid, 5).iter().map(bounds).collect();
+    assert_eq!(rows, [(0, 160, 0, 20)]);
+}
+
+#[test]
+fn single_row_scanline_is_detected() {
+    let (width, height) = (800, 1);
+    let line = barcode_image((width, height), (198, 594, 0, 1), 3);
+    let regions =
+        detect_barcode_regions_slice(&line, width, height, Some(small_image_config())).unwrap();
+
+    assert_eq!(
+        regions.iter().map(bounds).collect::<Vec<_>>(),
+        [(198, 594, 0, 1)]
+    );
+}
+
+#[test]
+fn trim_drops_a_trailing_quiet_section() {
+    let mut grid = grid_from_pattern(&["#######", "#######"]);
+    for band in &mut grid.magnitudes {
+        band[6] = 5.0;
+    }
+    let mut regions = vec![region((0, 70, 0, 10), 1.0, 1.0)];
+
+    trim_quiet_sections(&mut regions, &grid, 0.5);
+    assert_eq!(bounds(&regions[0]), (0, 60, 0, 10));
+
+    // Nothing falls below a lower ratio
+    let mut regions = vec![region((0, 70, 0, 10), 1.0, 1.0)];
+    trim_quiet_sections(&mut regions, &grid, 0.1);
+    assert_eq!(bounds(&regions[0]), (0, 70, 0, 10));
+}
+
+#[test]
+fn line_offset_reaches_a_barcode_off_the_band_center() {
+    // The bars fill only the top 8 rows of the 20-row band at y = 100
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 100, 108), 3);
+    let config = DetectionConfig {
+        section_height: 20,
+        ..small_image_config()
+    };
+
+    let centered = detect_barcode_regions_slice(&img, width, height, Some(config.clone())).unwrap();
+    assert!(centered.is_empty());
+
+    let config = DetectionConfig {
+        line_offset_ratio: 0.2,
+        ..config
+    };
+    let offset = detect_barcode_regions_slice(&img, width, height, Some(config)).unwrap();
+    assert_eq!(
+        offset.iter().map(bounds).collect::<Vec<_>>(),
+        [(198, 594, 100, 120)]
+    );
+}
+
+#[test]
+fn band_coverage_follows_the_barcode_band() {
+    // The barcode fills band 20 and half of its sections
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (198, 594, 100, 105), 3);
+    let coverage = band_coverage(img, width, height, Some(small_image_config())).unwrap();
+
+    assert_eq!(coverage.len(), 120);
+    for (band, &fraction) in coverage.iter().enumerate() {
+        let expected = if band == 20 { 0.5 } else { 0.0 };
+        assert_eq!(fraction, expected, "band {band}");
+    }
+}
+
+#[test]
+fn smoothing_cleans_up_a_speckled_pass_grid() {
+    let mut grid = grid_from_pattern(&[
+        "...............",
+        ".#######.......",
+        ".#######.......",
+        ".###.###.......",
+        ".#######.....#.",
+        ".#######.......",
+        "...............",
+    ]);
+    // The gap splits the middle band into runs too short to count
+    assert_eq!(row_merged_regions(&grid, 5).len(), 2);
+
+    grid.smooth((3, 3));
+    let passed = grid.pass_grid();
+    assert!(passed[3][4], "gap filled");
+    assert!(!passed[4][13], "speck removed");
+    assert_eq!(grid.magnitudes[3][4], 50.0);
+    let regions: Vec<_> = row_merged_regions(&grid, 5).iter().map(bounds).collect();
+    assert_eq!(regions, [(10, 80, 5, 30)]);
+}
+
+#[test]
+fn rect_round_trip_and_clamping() {
+    let region = region((10, 60, 20, 45), 1.0, 1.0);
+    let rect = region.to_rect();
+    assert_eq!(
+        rect,
+        Rect {
+            x: 10,
+            y: 20,
+            width: 50,
+            height: 25
+        }
+    );
+    assert_eq!(bounds(&BarcodeRegion::from_rect(rect)), bounds(&region));
+
+    // Ends saturate instead of wrapping
+    let oversized = Rect {
+        x: u32::MAX - 5,
+        y: 7,
+        width: 100,
+        height: u32::MAX,
+    };
+    assert_eq!(
+        bounds(&BarcodeRegion::from_rect(oversized)),
+        (u32::MAX - 5, u32::MAX, 7, u32::MAX)
+    );
+
+    // An inverted span becomes a zero-sized side
+    let inverted = BarcodeRegion {
+        x_start: 30,
+        x_end: 10,
+        ..region
+    };
+    assert_eq!(inverted.to_rect().width, 0);
+}
+
+#[test]
+fn max_variance_row_recovers_a_barcode_with_a_faded_center() {
+    // One 20-row band at y = 100 whose middle rows are washed out to flat gray
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 100, 120), 3);
+    for y in 105..116 {
+        let row = (y * width) as usize;
+        img[row + 200..row + 600].fill(140);
+    }
+    let detect = |row_pick| {
+        let config = DetectionConfig {
+            row_pick,
+            section_height: 20,
+            ..small_image_config()
+        };
+        detect_barcode_regions_slice(&img, width, height, Some(config)).unwrap()
+    };
+
+    assert!(detect(RowPick::Center).is_empty());
+    assert_eq!(
+        detect(RowPick::MaxVariance)
+            .iter()
+            .map(bounds)
+            .collect::<Vec<_>>(),
+        [(198, 594, 100, 120)]
+    );
+}
+
+#[test]
+fn regions_are_bucketed_by_the_column_of_their_center() {
+    let regions: Vec<BarcodeRegion> = [
+        (10, 90),
+        (110, 190),
+        (205, 295),
+        (40, 70),
+        (250, 349),
+        (320, 380),
+    ]
+    .into_iter()
+    .map(|(x_start, x_end)| BarcodeRegion {
+        x_start,
+        x_end,
+        y_start: 0,
+        y_end: 10,
+        ..Default::default()
+    })
+    .collect();
+    let tagged = assign_columns(regions, vec![0, 100, 200, 300]);
+
+    let columns: Vec<usize> = tagged.iter().map(|&(column, _)| column).collect();
+    assert_eq!(columns, [0, 1, 2, 0, 2, NO_COLUMN]);
+    // Regions keep their order
+    assert_eq!(tagged[4].1.x_start, 250);
+}
+
+#[test]
+fn rois_report_their_own_regions_in_image_coordinates() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let rois = vec![
+        BarcodeRegion {
+            x_start: 150,
+            x_end: 650,
+            y_start: 100,
+            y_end: 350,
+            ..Default::default()
+        },
+        BarcodeRegion {
+            x_start: 0,
+            x_end: 800,
+            y_start: 400,
+            y_end: 600,
+            ..Default::default()
+        },
+    ];
+    let per_roi = detect_in_rois(img, width, height, rois, Some(small_image_config())).unwrap();
+
+    assert_eq!(per_roi.len(), 2);
+    assert_eq!(per_roi[0].len(), 1);
+    let region = &per_roi[0][0];
+    assert!((150..=282).contains(&region.x_start), "{region:?}");
+    assert!((540..=650).contains(&region.x_end), "{region:?}");
+    assert_eq!((region.y_start, region.y_end), (150, 300));
+    assert!(per_roi[1].is_empty());
+}
+
+#[test]
+fn rois_apply_region_counts_one_by_one() {
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 100, 200), 3);
+    draw_barcode(&mut img, width, (200, 600, 350, 450), 3);
+    let whole = vec![BarcodeRegion::new(0, 800, 0, 600).unwrap()];
+    let capped = DetectionConfig {
+        max_regions: Some(1),
+        ..small_image_config()
+    };
+    let per_roi = detect_in_rois(img.clone(), width, height, whole.clone(), Some(capped)).unwrap();
+    assert_eq!(per_roi[0].len(), 1);
+    assert_eq!(per_roi[0][0].y_start, 100);
+
+    let blank = vec![BarcodeRegion::new(0, 800, 500, 600).unwrap()];
+    let required = DetectionConfig {
+        min_regions: 1,
+        ..small_image_config()
+    };
+    assert!(matches!(
+        detect_in_rois(img, width, height, blank, Some(required)),
+        Err(DetectError::TooFewRegions { found: 0, min: 1 })
+    ));
+}
+
+#[test]
+fn meta_reports_the_clamped_section_count() {
+    let (width, height) = (50, 200);
+    let (_, applied) = detect_with_meta(vec![255; 50 * 200], width, height, None).unwrap();
+    assert_eq!(applied.sections_x, Some(50));
+
+    // Reusing the applied config reproduces the layout without clamping
+    let (_, warnings) =
+        detect_with_warnings(vec![255; 50 * 200], width, height, Some(applied)).unwrap();
+    assert!(warnings.is_empty(), "{warnings:?}");
+}
+
+#[test]
+fn meta_applies_region_counts() {
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 350, 450), 3);
+    draw_barcode(&mut img, width, (200, 600, 100, 200), 3);
+    let capped = DetectionConfig {
+        max_regions: Some(1),
+        ..small_image_config()
+    };
+    let (regions, applied) = detect_with_meta(img, width, height, Some(capped)).unwrap();
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].y_start, 100);
+    assert_eq!(applied.max_regions, Some(1));
+
+    let required = DetectionConfig {
+        min_regions: 1,
+        ..small_image_config()
+    };
+    assert!(matches!(
+        detect_with_meta(vec![255; 800 * 600], width, height, Some(required)),
+        Err(DetectError::TooFewRegions { found: 0, min: 1 })
+    ));
+}
+
+#[test]
+fn strip_guard_bars_narrows_only_x() {
+    let region = BarcodeRegion {
+        x_start: 100,
+        x_end: 200,
+        y_start: 10,
+        y_end: 40,
+        ..Default::default()
+    };
+    let stripped = strip_guard_bars(&region, 25);
+    assert_eq!(bounds(&stripped), (125, 175, 10, 40));
+
+    // Too narrow for guard bars on both ends: left as is
+    let narrow = BarcodeRegion {
+        x_start: 5,
+        x_end: 15,
+        y_start: 10,
+        y_end: 40,
+        ..Default::default()
+    };
+    assert_eq!(bounds(&strip_guard_bars(&narrow, 25)), (5, 15, 10, 40));
+
+    let inverted = BarcodeRegion {
+        x_start: 30,
+        x_end: 10,
+        ..narrow
+    };
+    let collapsed = strip_guard_bars(&inverted, 25);
+    assert_eq!((collapsed.x_start, collapsed.x_end), (30, 30));
+}
+
+#[test]
+fn shift_to_text_below_moves_only_y() {
+    let region = BarcodeRegion {
+        x_start: 100,
+        x_end: 200,
+        y_start: 100,
+        y_end: 150,
+        ..Default::default()
+    };
+    let text = shift_to_text_below(&region, 4, 30, 600);
+    assert_eq!(bounds(&text), (100, 200, 154, 184));
+
+    // Clamped to the bottom of the image
+    assert_eq!(
+        bounds(&shift_to_text_below(&region, 4, 46, 170)),
+        (100, 200, 154, 170)
+    );
+    assert_eq!(
+        bounds(&shift_to_text_below(&region, 40, 46, 160)),
+        (100, 200, 160, 160)
+    );
+}
+
+#[test]
+fn auto_invert_reads_light_bars_on_dark_like_dark_on_light() {
+    let (width, height) = (800, 600);
+    let dark_on_light = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let light_on_dark: Vec<u8> = dark_on_light.iter().map(|&luma| 255 - luma).collect();
+    let detect = |img: &[u8], auto_invert| {
+        let config = DetectionConfig {
+            auto_invert,
+            ..small_image_config()
+        };
+        let regions = detect_barcode_regions_slice(img, width, height, Some(config)).unwrap();
+        // Detection itself doesn't depend on polarity
+        assert_eq!(
+            regions.iter().map(bounds).collect::<Vec<_>>(),
+            [(198, 594, 150, 300)]
+        );
+        regions[0].bar_count
+    };
+
+    let expected = detect(&dark_on_light, false);
+    // Without inverting, the light spaces are counted as the bars
+    assert_ne!(detect(&light_on_dark, false), expected);
+    assert_eq!(detect(&light_on_dark, true), expected);
+    // A light background is left alone
+    assert_eq!(detect(&dark_on_light, true), expected);
+}
+
+#[test]
+fn section_cap_rejects_oversized_layouts_up_front() {
+    // 12 sections across 120 bands
+    let (width, height) = (800, 600);
+    let img = vec![255; 800 * 600];
+    let capped = |max_total_sections| DetectionConfig {
+        max_total_sections: Some(max_total_sections),
+        ..small_image_config()
+    };
+
+    assert!(matches!(
+        detect_barcode_regions_slice(&img, width, height, Some(capped(1000))),
+        Err(DetectError::TooManySections { .. })
+    ));
+    assert!(detect_barcode_regions_slice(&img, width, height, Some(capped(1440))).is_ok());
+}
+
+#[test]
+fn merges_can_be_switched_off_separately() {
+    // Two barcodes side by side across the same ten bands, a section apart
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (0, 330, 150, 200), 3);
+    draw_barcode(&mut img, width, (396, 792, 150, 200), 3);
+    let detect = |merge_horizontal, merge_vertical| {
+        let config = DetectionConfig {
+            merge_horizontal,
+            merge_vertical,
+            ..small_image_config()
+        };
+        detect_barcode_regions_slice(&img, width, height, Some(config)).unwrap()
+    };
+
+    let both = detect(true, true);
+    assert_eq!(
+        both.iter().map(bounds).collect::<Vec<_>>(),
+        [(0, 726, 150, 200)]
+    );
+
+    // Each band keeps its own box, with both runs joined; the last section
+    // takes in the quiet zone right of the bars and fails the run check
+    let horizontal_only = detect(true, false);
+    assert_eq!(horizontal_only.len(), 10);
+    assert!(horizontal_only
+        .iter()
+        .all(|region| (region.x_start, region.x_end) == (0, 726)
+            && region.y_end - region.y_start == 5));
+
+    assert_eq!(detect(false, false).len(), 20);
+}
+
+#[test]
+fn suggested_threshold_separates_barcode_from_background() {
+    let (width, height) = (800, 600);
+    let labeled = |module| {
+        let img = barcode_image((width, height), (198, 594, 150, 300), module);
+        let boxes = vec![BarcodeRegion {
+            x_start: 198,
+            x_end: 594,
+            y_start: 150,
+            y_end: 300,
+            ..Default::default()
+        }];
+        (img, width, height, boxes)
+    };
+    let samples = vec![labeled(3), labeled(2)];
+    let threshold = suggest_threshold(samples.clone(), Some(small_image_config())).unwrap();
+
+    for (img, ..) in samples {
+        let (_, raw, _) =
+            detect_with_diagnostics(img.clone(), width, height, Some(small_image_config()))
+                .unwrap();
+        for (band, row) in raw.iter().enumerate() {
+            for (section, &magnitude) in row.iter().enumerate() {
+                let inside = (30..60).contains(&band) && (3..9).contains(&section);
+                assert_eq!(
+                    magnitude > threshold,
+                    inside,
+                    "band {band} section {section}"
+                );
+            }
+        }
+
+        let config = DetectionConfig {
+            threshold,
+            ..small_image_config()
+        };
+        let regions = detect_barcode_regions_slice(&img, width, height, Some(config)).unwrap();
+        assert_eq!(
+            regions.iter().map(bounds).collect::<Vec<_>>(),
+            [(198, 594, 150, 300)]
+        );
+    }
+}
+
+#[test]
+fn region_scanline_returns_the_raw_row() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let region = BarcodeRegion {
+        x_start: 180,
+        x_end: 620,
+        y_start: 150,
+        y_end: 300,
+        ..Default::default()
+    };
+    let profile = region_scanline(img.clone(), width, height, region).unwrap();
+
+    assert_eq!(profile.len(), 440);
+    let center_row = 225 * width as usize;
+    assert_eq!(profile, img[center_row + 180..center_row + 620]);
+    // Quiet zone, then bars and spaces of 3 to 9 pixels
+    assert!(profile[..20].iter().all(|&luma| luma == 255));
+    assert_eq!(profile[20], 0);
+    let runs: Vec<usize> = profile[20..420]
+        .chunk_by(|a, b| a == b)
+        .map(<[u8]>::len)
+        .collect();
+    // The last run is cut off where the barcode ends
+    assert!(runs[..runs.len() - 1]
+        .iter()
+        .all(|run| run % 3 == 0 && *run <= 9));
+    assert!(runs.len() > 50);
+}
+
+#[test]
+fn region_scanline_reads_vertical_regions_down_a_column() {
+    let (width, height) = (800, 600);
+    let img = transposed(
+        &barcode_image((width, height), (200, 600, 150, 300), 3),
+        width,
+        height,
+    );
+    let config = DetectionConfig {
+        best_scanline: true,
+        ..small_image_config()
+    };
+    let regions = detect_barcode_regions(
+        img.clone(),
+        height,
+        width,
+        Some(config),
+        Orientation::Vertical,
+        None,
+    )
+    .unwrap();
+    assert_eq!(regions.len(), 1);
+    let region = regions[0].clone();
+    assert!(region.vertical);
+
+    let profile = region_scanline(img, height, width, region.clone()).unwrap();
+    assert_eq!(profile.len(), (region.y_end - region.y_start) as usize);
+    // Dark bars and light spaces alternate along the column
+    assert!(profile.iter().all(|&luma| luma == 0 || luma == 255));
+    assert!(profile.chunk_by(|a, b| a == b).count() > 50);
+}
+
+#[test]
+fn padded_and_unpadded_ffts_agree_on_a_non_power_of_two_width() {
+    // 66px sections, padded to 128
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (198, 594, 150, 300), 3);
+    let detect = |pad_to_power_of_two| {
+        let config = DetectionConfig {
+            pad_to_power_of_two,
+            ..small_image_config()
+        };
+        assert_eq!(
+            config.fft_len(66),
+            if pad_to_power_of_two { 128 } else { 66 }
+        );
+        detect_with_diagnostics(img.clone(), width, height, Some(config)).unwrap()
+    };
+
+    let (unpadded, unpadded_raw, _) = detect(false);
+    let (padded, padded_raw, _) = detect(true);
+    assert_eq!(
+        padded.iter().map(bounds).collect::<Vec<_>>(),
+        unpadded.iter().map(bounds).collect::<Vec<_>>()
+    );
+    // The rescaled magnitudes stay within a few percent of the unpadded ones
+    for (padded_row, unpadded_row) in padded_raw.iter().zip(&unpadded_raw) {
+        for (&padded, &unpadded) in padded_row.iter().zip(unpadded_row) {
+            assert!((padded - unpadded).abs() <= 0.05 * unpadded.max(1.0));
+        }
+    }
+}
+
+#[test]
+fn pages_are_classified_by_barcodes_and_leftover_ink() {
+    let (width, height) = (800, 600);
+    let classify = |img: &[u8]| {
+        classify_page(img.to_vec(), width, height, Some(small_image_config())).unwrap()
+    };
+    // A solid block stands in for a line of text
+    let add_text = |img: &mut [u8], y_start: u32| {
+        for y in y_start..y_start + 20 {
+            img[(y * width + 40) as usize..(y * width + 120) as usize].fill(0);
+        }
+    };
+
+    let blank = vec![255; (width * height) as usize];
+    assert_eq!(classify(&blank), PageClass::Blank);
+
+    let mut text = blank.clone();
+    add_text(&mut text, 500);
+    assert_eq!(classify(&text), PageClass::TextOnly);
+
+    let barcode = barcode_image((width, height), (200, 600, 150, 300), 3);
+    assert_eq!(classify(&barcode), PageClass::BarcodePresent);
+
+    let mut mixed = barcode.clone();
+    add_text(&mut mixed, 20);
+    assert_eq!(classify(&mixed), PageClass::Mixed);
+}
+
+#[test]
+fn debug_dump_writes_one_image_per_band() {
+    // 20 bands of 5 rows
+    let (width, height) = (800, 100);
+    let img = barcode_image((width, height), (200, 600, 20, 80), 3);
+    let dir = std::env::temp_dir().join(format!("bar-dec-dump-{}", std::process::id()));
+    let config = DetectionConfig {
+        debug_dump_dir: Some(dir.clone()),
+        ..small_image_config()
+    };
+    detect_barcode_regions_slice(&img, width, height, Some(config)).unwrap();
+
+    let mut names: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    let band = image::open(dir.join("band_0004.png")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(names.len(), 20);
+    assert_eq!(names[0], "band_0000.png");
+    assert_eq!(names[19], "band_0019.png");
+    assert_eq!((band.width(), band.height()), (800, 5));
+    assert_eq!(band.to_luma8().as_raw()[..], img[20 * 800..25 * 800]);
+}
+
+#[test]
+fn dual_polarity_finds_what_a_single_pass_finds() {
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 100, 200), 3);
+    let inverted = barcode_image((width, height), (200, 600, 350, 450), 3);
+    for y in 350..450 {
+        for x in 200..600 {
+            let index = (y * width + x) as usize;
+            img[index] = 255 - inverted[index];
+        }
+    }
+    let detect = |dual_polarity| {
+        let config = DetectionConfig {
+            dual_polarity,
+            ..small_image_config()
+        };
+        detect_barcode_regions_slice(&img, width, height, Some(config))
+            .unwrap()
+            .iter()
+            .map(bounds)
+            .collect::<Vec<_>>()
+    };
+
+    // One region each; the second pass's copies are deduplicated
+    let dual = detect(true);
+    assert_eq!(dual.len(), 2);
+    assert_eq!((dual[0].2, dual[0].3), (100, 200));
+    assert_eq!((dual[1].2, dual[1].3), (350, 450));
+    // The spectrum ignores polarity, so one pass already finds both
+    assert_eq!(detect(false), dual);
+}
+
+#[test]
+fn union_prefers_the_region_with_a_symbology_guess() {
+    let mut regions = vec![region((0, 100, 0, 50), 0.5, 50.0)];
+    let guessed = BarcodeRegion {
+        symbology_guess: Some(Symbology::Ean13),
+        ..region((10, 90, 0, 50), 0.4, 40.0)
+    };
+    let apart = region((0, 100, 200, 250), 0.5, 50.0);
+    let summary = |regions: &[BarcodeRegion]| {
+        regions
+            .iter()
+            .map(|region| (bounds(region), region.symbology_guess))
+            .collect::<Vec<_>>()
+    };
+    union_regions(&mut regions, vec![guessed, apart]);
+    let expected = [
+        ((10, 90, 0, 50), Some(Symbology::Ean13)),
+        ((0, 100, 200, 250), None),
+    ];
+    assert_eq!(summary(&regions), expected);
+
+    // A guessless overlap never replaces a guess
+    union_regions(&mut regions, vec![region((0, 100, 0, 50), 0.9, 90.0)]);
+    assert_eq!(summary(&regions), expected);
+}
+
+#[test]
+fn min_active_bins_rejects_a_pure_tone_but_not_a_barcode() {
+    // Both patterns scored as edge maps, at a low contrast that puts their
+    // magnitudes within a few percent of each other
+    let (width, height) = (800, 600);
+    let mut tone = vec![128; (width * height) as usize];
+    let mut barcode = barcode_image((width, height), (198, 594, 150, 300), 3);
+    for y in 150..300 {
+        for x in 198..594 {
+            let index = (y * width + x) as usize;
+            let phase = 2.0 * std::f32::consts::PI * x as f32 / 6.0;
+            tone[index] = (127.5 + 127.5 * phase.sin()).round() as u8;
+            barcode[index] = if barcode[index] == 0 { 107 } else { 149 };
+        }
+    }
+    let detect = |img: &[u8], min_active_bins| {
+        let config = DetectionConfig {
+            signal: SignalType::Edges,
+            threshold: 10.0,
+            min_active_bins,
+            ..small_image_config()
+        };
+        detect_barcode_regions_slice(img, width, height, Some(config)).unwrap()
+    };
+
+    let tone_regions = detect(&tone, 0);
+    let barcode_regions = detect(&barcode, 0);
+    assert_eq!(tone_regions.len(), 1);
+    assert_eq!(barcode_regions.len(), 1);
+    let ratio = tone_regions[0].raw_magnitude / barcode_regions[0].raw_magnitude;
+    assert!((0.9..1.1).contains(&ratio), "{ratio}");
+
+    assert!(detect(&tone, 3).is_empty());
+    assert_eq!(
+        detect(&barcode, 3).iter().map(bounds).collect::<Vec<_>>(),
+        [(198, 594, 150, 300)]
+    );
+}
+
+#[test]
+fn batch_keeps_ids_when_one_image_fails() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (198, 594, 150, 300), 3);
+    let images = vec![
+        ("first".to_string(), img.clone(), width, height),
+        // Too short for its dimensions
+        ("broken".to_string(), img[..1000].to_vec(), width, height),
+        ("last".to_string(), img, width, height),
+    ];
+    let results = detect_batch(images, Some(small_image_config()));
+
+    let ids: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+    assert_eq!(ids, ["first", "broken", "last"]);
+    for index in [0, 2] {
+        let regions = results[index].1.as_ref().unwrap();
+        assert_eq!(
+            regions.iter().map(bounds).collect::<Vec<_>>(),
+            [(198, 594, 150, 300)]
+        );
+    }
+    assert!(results[1].1.is_err());
+}
+
+#[test]
+fn corners_run_clockwise_from_the_top_left() {
+    let region = BarcodeRegion {
+        x_start: 10,
+        x_end: 50,
+        y_start: 20,
+        y_end: 30,
+        ..Default::default()
+    };
+    assert_eq!(region.corners(), [(10, 20), (50, 20), (50, 30), (10, 30)]);
+}
+
+#[test]
+fn gradient_signal_finds_a_barcode_under_shading() {
+    // Faint bars over a left-to-right ramp that crosses the fixed binarization level
+    let (width, height) = (800, 600);
+    let bars = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let mut img: Vec<u8> = (0..height)
+        .flat_map(|_| (0..width).map(|x| (20 + x / 4) as u8))
+        .collect();
+    for y in 150..300 {
+        for x in 200..600 {
+            let index = (y * width + x) as usize;
+            img[index] = if bars[index] == 0 {
+                img[index] - 15
+            } else {
+                img[index] + 15
+            };
+        }
+    }
+    let detect = |signal| {
+        let config = DetectionConfig {
+            signal,
+            ..small_image_config()
+        };
+        detect_barcode_regions_slice(&img, width, height, Some(config)).unwrap()
+    };
+
+    assert!(detect(SignalType::Luma).is_empty());
+    let regions = detect(SignalType::GradientX);
+    assert_eq!(regions.len(), 1);
+    let (x_start, x_end, y_start, y_end) = bounds(&regions[0]);
+    assert!(x_start >= 198 && x_end <= 600);
+    assert_eq!((y_start, y_end), (150, 300));
+}
+
+#[test]
+fn checked_region_constructor_rejects_empty_and_reversed_boxes() {
+    let region = BarcodeRegion::new(100, 200, 50, 80).unwrap();
+    assert_eq!(bounds(&region), (100, 200, 50, 80));
+    assert_eq!(region.confidence, 0.0);
+
+    for (x_start, x_end, y_start, y_end) in [
+        (200, 100, 50, 80),
+        (100, 200, 80, 50),
+        (100, 100, 50, 80),
+        (100, 200, 50, 50),
+    ] {
+        assert!(matches!(
+            BarcodeRegion::new(x_start, x_end, y_start, y_end),
+            Err(DetectError::InvalidRegion { .. })
+        ));
+    }
+}
+
+#[test]
+fn orientation_conflicts_follow_the_policy() {
+    let horizontal = vec![
+        region((100, 300, 100, 160), 0.4, 40.0),
+        region((500, 700, 100, 160), 0.4, 40.0),
+    ];
+    // The first overlaps the first horizontal region and is stronger
+    let vertical = vec![
+        region((150, 210, 80, 280), 0.6, 60.0),
+        region((100, 160, 400, 600), 0.6, 60.0),
+    ];
+    let resolve = |conflict| {
+        resolve_orientation_conflicts(horizontal.clone(), vertical.clone(), conflict)
+            .iter()
+            .map(|region| (bounds(region), region.raw_magnitude))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        resolve(OrientationConflict::KeepBoth),
+        [
+            ((100, 300, 100, 160), 40.0),
+            ((500, 700, 100, 160), 40.0),
+            ((150, 210, 80, 280), 60.0),
+            ((100, 160, 400, 600), 60.0),
+        ]
+    );
+    assert_eq!(
+        resolve(OrientationConflict::KeepStronger),
+        [
+            ((150, 210, 80, 280), 60.0),
+            ((500, 700, 100, 160), 40.0),
+            ((100, 160, 400, 600), 60.0),
+        ]
+    );
+    assert_eq!(
+        resolve(OrientationConflict::Merge),
+        [
+            ((100, 300, 80, 280), 60.0),
+            ((500, 700, 100, 160), 40.0),
+            ((100, 160, 400, 600), 60.0),
+        ]
+    );
+
+    // A weaker overlap loses to the horizontal region
+    let weaker = vec![region((150, 210, 80, 280), 0.2, 20.0)];
+    let kept = resolve_orientation_conflicts(horizontal, weaker, OrientationConflict::KeepStronger);
+    assert_eq!(
+        kept.iter().map(bounds).collect::<Vec<_>>(),
+        [(100, 300, 100, 160), (500, 700, 100, 160)]
+    );
+}
+
+/// A barcode generated on the fly: `columns` repeated down rows `rows`, white elsewhere.
+struct GeneratedBarcode {
+    width: u32,
+    height: u32,
+    columns: Vec<u8>,
+    rows: std::ops::Range<u32>,
+}
+
+impl PixelSource for GeneratedBarcode {
+    fn luma_at(&self, x: u32, y: u32) -> u8 {
+        if self.rows.contains(&y) {
+            self.columns[x as usize]
+        } else {
+            255
+        }
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+#[test]
+fn custom_pixel_source_matches_the_buffer_it_generates() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let source = GeneratedBarcode {
+        width,
+        height,
+        columns: img[150 * 800..151 * 800].to_vec(),
+        rows: 150..300,
+    };
+
+    let from_source = detect_in_source(&source, Some(small_image_config())).unwrap();
+    let from_buffer =
+        detect_barcode_regions_slice(&img, width, height, Some(small_image_config())).unwrap();
+    assert_eq!(
+        from_source.iter().map(bounds).collect::<Vec<_>>(),
+        [(198, 594, 150, 300)]
+    );
+    assert_eq!(
+        from_source.iter().map(bounds).collect::<Vec<_>>(),
+        from_buffer.iter().map(bounds).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn keeping_dc_adds_only_the_first_bin() {
+    let spectrum_of = |line: &[f32]| {
+        let mut spectrum: Vec<Complex<f32>> = line.iter().map(|&x| Complex::new(x, 0.0)).collect();
+        FftPlanner::new()
+            .plan_fft_forward(spectrum.len())
+            .process(&mut spectrum);
+        spectrum
+    };
+    let magnitude = |spectrum: &[Complex<f32>], remove_dc| {
+        let config = DetectionConfig {
+            remove_dc,
+            ..DetectionConfig::default()
+        };
+        spectrum_magnitude(spectrum, &config)
+    };
+
+    // A zero-mean gradient line has nothing in bin 0, so keeping it changes nothing
+    let zero_mean: Vec<f32> = (0..32)
+        .map(|x| if x % 4 < 2 { 1.0 } else { -1.0 })
+        .collect();
+    let spectrum = spectrum_of(&zero_mean);
+    assert!(spectrum[0].norm() < 1e-4);
+    assert!((magnitude(&spectrum, false) - magnitude(&spectrum, true)).abs() < 1e-3);
+
+    // Shifted off zero, bin 0 holds the sum and is counted only without DC removal
+    let shifted: Vec<f32> = zero_mean.iter().map(|x| x + 0.5).collect();
+    let spectrum = spectrum_of(&shifted);
+    assert!((spectrum[0].re - 16.0).abs() < 1e-4);
+    let dc = magnitude(&spectrum, false) - magnitude(&spectrum, true);
+    assert!((dc - 16.0).abs() < 1e-3, "{dc}");
+}
+
+#[test]
+fn default_config_reproduces_the_constants() {
+    let config = DetectionConfig::default();
+    assert_eq!(config.threshold, THRESHOLD);
+    assert_eq!(config.consecutive_threshold, CONSECUTIVE_THRESHOLD);
+    assert_eq!(config.max_white_black_width, MAX_WHITE_BLACK_WIDTH);
+    assert_eq!(config.section_height, SECTION_HEIGHT);
+    assert_eq!(config.vertical_sections, VERTICAL_SECTIONS);
+    assert_eq!(config.horizontal_sections, HORIZONTAL_SECTIONS);
+
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let detect = |config| {
+        detect_barcode_regions(
+            img.clone(),
+            width,
+            height,
+            config,
+            Orientation::Horizontal,
+            None,
+        )
+        .unwrap()
+        .iter()
+        .map(|region| (bounds(region), region.raw_magnitude))
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(detect(None), detect(Some(DetectionConfig::default())));
+}
+
+#[test]
+fn diff_returns_only_the_added_barcode() {
+    let (width, height) = (800, 600);
+    let before = barcode_image((width, height), (200, 600, 50, 150), 3);
+    let mut after = before.clone();
+    draw_barcode(&mut after, width, (200, 600, 350, 450), 3);
+
+    let added = detect_diff(
+        before.clone(),
+        after,
+        width,
+        height,
+        Some(small_image_config()),
+    )
+    .unwrap();
+    assert_eq!(
+        added.iter().map(bounds).collect::<Vec<_>>(),
+        [(198, 594, 350, 450)]
+    );
+    assert!(detect_diff(
+        before.clone(),
+        before,
+        width,
+        height,
+        Some(small_image_config())
+    )
+    .unwrap()
+    .is_empty());
+}
+
+#[test]
+fn mismatched_and_empty_buffers_are_errors() {
+    let detect = |img_data: Vec<u8>, width, height| {
+        detect_barcode_regions(img_data, width, height, None, Orientation::Horizontal, None)
+    };
+    assert!(matches!(
+        detect(vec![255; 1000], 800, 600),
+        Err(DetectError::DimensionMismatch {
+            expected: 480_000,
+            got: 1000
+        })
+    ));
+    assert!(matches!(
+        detect(Vec::new(), 0, 600),
+        Err(DetectError::EmptyImage {
+            width: 0,
+            height: 600
+        })
+    ));
+}
+
+#[test]
+fn adaptive_run_length_grows_with_noise() {
+    let config = DetectionConfig {
+        adaptive_run_cap: Some(10),
+        ..DetectionConfig::default()
+    };
+    let mut clean = vec!["...................."; 10];
+    clean[4] = "......###...........";
+    // Every fourth section passes, a quarter of the grid
+    let mut noisy = vec!["#...#...#...#...#..."; 10];
+    noisy[4] = "......###...........";
+    let clean = grid_from_pattern(&clean);
+    let noisy = grid_from_pattern(&noisy);
+
+    // 3 of 200 sections pass, so three in a row is already unlikely
+    assert_eq!(config.min_run(&clean), 3);
+    assert_eq!(row_merged_regions(&clean, 3).len(), 1);
+    // A pass rate near 1/4 takes 7 in a row, which the short run lacks
+    assert_eq!(config.min_run(&noisy), 7);
+    assert!(row_merged_regions(&noisy, 7).is_empty());
+
+    // Without a cap the fixed threshold applies
+    assert_eq!(
+        DetectionConfig::default().min_run(&clean),
+        CONSECUTIVE_THRESHOLD
+    );
+}
+
+#[test]
+fn adjusting_a_narrow_region_keeps_its_bounds_ordered() {
+    for (x_start, x_end) in [(0, 10), (5, 15), (790, 800)] {
+        let mut regions = vec![BarcodeRegion::new(x_start, x_end, 100, 150).unwrap()];
+        adjust_regions(&mut regions, 800, 600, REFERENCE_DPI, CharacterBox::Bars);
+        // Too narrow to lose the guard bars, so x is left alone
+        assert_eq!((regions[0].x_start, regions[0].x_end), (x_start, x_end));
+
+        adjust_regions(
+            &mut regions,
+            800,
+            600,
+            REFERENCE_DPI,
+            CharacterBox::TextBelow,
+        );
+        assert!(regions[0].x_start <= regions[0].x_end);
+        assert!(regions[0].y_start <= regions[0].y_end);
+    }
+}
+
+/// Records the fields of every span as `name.field = value`.
+#[cfg(feature = "tracing")]
+#[derive(Default)]
+struct SpanRecorder {
+    names: std::sync::Mutex<Vec<&'static str>>,
+    fields: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[cfg(feature = "tracing")]
+struct FieldVisitor<'a> {
+    span: &'static str,
+    fields: &'a mut Vec<String>,
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::field::Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.fields
+            .push(format!("{}.{} = {value:?}", self.span, field.name()));
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl tracing::Subscriber for SpanRecorder {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        let mut names = self.names.lock().unwrap();
+        names.push(attributes.metadata().name());
+        attributes.record(&mut FieldVisitor {
+            span: attributes.metadata().name(),
+            fields: &mut self.fields.lock().unwrap(),
+        });
+        tracing::span::Id::from_u64(names.len() as u64)
+    }
+
+    fn record(&self, span: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+        let name = self.names.lock().unwrap()[span.into_u64() as usize - 1];
+        values.record(&mut FieldVisitor {
+            span: name,
+            fields: &mut self.fields.lock().unwrap(),
+        });
+    }
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, _: &tracing::Event<'_>) {}
+
+    fn enter(&self, _: &tracing::span::Id) {}
+
+    fn exit(&self, _: &tracing::span::Id) {}
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn detection_records_its_span_fields() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let recorder = SpanRecorder::default();
+    let fields = recorder.fields.clone();
+    tracing::subscriber::with_default(recorder, || {
+        detect_barcode_regions_slice(&img, width, height, Some(small_image_config())).unwrap();
+        detect_batch(
+            vec![("page".to_string(), img.clone(), width, height)],
+            Some(small_image_config()),
+        );
+    });
+
+    let fields = fields.lock().unwrap();
+    for expected in [
+        "detect.width = 800",
+        "detect.height = 600",
+        // 12 sections across 120 bands
+        "detect.sections = 1440",
+        "detect.regions = 1",
+        "detect_batch.images = 1",
+        "detect_image.id = page",
+    ] {
+        assert!(
+            fields.iter().any(|field| field == expected),
+            "{expected} in {fields:?}"
+        );
+    }
+}
+
+#[test]
+fn thumbnail_narrower_than_the_section_count_is_scanned() {
+    // Portrait, so 60 sections are asked for across 40 columns
+    let (width, height) = (40, 600);
+    let (regions, warnings) =
+        detect_with_warnings(vec![255; 40 * 600], width, height, None).unwrap();
+    assert!(regions.is_empty());
+    assert!(warnings
+        .iter()
+        .any(|warning| warning.contains("section count clamped to 40")));
+}
+
+#[test]
+fn confident_bounds_leave_out_weak_regions() {
+    let regions = vec![
+        region((100, 300, 50, 100), 0.9, 90.0),
+        region((120, 320, 200, 260), 0.7, 70.0),
+        // A stray far off to the side
+        region((700, 720, 500, 510), 0.1, 10.0),
+    ];
+    let confident = confident_bounds(regions.clone(), 0.5).unwrap();
+    assert_eq!(bounds(&confident), (100, 320, 50, 260));
+    assert_eq!(confident.confidence, 0.9);
+
+    assert_eq!(
+        bounds(&confident_bounds(regions.clone(), 0.0).unwrap()),
+        (100, 720, 50, 510)
+    );
+    assert!(confident_bounds(regions, 0.95).is_none());
+}
+
+#[test]
+fn run_check_handles_empty_lines_and_trailing_runs() {
+    assert!(!contains_large_white_black_regions(&[], 10));
+
+    // Eleven white pixels at the very end of the line
+    let mut line = vec![0.0, 1.0, 0.0];
+    line.extend([1.0; 11]);
+    assert!(contains_large_white_black_regions(&line, 10));
+    line.pop();
+    assert!(!contains_large_white_black_regions(&line, 10));
+}
+
+#[test]
+fn pinned_output_stays_constant() {
+    // Sized like a real scan: the v1 preset splits landscape pages into
+    // 100 sections, 40px each here
+    let (width, height) = (4000, 400);
+    let img = barcode_image((width, height), (1000, 3000, 100, 300), 3);
+    let regions =
+        detect_barcode_regions_slice(&img, width, height, Some(DetectionConfig::pinned())).unwrap();
+
+    assert_eq!(regions.len(), 1);
+    let region = &regions[0];
+    assert_eq!(bounds(region), (1000, 3000, 100, 300));
+    assert_eq!(region.bar_count, 165);
+    assert_eq!(region.symbology_guess, None);
+    assert!((region.raw_magnitude - 93.403).abs() < 1e-3, "{region:?}");
+    assert!((region.confidence - 0.930_804).abs() < 1e-5, "{region:?}");
+}
+
+#[test]
+fn pinned_output_stays_constant_on_a_ragged_page() {
+    // 4001px don't divide into 100 sections and 403 rows leave a partial
+    // band; the barcode runs into both leftovers, which v1 never scanned
+    let (width, height) = (4001, 403);
+    let img = barcode_image((width, height), (2000, 4001, 300, 403), 3);
+    let regions =
+        detect_barcode_regions_slice(&img, width, height, Some(DetectionConfig::pinned())).unwrap();
+
+    assert_eq!(regions.len(), 1);
+    let region = &regions[0];
+    assert_eq!(bounds(region), (2000, 4000, 300, 400));
+    assert!(region.clipped);
+    assert_eq!(region.bar_count, 165);
+    assert!((region.raw_magnitude - 93.403).abs() < 1e-3, "{region:?}");
+    assert!((region.confidence - 0.930_803).abs() < 1e-5, "{region:?}");
+}
+
+#[test]
+fn pinned_output_keeps_nested_boxes() {
+    // Overlapping strips both see the barcode's lower half; without the
+    // dedupe step, as in v1, the second box stays nested in the first
+    let (width, height) = (4000, 400);
+    let img = barcode_image((width, height), (1000, 3000, 100, 200), 3);
+    let tiled = |config: DetectionConfig| {
+        let mut strips = [(0, 250), (150, 400)].into_iter().map(|(y_start, y_end)| {
+            let rows = (y_start * width) as usize..(y_end * width) as usize;
+            (img[rows].to_vec(), y_start)
+        });
+        detect_barcode_regions_tiled(width, height, || Ok(strips.next()), Some(config))
+            .unwrap()
+            .iter()
+            .map(bounds)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        tiled(DetectionConfig::pinned()),
+        [(1000, 3000, 100, 200), (1000, 3000, 150, 200)]
+    );
+    let deduped = DetectionConfig {
+        dedupe: true,
+        ..DetectionConfig::pinned()
+    };
+    assert_eq!(tiled(deduped), [(1000, 3000, 100, 200)]);
+}
+
+#[test]
+fn extra_scan_lines_see_past_a_scratched_center_row() {
+    // Every band's center row is wiped white
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    for y in (2..height).step_by(5) {
+        img[(y * width) as usize..((y + 1) * width) as usize].fill(255);
+    }
+    let detect = |scan_lines, scan_line_merge| {
+        let config = DetectionConfig {
+            scan_lines,
+            scan_line_merge,
+            ..small_image_config()
+        };
+        detect_barcode_regions_slice(&img, width, height, Some(config))
+            .unwrap()
+            .iter()
+            .map(bounds)
+            .collect::<Vec<_>>()
+    };
+
+    assert!(detect(1, ScanLineMerge::Mean).is_empty());
+    assert_eq!(detect(3, ScanLineMerge::Mean), [(198, 594, 150, 300)]);
+    assert_eq!(detect(3, ScanLineMerge::Max), [(198, 594, 150, 300)]);
+}
+
+#[test]
+fn magnitude_weighted_confidence_favors_the_stronger_part() {
+    let group = [
+        region((0, 100, 0, 5), 0.25, 10.0),
+        region((0, 100, 5, 10), 0.75, 30.0),
+    ];
+    let merged = merge_group(&group, ConfidenceMerge::MagnitudeWeighted).unwrap();
+    // (0.25 * 10 + 0.75 * 30) / 40
+    assert_eq!(merged.confidence, 0.625);
+    assert_eq!(
+        merge_group(&group, ConfidenceMerge::Mean)
+            .unwrap()
+            .confidence,
+        0.5
+    );
+}
+
+#[test]
+fn content_hash_changes_with_a_single_pixel() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let region = BarcodeRegion::new(198, 594, 150, 300).unwrap();
+    let hash = |img: &[u8], region: &BarcodeRegion| {
+        region_content_hash(img.to_vec(), width, height, region.clone()).unwrap()
+    };
+
+    // The same pixels elsewhere in the frame hash the same
+    let mut moved = vec![255; img.len()];
+    draw_barcode(&mut moved, width, (200, 600, 350, 500), 3);
+    let moved_region = BarcodeRegion::new(198, 594, 350, 500).unwrap();
+    assert_eq!(hash(&img, &region), hash(&moved, &moved_region));
+
+    let mut changed = img.clone();
+    changed[(200 * width + 400) as usize] ^= 0xFF;
+    assert_ne!(hash(&img, &region), hash(&changed, &region));
+    // A change outside the region doesn't matter
+    let mut outside = img.clone();
+    outside[10] = 0;
+    assert_eq!(hash(&img, &region), hash(&outside, &region));
+}
+
+#[test]
+fn vertical_pass_maps_a_rotated_barcode_back() {
+    // A 600x800 portrait page holding the 800x600 test barcode turned on its side
+    let img = transposed(
+        &barcode_image((800, 600), (200, 600, 150, 300), 3),
+        800,
+        600,
+    );
+    let detect = |orientation| {
+        detect_barcode_regions(
+            img.clone(),
+            600,
+            800,
+            Some(small_image_config()),
+            orientation,
+            None,
+        )
+        .unwrap()
+        .iter()
+        .map(bounds)
+        .collect::<Vec<_>>()
+    };
+
+    assert!(detect(Orientation::Horizontal).is_empty());
+    assert_eq!(detect(Orientation::Vertical), [(150, 300, 198, 594)]);
+}
+
+#[test]
+fn vertical_regions_shift_their_scanline_along_x() {
+    let img = transposed(
+        &barcode_image((800, 600), (200, 600, 150, 300), 3),
+        800,
+        600,
+    );
+    let detect = |origin| {
+        detect_barcode_regions(
+            img.clone(),
+            600,
+            800,
+            Some(DetectionConfig {
+                origin,
+                ..small_image_config()
+            }),
+            Orientation::Vertical,
+            None,
+        )
+        .unwrap()
+    };
+
+    let plain = detect((0, 0));
+    let shifted = detect((1000, 5000));
+    assert_eq!(shifted.len(), 1);
+    assert!(shifted[0].vertical);
+    // The scanline is a column, so it moves with the x origin
+    assert_eq!(bounds(&shifted[0]), (1150, 1300, 5198, 5594));
+    assert_eq!(shifted[0].best_scanline_y, plain[0].best_scanline_y + 1000);
+    assert!((1150..1300).contains(&shifted[0].best_scanline_y));
+}
+
+#[test]
+fn rgba_frames_match_their_luma() {
+    let (width, height) = (800, 600);
+    let luma = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let rgba: Vec<u8> = luma.iter().flat_map(|&l| [l, l, l, 255]).collect();
+    let rgb: Vec<u8> = luma.iter().flat_map(|&l| [l, l, l]).collect();
+    let expected =
+        detect_barcode_regions_slice(&luma, width, height, Some(small_image_config())).unwrap();
+
+    for (img_data, channels) in [(rgba.clone(), 4), (rgb, 3)] {
+        let regions = detect_barcode_regions_rgb(
+            img_data,
+            width,
+            height,
+            channels,
+            Some(small_image_config()),
+        )
+        .unwrap();
+        assert_eq!(
+            regions.iter().map(bounds).collect::<Vec<_>>(),
+            expected.iter().map(bounds).collect::<Vec<_>>()
+        );
+    }
+
+    assert!(matches!(
+        detect_barcode_regions_rgb(rgba.clone(), width, height, 2, None),
+        Err(DetectError::UnsupportedColorType(_))
+    ));
+    assert!(matches!(
+        detect_barcode_regions_rgb(rgba[..1000].to_vec(), width, height, 4, None),
+        Err(DetectError::DimensionMismatch { .. })
+    ));
+    // Pure red weighs in at 0.299
+    assert_eq!(interleaved_to_luma(&[255, 0, 0, 255], 4), [76]);
+}
+
+#[test]
+fn buffer_sizes_saturate_instead_of_overflowing() {
+    assert_eq!(buffer_len(800, 600, 4), 1_920_000);
+    assert_eq!(buffer_len(u32::MAX, u32::MAX, 4), usize::MAX);
+    assert!(matches!(
+        detect_barcode_regions_rgb(vec![0; 12], u32::MAX, u32::MAX, 4, None),
+        Err(DetectError::DimensionMismatch {
+            expected: usize::MAX,
+            got: 12
+        })
+    ));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn regions_round_trip_through_json() {
+    let regions = vec![
+        region((100, 300, 50, 100), 0.9, 90.0),
+        BarcodeRegion {
+            symbology_guess: Some(Symbology::Code128),
+            bar_count: 31,
+            clipped: true,
+            ..region((0, 40, 200, 260), 0.25, 20.0)
+        },
+    ];
+    let json = regions_to_json(&regions);
+    assert!(json.starts_with(r#"[{"x_start":100,"x_end":300,"y_start":50,"y_end":100,"#));
+
+    let restored: Vec<BarcodeRegion> = serde_json::from_str(&json).unwrap();
+    assert_eq!(regions_to_json(&restored), json);
+    assert_eq!(format!("{restored:?}"), format!("{regions:?}"));
+}
+
+#[test]
+fn bands_come_back_in_image_order() {
+    // Runs the same with and without the `parallel` feature
+    let (width, height) = (800, 600);
+    let mut img = vec![255; (width * height) as usize];
+    for y_start in [400, 50, 250] {
+        draw_barcode(&mut img, width, (200, 600, y_start, y_start + 100), 3);
+    }
+    let regions =
+        detect_barcode_regions_slice(&img, width, height, Some(small_image_config())).unwrap();
+    assert_eq!(
+        regions.iter().map(bounds).collect::<Vec<_>>(),
+        [
+            (198, 594, 50, 150),
+            (198, 594, 250, 350),
+            (198, 594, 400, 500)
+        ]
+    );
+}
+
+#[test]
+fn fft_plans_are_made_once_per_length() {
+    let mut planner = FftPlanner::new();
+    let mut plans = FftPlans::new(&mut planner);
+    let plan = |plans: &mut FftPlans| plans.forward(66) as *const dyn Fft<f32> as *const ();
+
+    let first = plan(&mut plans);
+    assert_eq!(plan(&mut plans), first);
+    assert_eq!(plans.forward(66).len(), 66);
+    // A trailing section of another width gets a plan of its own
+    assert_eq!(plans.forward(70).len(), 70);
+    assert_eq!(plans.plans.len(), 2);
+    assert_eq!(plan(&mut plans), first);
+}
+
+#[test]
+fn crops_follow_the_region_order_and_stay_in_bounds() {
+    let (width, height) = (8, 4);
+    let img: Vec<u8> = (0..width * height).map(|i| i as u8).collect();
+    let regions = vec![
+        BarcodeRegion::new(2, 4, 1, 3).unwrap(),
+        // Expanded past the bottom-right corner
+        BarcodeRegion::new(6, 20, 3, 10).unwrap(),
+        BarcodeRegion::new(0, 1, 0, 1).unwrap(),
+    ];
+    let crops = crop_regions(img, width, height, regions).unwrap();
+
+    assert_eq!(
+        crops,
+        [
+            (vec![10, 11, 18, 19], 2, 2),
+            (vec![30, 31], 2, 1),
+            (vec![0], 1, 1)
+        ]
+    );
+}
+
+#[test]
+fn adjust_offsets_scale_with_dpi() {
+    let barcode = BarcodeRegion::new(200, 400, 100, 150).unwrap();
+    let adjusted = |dpi| {
+        let mut regions = vec![barcode.clone()];
+        adjust_regions(&mut regions, 800, 600, dpi, CharacterBox::TextBelow);
+        bounds(&regions[0])
+    };
+
+    // The reference DPI keeps the original 25px guard bars and 50px text box
+    assert_eq!(adjusted(REFERENCE_DPI), (225, 375, 154, 200));
+    // Twice the resolution, twice the pixels for the same physical size
+    assert_eq!(adjusted(600.0), (250, 350, 158, 250));
+    assert_eq!(adjusted(150.0), (213, 387, 152, 175));
+}
+
+#[test]
+fn drawn_outlines_are_red_and_the_rest_stays_gray() {
+    let (width, height) = (20, 10);
+    let img = vec![100; 200];
+    let region = BarcodeRegion::new(2, 12, 1, 9).unwrap();
+    let rgb = draw_regions(img, width, height, vec![region], 2).unwrap();
+    let pixel = |x: u32, y: u32| {
+        let index = 3 * (y * width + x) as usize;
+        [rgb[index], rgb[index + 1], rgb[index + 2]]
+    };
+
+    assert_eq!(rgb.len(), 600);
+    for (x, y) in [(2, 1), (11, 1), (3, 2), (11, 8), (2, 5), (10, 5)] {
+        assert_eq!(pixel(x, y), [255, 0, 0], "({x}, {y})");
+    }
+    // Inside the two-pixel outline, and outside the region
+    for (x, y) in [(4, 3), (9, 6), (1, 1), (12, 5), (5, 9)] {
+        assert_eq!(pixel(x, y), [100, 100, 100], "({x}, {y})");
+    }
+}
+
+#[test]
+fn drawing_clamps_regions_past_the_image() {
+    let (width, height) = (20, 10);
+    let past_the_edge = BarcodeRegion::new(15, 40, 2, 8).unwrap();
+    let far_away = BarcodeRegion::new(u32::MAX - 5, u32::MAX, u32::MAX - 5, u32::MAX).unwrap();
+    let rgb = draw_regions(
+        vec![100; 200],
+        width,
+        height,
+        vec![past_the_edge, far_away],
+        3,
+    )
+    .unwrap();
+    let pixel = |x: u32, y: u32| {
+        let index = 3 * (y * width + x) as usize;
+        [rgb[index], rgb[index + 1], rgb[index + 2]]
+    };
+
+    // The right side of the clamped box runs along the image edge
+    for (x, y) in [(15, 2), (19, 5), (17, 4)] {
+        assert_eq!(pixel(x, y), [255, 0, 0], "({x}, {y})");
+    }
+    assert_eq!(pixel(14, 5), [100, 100, 100]);
+}
+
+#[test]
+fn filter_drops_small_and_square_regions() {
+    let regions = vec![
+        // A barcode, 4:1
+        BarcodeRegion::new(0, 200, 0, 50).unwrap(),
+        // A one-band sliver
+        BarcodeRegion::new(0, 200, 100, 105).unwrap(),
+        // Nearly square
+        BarcodeRegion::new(300, 360, 0, 55).unwrap(),
+        // Too narrow
+        BarcodeRegion::new(400, 430, 0, 10).unwrap(),
+    ];
+    let kept = filter_regions(regions.clone(), 40, 10, 1.5, f32::INFINITY);
+    assert_eq!(
+        kept.iter().map(bounds).collect::<Vec<_>>(),
+        [(0, 200, 0, 50)]
+    );
+
+    // The defaults keep everything
+    assert_eq!(filter_regions(regions, 0, 0, 0.0, f32::INFINITY).len(), 4);
+}
+
+#[test]
+fn filter_treats_empty_regions_as_zero_aspect() {
+    // `BarcodeRegion::new` refuses empty boxes, so build them directly
+    let empty = |(x_start, x_end, y_start, y_end)| BarcodeRegion {
+        x_start,
+        x_end,
+        y_start,
+        y_end,
+        ..Default::default()
+    };
+    let regions = vec![
+        empty((10, 10, 20, 20)),
+        empty((10, 10, 20, 40)),
+        empty((10, 50, 20, 20)),
+    ];
+    let aspects = |min_aspect, max_aspect| {
+        filter_regions(regions.clone(), 0, 0, min_aspect, max_aspect)
+            .iter()
+            .map(bounds)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(aspects(0.0, 0.0), [(10, 10, 20, 20), (10, 10, 20, 40)]);
+    assert_eq!(aspects(1.0, f32::INFINITY), [(10, 50, 20, 20)]);
+}
+
+#[test]
+fn otsu_binarization_reads_a_dim_scan() {
+    // Bars at 20 and spaces at 80: every pixel is below the fixed level of 128
+    let (width, height) = (800, 600);
+    let img: Vec<u8> = barcode_image((width, height), (200, 600, 150, 300), 3)
+        .iter()
+        .map(|&luma| if luma == 0 { 20 } else { 80 })
+        .collect();
+    let detect = |binarization| {
+        let config = DetectionConfig {
+            binarization,
+            ..small_image_config()
+        };
+        detect_barcode_regions_slice(&img, width, height, Some(config))
+            .unwrap()
+            .iter()
+            .map(bounds)
+            .collect::<Vec<_>>()
+    };
+
+    assert!(detect(Binarization::Fixed).is_empty());
+    assert_eq!(detect(Binarization::Otsu), [(198, 594, 150, 300)]);
+    assert_eq!(otsu_threshold(&[20, 80, 20, 80]), 20);
+}
+
+#[test]
+fn otsu_binarization_carries_over_to_verify_and_bar_count() {
+    // Spaces at 60 and bars at 20, so the fixed cutoff sees a black row
+    let (width, height) = (800, 600);
+    let img: Vec<u8> = barcode_image((width, height), (200, 600, 150, 300), 3)
+        .iter()
+        .map(|&luma| if luma == 0 { 20 } else { 60 })
+        .collect();
+    let config = DetectionConfig {
+        binarization: Binarization::Otsu,
+        verify: true,
+        ..small_image_config()
+    };
+
+    let regions = detect_barcode_regions_slice(&img, width, height, Some(config)).unwrap();
+    assert_eq!(
+        regions.iter().map(bounds).collect::<Vec<_>>(),
+        [(198, 594, 150, 300)]
+    );
+    assert!(regions[0].bar_count > 0);
+}
+
+#[test]
+fn peak_frequency_band_keeps_only_barcode_like_spacing() {
+    // 3px bars and spaces, a period of 6px across 66px sections
+    let (width, height) = (800, 600);
+    let mut img = vec![255; (width * height) as usize];
+    for y in 150..300 {
+        for x in 198..594 {
+            if x % 6 < 3 {
+                img[(y * width + x) as usize] = 0;
+            }
+        }
+    }
+    let frequencies =
+        section_peak_frequencies(img.clone(), width, height, Some(small_image_config())).unwrap();
+    assert_eq!(frequencies[40][3..9], [1.0 / 6.0; 6]);
+    assert_eq!(frequencies[40][0], 0.0);
+
+    let detect = |min_freq, max_freq| {
+        let config = DetectionConfig {
+            min_freq,
+            max_freq,
+            ..small_image_config()
+        };
+        detect_barcode_regions_slice(&img, width, height, Some(config))
+            .unwrap()
+            .len()
+    };
+    assert_eq!(detect(0.1, 0.2), 1);
+    assert_eq!(detect(0.2, 0.5), 0);
+}
+
+#[test]
+fn gray_image_entry_point_matches_the_buffer_api() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let gray = GrayImage::from_raw(width, height, img.clone()).unwrap();
+
+    let from_image = detect_barcode_regions_image(&gray, Some(small_image_config())).unwrap();
+    let from_buffer =
+        detect_barcode_regions_slice(&img, width, height, Some(small_image_config())).unwrap();
+    assert_eq!(
+        from_image.iter().map(bounds).collect::<Vec<_>>(),
+        [(198, 594, 150, 300)]
+    );
+    assert_eq!(
+        from_image.iter().map(bounds).collect::<Vec<_>>(),
+        from_buffer.iter().map(bounds).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn overlap_merge_joins_boxes_from_neighboring_bands() {
+    let regions = vec![
+        region((10, 110, 0, 20), 0.5, 50.0),
+        // Shifted 5px down: IoU 3/5
+        region((10, 110, 5, 25), 0.5, 50.0),
+        region((300, 400, 0, 20), 0.5, 50.0),
+    ];
+    let merged = |min_iou| {
+        let mut regions = regions.clone();
+        merge_overlapping_regions(&mut regions, min_iou, ConfidenceMerge::Mean);
+        regions.iter().map(bounds).collect::<Vec<_>>()
+    };
+
+    assert_eq!(merged(0.5), [(10, 110, 0, 25), (300, 400, 0, 20)]);
+    assert_eq!(merged(0.7).len(), 3);
+}
+
+#[test]
+fn dedupe_drops_nested_boxes_and_merges_near_copies() {
+    let deduped = |regions: &[BarcodeRegion]| {
+        let mut regions = regions.to_vec();
+        dedupe_regions(&mut regions, ConfidenceMerge::Mean);
+        regions.iter().map(bounds).collect::<Vec<_>>()
+    };
+
+    let outer = region((0, 100, 0, 50), 0.5, 50.0);
+    let inner = region((20, 40, 10, 30), 0.5, 50.0);
+    assert_eq!(deduped(&[inner.clone(), outer.clone()]), [(0, 100, 0, 50)]);
+
+    // 95% overlap is one barcode, 80% is two
+    let near_copy = region((0, 100, 0, 52), 0.5, 50.0);
+    assert_eq!(deduped(&[outer.clone(), near_copy]), [(0, 100, 0, 52)]);
+    let shifted = region((0, 100, 5, 55), 0.5, 50.0);
+    assert_eq!(deduped(&[outer, shifted]).len(), 2);
+}
+
+#[test]
+fn regions_report_bar_count_and_module_width() {
+    // 6px bars and spaces, a bar every 12px over 432px. Elements count as two
+    // modules on average, so that's a 3px module
+    let (width, height) = (720, 600);
+    let mut img = vec![255; (width * height) as usize];
+    for y in 150..300 {
+        for x in 144..576 {
+            if x % 12 < 6 {
+                img[(y * width + x) as usize] = 0;
+            }
+        }
+    }
+    // 72px sections hold six whole periods
+    let config = DetectionConfig {
+        sections_x: Some(10),
+        ..DetectionConfig::default()
+    };
+    let regions = detect_barcode_regions_slice(&img, width, height, Some(config)).unwrap();
+
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].bar_count, 36);
+    assert!((regions[0].estimated_module_width - 3.0).abs() < 1e-4);
+}
+
+#[test]
+fn leftover_columns_join_the_last_section() {
+    // 1001 columns in 60 sections of 16px leave columns 960..1001 over, and
+    // the only bars are drawn there
+    let (width, height) = (1001, 600);
+    let img = barcode_image((width, height), (960, 1001, 150, 300), 3);
+    let detect = |trailing_columns| {
+        let config = DetectionConfig {
+            trailing_columns,
+            sections_x: Some(60),
+            consecutive_threshold: 1,
+            // The widened last section starts with 16 white columns
+            max_white_black_width: 20,
+            // Sums over 16px lines are small next to the default threshold
+            threshold: 10.0,
+            ..DetectionConfig::default()
+        };
+        detect_barcode_regions_slice(&img, width, height, Some(config)).unwrap()
+    };
+
+    assert!(detect(TrailingColumns::Ignore).is_empty());
+    assert_eq!(
+        detect(TrailingColumns::WidenLast)
+            .iter()
+            .map(bounds)
+            .collect::<Vec<_>>(),
+        [(944, 1001, 150, 300)]
+    );
+}
+
+#[test]
+fn reused_detector_matches_one_shot_calls() {
+    let mut detector = BarcodeDetector::builder()
+        .config(small_image_config())
+        .threshold(40.0)
+        .section_height(10)
+        .build();
+    assert_eq!(detector.config().threshold, 40.0);
+    assert_eq!(detector.config().section_height, 10);
+    assert_eq!(detector.config().sections_x, Some(12));
+
+    let (width, height) = (800, 600);
+    let pages = [
+        barcode_image((width, height), (200, 600, 150, 300), 3),
+        barcode_image((width, height), (0, 400, 400, 500), 3),
+    ];
+    for page in &pages {
+        let reused = detector.detect(page, width, height).unwrap();
+        let one_shot =
+            detect_barcode_regions_slice(page, width, height, Some(detector.config().clone()))
+                .unwrap();
+        assert_eq!(reused.len(), 1);
+        assert_eq!(
+            reused.iter().map(bounds).collect::<Vec<_>>(),
+            one_shot.iter().map(bounds).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[test]
+fn double_precision_agrees_on_a_5000px_section() {
+    let (width, height) = (5000, 50);
+    let img = barcode_image((width, height), (0, 5000, 0, 50), 3);
+    let detect = |fft_precision| {
+        let config = DetectionConfig {
+            fft_precision,
+            sections_x: Some(1),
+            ..DetectionConfig::default()
+        };
+        detect_with_diagnostics(img.clone(), width, height, Some(config)).unwrap()
+    };
+
+    let (single, single_raw, _) = detect(FftPrecision::Single);
+    let (double, double_raw, _) = detect(FftPrecision::Double);
+    assert_eq!(
+        single.iter().map(bounds).collect::<Vec<_>>(),
+        double.iter().map(bounds).collect::<Vec<_>>()
+    );
+    for (single, double) in single_raw.iter().flatten().zip(double_raw.iter().flatten()) {
+        assert!(*double > THRESHOLD);
+        assert!(
+            (single - double).abs() / double < 1e-4,
+            "{single} vs {double}"
+        );
+    }
+}
+
+#[test]
+fn diagnostics_flag_the_sections_over_the_threshold() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (198, 594, 150, 300), 3);
+    let (regions, magnitudes, passed) =
+        detect_with_diagnostics(img, width, height, Some(small_image_config())).unwrap();
+
+    assert_eq!(regions.len(), 1);
+    assert_eq!((magnitudes.len(), passed.len()), (120, 120));
+    for (band, (magnitudes, passed)) in magnitudes.iter().zip(&passed).enumerate() {
+        assert_eq!(magnitudes.len(), 12);
+        for (section, (&magnitude, &passed)) in magnitudes.iter().zip(passed).enumerate() {
+            assert_eq!(passed, magnitude > THRESHOLD);
+            let inside = (30..60).contains(&band) && (3..9).contains(&section);
+            assert_eq!(passed, inside, "band {band} section {section}");
+        }
+    }
+}
+
+#[test]
+fn diagnostics_apply_region_counts_to_the_regions_only() {
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 350, 450), 3);
+    draw_barcode(&mut img, width, (200, 600, 100, 200), 3);
+    let config = DetectionConfig {
+        max_regions: Some(1),
+        ..small_image_config()
+    };
+    let (regions, _, passed) = detect_with_diagnostics(img, width, height, Some(config)).unwrap();
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].y_start, 100);
+    // The grid still covers both barcodes
+    assert!(passed[20].iter().any(|&p| p) && passed[80].iter().any(|&p| p));
+
+    let required = DetectionConfig {
+        min_regions: 1,
+        ..small_image_config()
+    };
+    assert!(matches!(
+        detect_with_diagnostics(vec![255; 800 * 600], width, height, Some(required)),
+        Err(DetectError::TooFewRegions { found: 0, min: 1 })
+    ));
+}
+
+#[test]
+fn orientations_keep_the_first_max_regions() {
+    // Two barcodes side by side, both with their bars running across
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 50, 150), 3);
+    draw_barcode(&mut img, width, (200, 600, 400, 500), 3);
+    let img = transposed(&img, width, height);
+
+    for orientation in [Orientation::Vertical, Orientation::Both] {
+        let limited = |max_regions| {
+            let config = DetectionConfig {
+                max_regions: Some(max_regions),
+                ..small_image_config()
+            };
+            detect_barcode_regions(img.clone(), height, width, Some(config), orientation, None)
+                .unwrap()
+        };
+        assert_eq!(
+            limited(1).iter().map(bounds).collect::<Vec<_>>(),
+            [(50, 150, 198, 594)]
+        );
+        assert!(limited(0).is_empty());
+    }
+}
+
+#[test]
+fn roi_leaves_out_a_printed_border() {
+    // A patterned border along the top edge looks just like bars
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (0, 800, 0, 60), 3);
+    draw_barcode(&mut img, width, (200, 600, 250, 400), 3);
+    let detect = |roi| {
+        detect_barcode_regions(
+            img.clone(),
+            width,
+            height,
+            Some(small_image_config()),
+            Orientation::Horizontal,
+            roi,
+        )
+        .unwrap()
+        .iter()
+        .map(bounds)
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(detect(None).len(), 2);
+    // Reported in image coordinates, not the ROI's
+    let inner = detect(Some((0, 100, 800, 400)));
+    assert_eq!(inner.len(), 1);
+    assert_eq!((inner[0].2, inner[0].3), (250, 400));
+    assert!(inner[0].0 >= 190 && inner[0].1 <= 610, "{inner:?}");
+}
+
+#[test]
+fn bars_box_stays_over_the_detected_barcode() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let detect = |character_box| {
+        let config = DetectionConfig {
+            character_box,
+            ..small_image_config()
+        };
+        detect_character_regions(&img, width, height, Some(config))
+            .unwrap()
+            .iter()
+            .map(bounds)
+            .collect::<Vec<_>>()
+    };
+
+    // Detected at (198, 594, 150, 300), less 25px of guard bars on each side
+    assert_eq!(detect(CharacterBox::Bars), [(223, 569, 150, 300)]);
+    // The text line starts 4px under the bars and is 46px tall
+    assert_eq!(detect(CharacterBox::TextBelow), [(223, 569, 304, 350)]);
+}
+
+#[test]
+fn csv_has_a_header_and_one_line_per_region() {
+    let regions = vec![
+        region((820, 1599, 100, 300), 0.92, 92.0),
+        region((0, 40, 5, 10), 0.5, 50.0),
+    ];
+    assert_eq!(
+        regions_to_csv(&regions),
+        "x_start,x_end,y_start,y_end,confidence\n820,1599,100,300,0.92\n0,40,5,10,0.5\n"
+    );
+    assert_eq!(
+        regions_to_csv(&[]),
+        "x_start,x_end,y_start,y_end,confidence\n"
+    );
+}
+
+#[test]
+fn skew_estimate_finds_the_tilt_of_the_bars() {
+    // The test barcode with every row shifted so the bars lean 3 degrees right
+    let (width, height) = (800, 600);
+    let upright = barcode_image((width, height), (100, 700, 150, 300), 3);
+    let slope = 3f32.to_radians().tan();
+    let mut img = vec![255; (width * height) as usize];
+    for y in 150..300 {
+        let shift = ((225.0 - y as f32) * slope).round() as i32;
+        for x in 150..650 {
+            let source = (y * width) as i32 + x - shift;
+            img[(y * width) as usize + x as usize] = upright[source as usize];
+        }
+    }
+    let region = BarcodeRegion::new(150, 650, 150, 300).unwrap();
+
+    assert_eq!(
+        estimate_skew(
+            &image_view(&img, width, height).unwrap(),
+            &region,
+            &mut FftPlanner::new()
+        ),
+        3.0
+    );
+    assert_eq!(
+        estimate_skew(
+            &image_view(&upright, width, height).unwrap(),
+            &region,
+            &mut FftPlanner::new()
+        ),
+        0.0
+    );
+
+    let config = DetectionConfig {
+        estimate_skew: true,
+        ..small_image_config()
+    };
+    let regions = detect_barcode_regions_slice(&img, width, height, Some(config.clone())).unwrap();
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].skew_degrees, 3.0);
+
+    // Transposed, the same label is turned 3 degrees counterclockwise
+    let regions = detect_barcode_regions(
+        transposed(&img, width, height),
+        height,
+        width,
+        Some(config),
+        Orientation::Vertical,
+        None,
+    )
+    .unwrap();
+    assert_eq!(regions.len(), 1);
+    assert!(regions[0].vertical);
+    assert_eq!(regions[0].skew_degrees, -3.0);
+}
+
+#[test]
+fn region_limits_stop_early_and_enforce_a_minimum() {
+    let (width, height) = (800, 600);
+    let mut img = barcode_image((width, height), (200, 600, 50, 150), 3);
+    draw_barcode(&mut img, width, (200, 600, 400, 500), 3);
+    let source = image_view(&img, width, height).unwrap();
+
+    let counting = CountingSource::new(&source);
+    let config = DetectionConfig {
+        max_regions: Some(1),
+        ..small_image_config()
+    };
+    let first = detect_in_source(&counting, Some(config)).unwrap();
+    assert_eq!(
+        first.iter().map(bounds).collect::<Vec<_>>(),
+        [(198, 594, 50, 150)]
+    );
+    // Scanning stopped between the two barcodes
+    assert!(counting.last_row.load(Ordering::Relaxed) < 400);
+
+    let config = DetectionConfig {
+        min_regions: 3,
+        ..small_image_config()
+    };
+    assert!(matches!(
+        detect_barcode_regions_slice(&img, width, height, Some(config)),
+        Err(DetectError::TooFewRegions { .. })
+    ));
+}
+
+#[test]
+fn sixteen_bit_barcode_in_the_shadows_is_detected() {
+    // Bars at 1000 and spaces at 3000 both fall to black in 8 bits
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let deep: Vec<u16> = img
+        .iter()
+        .map(|&luma| if luma == 0 { 1000 } else { 3000 })
+        .collect();
+    let shallow: Vec<u8> = deep.iter().map(|&level| (level >> 8) as u8).collect();
+
+    assert!(
+        detect_barcode_regions_slice(&shallow, width, height, Some(small_image_config()))
+            .unwrap()
+            .is_empty()
+    );
+    for threshold in [None, Some(2000)] {
+        let regions = detect_barcode_regions_u16(
+            deep.clone(),
+            width,
+            height,
+            threshold,
+            Some(small_image_config()),
+        )
+        .unwrap();
+        assert_eq!(
+            regions.iter().map(bounds).collect::<Vec<_>>(),
+            [(198, 594, 150, 300)]
+        );
+    }
+    // A level under both leaves nothing to split
+    assert!(
+        detect_barcode_regions_u16(deep, width, height, Some(600), Some(small_image_config()))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn one_sided_sum_counts_each_mirrored_bin_once() {
+    let magnitude = |line: &[f32], spectrum_sum| {
+        let mut spectrum: Vec<Complex<f32>> = line.iter().map(|&x| Complex::new(x, 0.0)).collect();
+        FftPlanner::new()
+            .plan_fft_forward(spectrum.len())
+            .process(&mut spectrum);
+        let config = DetectionConfig {
+            spectrum_sum,
+            ..DetectionConfig::default()
+        };
+        spectrum_magnitude(&spectrum, &config)
+    };
+    let line: Vec<f32> = (0..33).map(|x| if x % 5 < 2 { 0.0 } else { 1.0 }).collect();
+
+    // An odd length has no Nyquist bin, so the mirror doubles the sum exactly
+    let one_sided = magnitude(&line, SpectrumSum::OneSided);
+    let two_sided = magnitude(&line, SpectrumSum::TwoSided);
+    assert!(
+        (two_sided - 2.0 * one_sided).abs() < 1e-3,
+        "{one_sided} vs {two_sided}"
+    );
+}
+
+#[test]
+fn normalized_bounds_clamp_to_one() {
+    let region = BarcodeRegion::new(200, 800, 150, 600).unwrap();
+    assert_eq!(region.normalized(800, 600), (0.25, 1.0, 0.25, 1.0));
+
+    // Grown past the image edge by adjust_regions
+    let grown = BarcodeRegion::new(700, 900, 550, 650).unwrap();
+    assert_eq!(grown.normalized(800, 600), (0.875, 1.0, 550.0 / 600.0, 1.0));
+}
+
+#[test]
+fn detection_reports_normalized_bounds_on_request() {
+    let (width, height) = (800, 600);
+    let img = barcode_image((width, height), (200, 600, 150, 300), 3);
+    let plain =
+        detect_barcode_regions_slice(&img, width, height, Some(small_image_config())).unwrap();
+    assert_eq!(plain[0].normalized_bounds, None);
+
+    let config = DetectionConfig {
+        normalize: true,
+        ..small_image_config()
+    };
+    let regions = detect_barcode_regions_slice(&img, width, height, Some(config.clone())).unwrap();
+    assert_eq!(
+        regions[0].normalized_bounds,
+        Some(plain[0].normalized(width, height))
+    );
+
+    // Still fractions of the whole image with a ROI, and without the origin
+    let config = DetectionConfig {
+        origin: (1000, 5000),
+        ..config
+    };
+    let roi = Some((100, 100, 600, 300));
+    let regions =
+        detect_barcode_regions(img, width, height, Some(config), Orientation::Both, roi).unwrap();
+    assert_eq!(regions.len(), 1);
+    let (x_start, x_end, y_start, y_end) = bounds(&regions[0]);
+    let local = BarcodeRegion::new(x_start - 1000, x_end - 1000, y_start - 5000, y_end - 5000);
+    assert_eq!(
+        regions[0].normalized_bounds,
+        Some(local.unwrap().normalized(width, height))
+    );
+}
+
+#[test]
+fn peak_share_limits_reject_thick_lines_and_speckle() {
+    // Edge maps skip the run-width prefilter, so only the share checks stand
+    // between these patterns and a detection
+    let (width, height) = (800, 600);
+    let barcode = barcode_image((width, height), (198, 594, 150, 300), 3);
+    // One thick line per section puts most of the energy into its first
+    // bin; one dot per section spreads it evenly over all of them
+    let mut thick_lines = vec![255; (width * height) as usize];
+    let mut speckle = thick_lines.clone();
+    for y in 150..300 {
+        for x in 198..594 {
+            if x % 66 < 33 {
+                thick_lines[(y * width + x) as usize] = 0;
+            }
+            if (x + y) % 66 == 0 {
+                speckle[(y * width + x) as usize] = 0;
+            }
+        }
+    }
+    let detect = |img: &[u8], max_peak_share, min_peak_share| {
+        let config = DetectionConfig {
+            signal: SignalType::Edges,
+            max_peak_share,
+            min_peak_share,
+            ..small_image_config()
+        };
+        detect_barcode_regions_slice(img, width, height, Some(config))
+            .unwrap()
+            .len()
+    };
+
+    for img in [&barcode, &thick_lines, &speckle] {
+        assert_eq!(detect(img, 1.0, 0.0), 1);
+    }
+    assert_eq!(detect(&thick_lines, 0.5, 0.0), 0);
+    assert_eq!(detect(&speckle, 1.0, 0.1), 0);
+    assert_eq!(detect(&barcode, 0.6, 0.1), 1);
+}
+
+#[test]
+fn vertical_gap_keeps_stacked_barcodes_apart() {
+    // Barcodes on the right, 20px apart, with a text line triggering on the left between them
+    let mut pattern = vec!["..........######"; 4];
+    pattern.extend(["######.........."; 4]);
+    pattern.extend(["..........######"; 4]);
+    let grid = grid_from_pattern(&pattern);
+    let merged = |max_gap| {
+        let mut regions = row_merged_regions(&grid, 5);
+        if let Some(max_gap) = max_gap {
+            // Start over from the per-band regions
+            regions.clear();
+            for (&(y_start, band_height), magnitudes) in grid.bands.iter().zip(&grid.magnitudes) {
+                detect_regions(
+                    magnitudes,
+                    y_start,
+                    band_height,
+                    grid.section_width,
+                    grid.width,
+                    5,
+                    &mut regions,
+                );
+            }
+            merge_barcode_regions(&mut regions, ConfidenceMerge::Mean);
+            merge_regions_across_gaps(&mut regions, &grid, max_gap, ConfidenceMerge::Mean);
+        }
+        let mut boxes: Vec<_> = regions.iter().map(bounds).collect();
+        boxes.sort_by_key(|&(_, _, y_start, _)| y_start);
+        boxes
+    };
+
+    assert_eq!(merged(None), [(0, 160, 0, 60)]);
+    assert_eq!(merged(Some(30)), [(0, 160, 0, 60)]);
+    assert_eq!(
+        merged(Some(10)),
+        [(100, 160, 0, 20), (0, 60, 20, 40), (100, 160, 40, 60)]
+    );
+}
+
+#[test]
+fn hann_window_keeps_a_leaky_tone_near_its_peak() {
+    // 66 / 7 cycles don't fit the line, so without a window the tone leaks
+    // across the whole spectrum
+    let line: Vec<f32> = (0..66)
+        .map(|x| (2.0 * std::f32::consts::PI * x as f32 / 7.0).sin())
+        .collect();
+    let energy_near_peak = |window| {
+        let windowed = window_line(line.clone(), window);
+        let mut spectrum: Vec<Complex<f32>> =
+            windowed.iter().map(|&x| Complex::new(x, 0.0)).collect();
+        FftPlanner::new()
+            .plan_fft_forward(66)
+            .process(&mut spectrum);
+        let energies: Vec<f32> = spectrum[1..=33].iter().map(|c| c.norm_sqr()).collect();
+        let peak = (0..energies.len())
+            .max_by(|&a, &b| energies[a].total_cmp(&energies[b]))
+            .unwrap();
+        let near: f32 = energies[peak.saturating_sub(2)..(peak + 3).min(33)]
+            .iter()
+            .sum();
+        near / energies.iter().sum::<f32>()
+    };
+
+    let unwindowed = energy_near_peak(FftWindow::None);
+    assert!(unwindowed < 0.97, "{unwindowed}");
+    assert!(energy_near_peak(FftWindow::Hann) > 0.99);
+    assert!(energy_near_peak(FftWindow::Hamming) > 0.99);
+
+    // Windowing tapers the centered line to zero at both ends
+    let windowed = window_line(line.clone(), FftWindow::Hann);
+    assert_eq!((windowed[0], windowed[65]), (0.0, 0.0));
+    assert_eq!(window_line(line.clone(), FftWindow::None), line);
+}
+
+#[test]
+fn invalid_config_names_the_offending_field() {
+    let invalid = [
+        (
+            DetectionConfig {
+                section_height: 0,
+                ..DetectionConfig::default()
+            },
+            "section_height",
+        ),
+        (
+            DetectionConfig {
+                consecutive_threshold: 0,
+                ..DetectionConfig::default()
+            },
+            "consecutive_threshold",
+        ),
+        (
+            DetectionConfig {
+                threshold: f32::NAN,
+                ..DetectionConfig::default()
+            },
+            "threshold",
+        ),
+    ];
+    let img = vec![255; 800 * 600];
+    for (config, field) in invalid {
+        assert_eq!(config.validate().unwrap_err().field, field);
+        match detect_barcode_regions_slice(&img, 800, 600, Some(config.clone())) {
+            Err(DetectError::InvalidConfig(err)) => {
+                assert_eq!(err.field, field);
+                assert!(err.to_string().contains(field), "{err}");
+            }
+            other => panic!("{field}: {other:?}"),
+        }
+        let batch = detect_batch(
+            vec![("page".to_string(), img.clone(), 800, 600)],
+            Some(config),
+        );
+        assert!(batch[0].1.as_ref().unwrap_err().contains(field));
+    }
+    assert!(DetectionConfig::default().validate().is_ok());
+    assert!(DetectionConfig::pinned().validate().is_ok());
+}
@@ -0,0 +1,104 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+#[cfg(feature = "python")]
+use std::path::PathBuf;
+
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::ColorType;
+
+use crate::error::DetectError;
+use crate::{interleaved_to_luma, run_detection, BarcodeRegion, DetectionConfig};
+
+/// Detects barcode regions on every page of a multi-page TIFF.
+///
+/// Pages are decoded one at a time, so only a single page is held in memory
+/// while detection runs on it. The `image` crate only decodes the first page
+/// of a TIFF, so pages are read with the `tiff` crate directly; both need
+/// the `tiff` feature, which is on by default.
+///
+/// # Arguments
+///
+/// * `path` - Path to the TIFF file.
+/// * `config` - Optional detection settings applied to every page; `None`
+///   uses the defaults.
+///
+/// # Returns
+///
+/// One vector of `BarcodeRegion` per page, in page order, or a `DetectError`
+/// if the file can't be read or a page uses an unsupported pixel format.
+///
+/// # Example
+///
+/// ```no_run
+/// # use bar_dec::detect_tiff_pages;
+/// for (page, regions) in detect_tiff_pages("archive.tif", None)?.iter().enumerate() {
+///     println!("page {page}: {} barcodes", regions.len());
+/// }
+/// # Ok::<(), bar_dec::DetectError>(())
+/// ```
+pub fn detect_tiff_pages<P: AsRef<Path>>(
+    path: P,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<Vec<BarcodeRegion>>, DetectError> {
+    let config = config.unwrap_or_default();
+    config.validate()?;
+    let mut decoder = Decoder::new(BufReader::new(File::open(path)?))?;
+    let mut pages = Vec::new();
+
+    loop {
+        let (width, height) = decoder.dimensions()?;
+        let luma = read_page_luma(&mut decoder)?;
+        pages.push(run_detection(
+            &luma,
+            width,
+            height,
+            &config,
+            &mut Vec::new(),
+        )?);
+
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image()?;
+    }
+
+    Ok(pages)
+}
+
+/// Decodes the current page and converts it to 8-bit luma.
+fn read_page_luma(decoder: &mut Decoder<BufReader<File>>) -> Result<Vec<u8>, DetectError> {
+    let color_type = decoder.colortype()?;
+
+    match (color_type, decoder.read_image()?) {
+        (ColorType::Gray(8), DecodingResult::U8(data)) => Ok(data),
+        (ColorType::Gray(16), DecodingResult::U16(data)) => {
+            Ok(data.iter().map(|&v| (v >> 8) as u8).collect())
+        }
+        (ColorType::RGB(8), DecodingResult::U8(data)) => Ok(interleaved_to_luma(&data, 3)),
+        (ColorType::RGBA(8), DecodingResult::U8(data)) => Ok(interleaved_to_luma(&data, 4)),
+        (color_type, _) => Err(DetectError::UnsupportedColorType(format!("{color_type:?}"))),
+    }
+}
+
+/// Detects barcode regions on every page of a multi-page TIFF.
+///
+/// # Arguments
+///
+/// * `path` - Path to the TIFF file.
+/// * `config` - Optional detection settings; `None` uses the defaults.
+///
+/// # Returns
+///
+/// One list of `BarcodeRegion` per page, in page order.
+#[cfg(feature = "python")]
+#[pyfunction]
+#[pyo3(name = "detect_tiff_pages", signature = (path, config = None))]
+pub(crate) fn py_detect_tiff_pages(
+    path: PathBuf,
+    config: Option<DetectionConfig>,
+) -> Result<Vec<Vec<BarcodeRegion>>, DetectError> {
+    detect_tiff_pages(path, config)
+}
@@ -0,0 +1,47 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::Clamped;
+
+use crate::{buffer_len, interleaved_to_luma, DetectError};
+
+/// Detects character regions in a canvas `ImageData`, for running in the browser.
+///
+/// # Arguments
+///
+/// * `data` - The RGBA bytes of the image, as in `ImageData.data`.
+/// * `width` - The width of the image.
+/// * `height` - The height of the image.
+///
+/// # Returns
+///
+/// An array of region objects with the `BarcodeRegion` field names
+/// (`x_start`, `x_end`, `y_start`, `y_end`, ...). Throws if `data` doesn't
+/// hold `width * height * 4` bytes.
+///
+/// # Example
+///
+/// ```js
+/// const { data, width, height } = ctx.getImageData(0, 0, canvas.width, canvas.height);
+/// for (const region of detect_character_regions(data, width, height)) {
+///   ctx.strokeRect(region.x_start, region.y_start,
+///     region.x_end - region.x_start, region.y_end - region.y_start);
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn detect_character_regions(
+    data: Clamped<Vec<u8>>,
+    width: u32,
+    height: u32,
+) -> Result<JsValue, JsError> {
+    let expected = buffer_len(width, height, 4);
+    if data.len() != expected {
+        return Err(DetectError::DimensionMismatch {
+            expected,
+            got: data.len(),
+        }
+        .into());
+    }
+
+    let luma = interleaved_to_luma(&data, 4);
+    let regions = crate::detect_character_regions(&luma, width, height, None)?;
+    Ok(serde_wasm_bindgen::to_value(&regions)?)
+}